@@ -0,0 +1,309 @@
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use thiserror::Error;
+
+use super::stream::{MessageAccumulator, StreamEvent};
+use super::types::{ApiErrorBody, MessagesRequest, MessagesResponse};
+use crate::sse::SseParser;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Error)]
+pub enum AnthropicError {
+    #[error("No Anthropic API key configured")]
+    MissingApiKey,
+
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Failed to (de)serialize payload: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Stream ended before the message was complete")]
+    IncompleteStream,
+
+    #[error("Anthropic API error ({status}) {error_type}: {message}")]
+    Api {
+        status: u16,
+        error_type: String,
+        message: String,
+    },
+}
+
+/// Thin client for the Anthropic Messages API.
+#[derive(Clone)]
+pub struct AnthropicClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl AnthropicClient {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+        }
+    }
+
+    /// Points the client at a different API host, e.g. a proxy or a local mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn headers(&self) -> Result<HeaderMap, AnthropicError> {
+        let mut headers = HeaderMap::new();
+        let api_key = HeaderValue::from_str(&self.api_key)
+            .map_err(|e| AnthropicError::InvalidApiKey(e.to_string()))?;
+        headers.insert("x-api-key", api_key);
+        headers.insert("anthropic-version", HeaderValue::from_static(API_VERSION));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        Ok(headers)
+    }
+
+    pub async fn send_message(
+        &self,
+        request: &MessagesRequest,
+    ) -> Result<MessagesResponse, AnthropicError> {
+        let response = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .headers(self.headers()?)
+            .json(request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(api_error(status.as_u16(), &body));
+        }
+
+        Ok(serde_json::from_str::<MessagesResponse>(&body)?.without_unknown_blocks())
+    }
+
+    /// Sends a streaming request, invoking `on_event` for every server-sent
+    /// event and returning the assembled message once `message_stop` arrives.
+    pub async fn stream_message<F>(
+        &self,
+        request: &MessagesRequest,
+        mut on_event: F,
+    ) -> Result<MessagesResponse, AnthropicError>
+    where
+        F: FnMut(&StreamEvent),
+    {
+        let mut request = request.clone();
+        request.stream = Some(true);
+
+        let mut response = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .headers(self.headers()?)
+            .json(&request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(api_error(status.as_u16(), &body));
+        }
+
+        let mut parser = SseParser::new();
+        let mut accumulator = MessageAccumulator::new();
+
+        while let Some(chunk) = response.chunk().await? {
+            for sse in parser.push(&chunk) {
+                let event: StreamEvent = serde_json::from_str(&sse.data)?;
+                if event == StreamEvent::Unknown {
+                    continue;
+                }
+                on_event(&event);
+
+                match event {
+                    StreamEvent::Error { error } => {
+                        return Err(AnthropicError::Api {
+                            status: status.as_u16(),
+                            error_type: error.error_type,
+                            message: error.message,
+                        });
+                    }
+                    StreamEvent::MessageStop => {
+                        return accumulator.finish().ok_or(AnthropicError::IncompleteStream);
+                    }
+                    event => accumulator.apply(&event),
+                }
+            }
+        }
+
+        Err(AnthropicError::IncompleteStream)
+    }
+}
+
+fn api_error(status: u16, body: &str) -> AnthropicError {
+    match serde_json::from_str::<ApiErrorBody>(body) {
+        Ok(parsed) => AnthropicError::Api {
+            status,
+            error_type: parsed.error.error_type,
+            message: parsed.error.message,
+        },
+        Err(_) => AnthropicError::Api {
+            status,
+            error_type: "unknown_error".to_string(),
+            message: body.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::types::{ContentBlock, Message, StopReason};
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn request() -> MessagesRequest {
+        MessagesRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![Message::user("Hello")],
+            max_tokens: 256,
+            system: Some("Be brief.".to_string()),
+            temperature: Some(0.2),
+            stop_sequences: Some(vec!["###".to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_message() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", API_VERSION))
+            .and(body_partial_json(json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": 256,
+                "system": "Be brief.",
+                "temperature": 0.2,
+                "stop_sequences": ["###"],
+                "messages": [{ "role": "user", "content": "Hello" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_01",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "content": [{ "type": "text", "text": "Hi there" }],
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": { "input_tokens": 12, "output_tokens": 3 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = AnthropicClient::new("test-key").with_base_url(server.uri());
+        let response = client.send_message(&request()).await.unwrap();
+
+        assert_eq!(response.id, "msg_01");
+        assert_eq!(response.content, vec![ContentBlock::Text { text: "Hi there".to_string() }]);
+        assert_eq!(response.text(), "Hi there");
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(response.usage.output_tokens, 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_message_api_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "error",
+                "error": { "type": "authentication_error", "message": "invalid x-api-key" }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = AnthropicClient::new("bad-key").with_base_url(server.uri());
+        match client.send_message(&request()).await {
+            Err(AnthropicError::Api { status, error_type, .. }) => {
+                assert_eq!(status, 401);
+                assert_eq!(error_type, "authentication_error");
+            }
+            other => panic!("unexpected result: {:?}", other.map(|r| r.id)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_message() {
+        let server = MockServer::start().await;
+
+        let body = [
+            r#"{"type":"message_start","message":{"id":"msg_02","type":"message","role":"assistant","model":"claude-sonnet-4-5","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"message_annotation"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":", world"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":5}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]
+        .iter()
+        .map(|data| {
+            let event: serde_json::Value = serde_json::from_str(data).unwrap();
+            format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), data)
+        })
+        .collect::<String>();
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = AnthropicClient::new("test-key").with_base_url(server.uri());
+        let mut events = Vec::new();
+        let message = client
+            .stream_message(&request(), |event| events.push(event.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[2], StreamEvent::Ping);
+        assert_eq!(message.text(), "Hello, world");
+        assert_eq!(message.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(message.usage.output_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_event() {
+        let server = MockServer::start().await;
+
+        let body = "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = AnthropicClient::new("test-key").with_base_url(server.uri());
+        let result = client.stream_message(&request(), |_| {}).await;
+
+        assert!(matches!(
+            result,
+            Err(AnthropicError::Api { ref error_type, .. }) if error_type == "overloaded_error"
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::async_runtime::JoinHandle;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::secrets::SecretStore;
+
+use super::client::{AnthropicClient, AnthropicError};
+use super::stream::StreamEvent;
+use super::types::{MessagesRequest, MessagesResponse};
+
+/// Secret store key; the same account name the keyring entry always had.
+const API_KEY_SECRET: &str = "anthropic-api-key";
+
+/// Event emitted to the webview for every update of a streaming request.
+pub const STREAM_EVENT: &str = "anthropic-stream";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnthropicConfig {
+    api_key: Option<String>,
+    base_url: Option<String>,
+}
+
+pub struct AnthropicState {
+    config: Mutex<AnthropicConfig>,
+    secrets: Arc<SecretStore>,
+}
+
+impl AnthropicState {
+    pub fn new(secrets: Arc<SecretStore>) -> Self {
+        Self {
+            config: Mutex::new(AnthropicConfig {
+                api_key: None,
+                base_url: std::env::var("ANTHROPIC_BASE_URL").ok(),
+            }),
+            secrets,
+        }
+    }
+
+    /// Resolves the API key from the in-memory cache or the secret store.
+    fn api_key(&self) -> Result<Option<String>, String> {
+        let mut config = self.config.lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+        if let Some(api_key) = &config.api_key {
+            return Ok(Some(api_key.clone()));
+        }
+
+        let api_key = self.secrets.get(API_KEY_SECRET)
+            .map_err(|e| format!("Failed to access keyring: {}", e))?;
+        config.api_key = api_key.clone();
+        Ok(api_key)
+    }
+
+    /// Builds a Messages API client with the stored key. The key itself never
+    /// leaves the backend.
+    pub fn client(&self) -> Result<AnthropicClient, String> {
+        let api_key = self.api_key()?
+            .ok_or_else(|| AnthropicError::MissingApiKey.to_string())?;
+
+        let config = self.config.lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+        let client = AnthropicClient::new(api_key);
+        Ok(match &config.base_url {
+            Some(base_url) => client.with_base_url(base_url.clone()),
+            None => client,
+        })
+    }
+}
+
+/// In-flight streaming requests keyed by the request id chosen by the frontend.
+#[derive(Default)]
+pub struct StreamRegistry(pub Mutex<HashMap<String, JoinHandle<()>>>);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StreamUpdate {
+    Event { event: StreamEvent },
+    Done { message: MessagesResponse },
+    Error { message: String },
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StreamPayload {
+    pub request_id: String,
+    #[serde(flatten)]
+    pub update: StreamUpdate,
+}
+
+fn emit_stream_update(app: &AppHandle, request_id: &str, update: StreamUpdate) {
+    let payload = StreamPayload {
+        request_id: request_id.to_string(),
+        update,
+    };
+    if let Err(e) = app.emit(STREAM_EVENT, payload) {
+        eprintln!("Failed to emit stream event: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn set_api_key(api_key: String, state: State<'_, AnthropicState>) -> Result<(), String> {
+    state.secrets.set(API_KEY_SECRET, &api_key)
+        .map_err(|e| format!("Failed to store API key: {}", e))?;
+
+    let mut config = state.config.lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+    config.api_key = Some(api_key);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn has_api_key(state: State<'_, AnthropicState>) -> Result<bool, String> {
+    Ok(state.api_key()?.is_some())
+}
+
+#[tauri::command]
+pub async fn delete_api_key(state: State<'_, AnthropicState>) -> Result<(), String> {
+    state.secrets.delete(API_KEY_SECRET)
+        .map_err(|e| format!("Failed to delete API key: {}", e))?;
+
+    let mut config = state.config.lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+    config.api_key = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn send_message(
+    request: MessagesRequest,
+    state: State<'_, AnthropicState>,
+) -> Result<MessagesResponse, String> {
+    let client = state.client()?;
+    client.send_message(&request).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn stream_message(
+    request_id: String,
+    request: MessagesRequest,
+    app: AppHandle,
+    state: State<'_, AnthropicState>,
+    streams: State<'_, StreamRegistry>,
+) -> Result<(), String> {
+    let client = state.client()?;
+
+    // Hold the registry lock while spawning so the task cannot deregister
+    // itself before it has been registered.
+    let mut streams = streams.0.lock().map_err(|e| e.to_string())?;
+    if streams.contains_key(&request_id) {
+        return Err(format!("A stream with id {} is already running", request_id));
+    }
+
+    let task_app = app.clone();
+    let task_id = request_id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let result = client
+            .stream_message(&request, |event| {
+                emit_stream_update(&task_app, &task_id, StreamUpdate::Event { event: event.clone() });
+            })
+            .await;
+
+        let update = match result {
+            Ok(message) => StreamUpdate::Done { message },
+            Err(e) => StreamUpdate::Error { message: e.to_string() },
+        };
+        emit_stream_update(&task_app, &task_id, update);
+
+        if let Ok(mut streams) = task_app.state::<StreamRegistry>().0.lock() {
+            streams.remove(&task_id);
+        }
+    });
+
+    streams.insert(request_id, handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cancel_stream(
+    request_id: String,
+    app: AppHandle,
+    streams: State<'_, StreamRegistry>,
+) -> Result<(), String> {
+    let handle = streams.0.lock().map_err(|e| e.to_string())?.remove(&request_id);
+
+    if let Some(handle) = handle {
+        handle.abort();
+        emit_stream_update(&app, &request_id, StreamUpdate::Cancelled);
+    }
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod types;
+pub mod client;
+pub mod stream;
+pub mod commands;
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::types::{ApiErrorDetail, ContentBlock, MessagesResponse, StopReason};
+
+/// Events of the Messages API streaming protocol.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart {
+        message: MessagesResponse,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: MessageDelta,
+        #[serde(default)]
+        usage: Option<DeltaUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: ApiErrorDetail,
+    },
+    /// An event type this client does not know. Skipped.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta { text: String },
+    InputJsonDelta { partial_json: String },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessageDelta {
+    pub stop_reason: Option<StopReason>,
+    pub stop_sequence: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeltaUsage {
+    pub output_tokens: u32,
+}
+
+/// Folds stream events into the final message, mirroring what the
+/// non-streaming endpoint would have returned.
+#[derive(Debug, Default)]
+pub struct MessageAccumulator {
+    message: Option<MessagesResponse>,
+    partial_json: HashMap<usize, String>,
+}
+
+impl MessageAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.message = Some(message.clone());
+            }
+            StreamEvent::ContentBlockStart { index, content_block } => {
+                if let Some(message) = self.message.as_mut() {
+                    if *index >= message.content.len() {
+                        message.content.resize(*index + 1, ContentBlock::Text { text: String::new() });
+                    }
+                    message.content[*index] = content_block.clone();
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let block = self.message.as_mut().and_then(|m| m.content.get_mut(*index));
+                match (block, delta) {
+                    (Some(ContentBlock::Text { text }), ContentDelta::TextDelta { text: delta }) => {
+                        text.push_str(delta);
+                    }
+                    (Some(ContentBlock::ToolUse { .. }), ContentDelta::InputJsonDelta { partial_json }) => {
+                        self.partial_json.entry(*index).or_default().push_str(partial_json);
+                    }
+                    _ => {}
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                // Tool inputs arrive as JSON fragments and are only valid once complete.
+                let json = self.partial_json.remove(index);
+                let block = self.message.as_mut().and_then(|m| m.content.get_mut(*index));
+                if let (Some(ContentBlock::ToolUse { input, .. }), Some(json)) = (block, json) {
+                    if let Ok(value) = serde_json::from_str(&json) {
+                        *input = value;
+                    }
+                }
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(message) = self.message.as_mut() {
+                    message.stop_reason = delta.stop_reason;
+                    message.stop_sequence = delta.stop_sequence.clone();
+                    if let Some(usage) = usage {
+                        message.usage.output_tokens = usage.output_tokens;
+                    }
+                }
+            }
+            StreamEvent::MessageStop
+            | StreamEvent::Ping
+            | StreamEvent::Error { .. }
+            | StreamEvent::Unknown => {}
+        }
+    }
+
+    pub fn finish(self) -> Option<MessagesResponse> {
+        self.message.map(MessagesResponse::without_unknown_blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserialize_events() {
+        let event: StreamEvent = serde_json::from_value(json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": "Hi" }
+        }))
+        .unwrap();
+        assert_eq!(
+            event,
+            StreamEvent::ContentBlockDelta {
+                index: 0,
+                delta: ContentDelta::TextDelta { text: "Hi".to_string() }
+            }
+        );
+
+        let event: StreamEvent = serde_json::from_value(json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": { "type": "signature_delta", "signature": "abc" }
+        }))
+        .unwrap();
+        assert!(matches!(
+            event,
+            StreamEvent::ContentBlockDelta { delta: ContentDelta::Unsupported, .. }
+        ));
+
+        let event: StreamEvent = serde_json::from_value(json!({ "type": "ping" })).unwrap();
+        assert_eq!(event, StreamEvent::Ping);
+
+        let event: StreamEvent = serde_json::from_value(json!({ "type": "message_annotation", "note": 1 })).unwrap();
+        assert_eq!(event, StreamEvent::Unknown);
+    }
+
+    #[test]
+    fn test_accumulate_tool_use_input() {
+        let events: Vec<StreamEvent> = serde_json::from_value(json!([
+            { "type": "message_start", "message": {
+                "id": "msg_03", "model": "claude-sonnet-4-5", "role": "assistant", "content": [],
+                "stop_reason": null, "stop_sequence": null,
+                "usage": { "input_tokens": 5, "output_tokens": 1 }
+            }},
+            { "type": "content_block_start", "index": 0,
+              "content_block": { "type": "tool_use", "id": "toolu_1", "name": "search", "input": {} } },
+            { "type": "content_block_delta", "index": 0,
+              "delta": { "type": "input_json_delta", "partial_json": "{\"query\": \"ru" } },
+            { "type": "content_block_delta", "index": 0,
+              "delta": { "type": "input_json_delta", "partial_json": "st\"}" } },
+            { "type": "content_block_stop", "index": 0 },
+            { "type": "content_block_start", "index": 1,
+              "content_block": { "type": "thinking", "thinking": "" } },
+            { "type": "content_block_delta", "index": 1,
+              "delta": { "type": "thinking_delta", "thinking": "Hmm" } },
+            { "type": "content_block_stop", "index": 1 },
+            { "type": "message_delta", "delta": { "stop_reason": "tool_use", "stop_sequence": null } }
+        ]))
+        .unwrap();
+
+        let mut accumulator = MessageAccumulator::new();
+        for event in &events {
+            accumulator.apply(event);
+        }
+        let message = accumulator.finish().unwrap();
+
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(message.tool_uses(), vec![("toolu_1", "search", &json!({ "query": "rust" }))]);
+        // The thinking block is not kept.
+        assert_eq!(message.content.len(), 1);
+    }
+}
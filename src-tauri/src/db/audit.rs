@@ -0,0 +1,364 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::mcp::store::hash;
+use crate::mcp::tool_loop::{Approval, ToolCallObserver, ToolCallRecord};
+
+/// Results longer than this are stored truncated, with the hash of the full
+/// payload.
+pub const MAX_RESULT_BYTES: usize = 8 * 1024;
+
+const DEFAULT_LIMIT: u32 = 500;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Failed to write audit export: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to serialize audit entry: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    /// The server ran the tool and reported an error.
+    ToolError,
+    /// The call never completed, e.g. the server was unreachable.
+    Failed,
+    Denied,
+}
+
+/// One recorded tool call.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub session_id: Option<String>,
+    pub server_id: String,
+    pub tool_name: String,
+    pub arguments: Value,
+    pub approval: Approval,
+    pub outcome: Outcome,
+    /// The serialized result, cut at [`MAX_RESULT_BYTES`].
+    pub result_summary: Option<String>,
+    /// SHA-256 of the full serialized result.
+    pub result_hash: Option<String>,
+    pub result_size: Option<i64>,
+    pub result_truncated: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub started_at: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuditFilter {
+    pub session_id: Option<String>,
+    pub server_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub outcome: Option<Outcome>,
+    pub limit: Option<u32>,
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    // Fixed width, so timestamps compare correctly as text.
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Unit enums are stored by their serde name.
+fn enum_text<T: Serialize>(value: T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(text)) => text,
+        _ => String::new(),
+    }
+}
+
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Persistent record of every MCP tool call.
+#[derive(Clone)]
+pub struct AuditLog {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> Result<Self, AuditError> {
+        Ok(Self::from_connection(super::open(path)?))
+    }
+
+    pub fn in_memory() -> Result<Self, AuditError> {
+        Ok(Self::from_connection(super::open_in_memory()?))
+    }
+
+    fn from_connection(conn: Connection) -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(conn)),
+        }
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record(&self, session_id: Option<&str>, call: &ToolCallRecord) -> Result<i64, AuditError> {
+        let started_at = Utc::now()
+            - chrono::Duration::from_std(call.duration).unwrap_or_else(|_| chrono::Duration::zero());
+
+        let (outcome, result, error) = match (call.approval, call.result) {
+            (Approval::Denied, Err(e)) => (Outcome::Denied, None, Some(e.to_string())),
+            (_, Err(e)) => (Outcome::Failed, None, Some(e.to_string())),
+            (_, Ok(result)) if result.is_error => (Outcome::ToolError, Some(serde_json::to_string(result)?), None),
+            (_, Ok(result)) => (Outcome::Success, Some(serde_json::to_string(result)?), None),
+        };
+
+        let summary = result.as_deref().map(|r| truncate(r, MAX_RESULT_BYTES));
+        let result_hash = result.as_deref().map(|r| hash(r.as_bytes()));
+        let result_size = result.as_ref().map(|r| r.len() as i64);
+        let truncated = match (&result, summary) {
+            (Some(full), Some(summary)) => summary.len() < full.len(),
+            _ => false,
+        };
+
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO mcp_tool_calls (session_id, server_id, tool_name, arguments, approval, outcome,
+                result_summary, result_hash, result_size, result_truncated, error, duration_ms, started_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                session_id,
+                call.server_id,
+                call.tool_name,
+                call.arguments.to_string(),
+                enum_text(call.approval),
+                enum_text(outcome),
+                summary,
+                result_hash,
+                result_size,
+                truncated,
+                error,
+                call.duration.as_millis() as i64,
+                timestamp(started_at),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Entries matching `filter`, newest first.
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, AuditError> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(session_id) = &filter.session_id {
+            conditions.push("session_id = ?");
+            values.push(Box::new(session_id.clone()));
+        }
+        if let Some(server_id) = &filter.server_id {
+            conditions.push("server_id = ?");
+            values.push(Box::new(server_id.clone()));
+        }
+        if let Some(since) = filter.since {
+            conditions.push("started_at >= ?");
+            values.push(Box::new(timestamp(since)));
+        }
+        if let Some(until) = filter.until {
+            conditions.push("started_at < ?");
+            values.push(Box::new(timestamp(until)));
+        }
+        if let Some(outcome) = filter.outcome {
+            conditions.push("outcome = ?");
+            values.push(Box::new(enum_text(outcome)));
+        }
+
+        let mut sql = String::from(
+            "SELECT id, session_id, server_id, tool_name, arguments, approval, outcome, result_summary,
+                result_hash, result_size, result_truncated, error, duration_ms, started_at
+             FROM mcp_tool_calls",
+        );
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY started_at DESC, id DESC LIMIT ?");
+        values.push(Box::new(filter.limit.unwrap_or(DEFAULT_LIMIT)));
+
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&sql)?;
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+        let entries = stmt.query_map(params.as_slice(), entry_from_row)?;
+        Ok(entries.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Writes the matching entries as JSON Lines, oldest first. Returns the
+    /// number of entries written.
+    pub fn export_jsonl(&self, filter: &AuditFilter, mut out: impl Write) -> Result<usize, AuditError> {
+        let filter = AuditFilter {
+            limit: Some(filter.limit.unwrap_or(u32::MAX)),
+            ..filter.clone()
+        };
+        let entries = self.query(&filter)?;
+        for entry in entries.iter().rev() {
+            serde_json::to_writer(&mut out, entry)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+        Ok(entries.len())
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<AuditEntry> {
+    fn json<T: serde::de::DeserializeOwned>(index: usize, value: serde_json::Result<T>) -> rusqlite::Result<T> {
+        value.map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+        })
+    }
+    let text = |index: usize| row.get::<_, String>(index);
+
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        server_id: row.get(2)?,
+        tool_name: row.get(3)?,
+        arguments: json(4, serde_json::from_str(&text(4)?))?,
+        approval: json(5, serde_json::from_value(Value::String(text(5)?)))?,
+        outcome: json(6, serde_json::from_value(Value::String(text(6)?)))?,
+        result_summary: row.get(7)?,
+        result_hash: row.get(8)?,
+        result_size: row.get(9)?,
+        result_truncated: row.get(10)?,
+        error: row.get(11)?,
+        duration_ms: row.get(12)?,
+        started_at: row.get(13)?,
+    })
+}
+
+/// Records the tool calls of one chat.
+pub struct AuditRecorder {
+    log: AuditLog,
+    session_id: Option<String>,
+}
+
+impl AuditRecorder {
+    pub fn new(log: AuditLog, session_id: Option<String>) -> Self {
+        Self { log, session_id }
+    }
+}
+
+impl ToolCallObserver for AuditRecorder {
+    fn tool_called(&self, record: &ToolCallRecord) {
+        if let Err(e) = self.log.record(self.session_id.as_deref(), record) {
+            eprintln!("Failed to record tool call {}/{}: {}", record.server_id, record.tool_name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::{CallToolResult, MCPError, ToolContent};
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn record(log: &AuditLog, session: &str, server: &str, approval: Approval, result: Result<CallToolResult, MCPError>) {
+        let arguments = json!({ "path": "/tmp/notes.txt" });
+        log.record(
+            Some(session),
+            &ToolCallRecord {
+                server_id: server,
+                tool_name: "read_file",
+                arguments: &arguments,
+                approval,
+                result: &result,
+                duration: Duration::from_millis(12),
+            },
+        )
+        .unwrap();
+    }
+
+    fn text(text: String, is_error: bool) -> Result<CallToolResult, MCPError> {
+        Ok(CallToolResult {
+            content: vec![ToolContent::Text { text }],
+            is_error,
+            structured_content: None,
+        })
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let log = AuditLog::in_memory().unwrap();
+        record(&log, "chat-1", "fs", Approval::AllowedByPolicy, text("hello".to_string(), false));
+        record(&log, "chat-1", "fs", Approval::ApprovedByUser, text("x".repeat(MAX_RESULT_BYTES * 2), true));
+        record(&log, "chat-2", "github", Approval::Denied, Err(MCPError::new("TOOL_DENIED", "rejected")));
+
+        let all = log.query(&AuditFilter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].outcome, Outcome::Denied);
+        assert_eq!(all[0].error.as_deref(), Some("TOOL_DENIED: rejected"));
+        assert_eq!(all[2].arguments, json!({ "path": "/tmp/notes.txt" }));
+
+        let chat = log.query(&AuditFilter { session_id: Some("chat-1".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(chat.len(), 2);
+
+        let errors = log.query(&AuditFilter { outcome: Some(Outcome::ToolError), ..Default::default() }).unwrap();
+        let large = &errors[0];
+        assert!(large.result_truncated);
+        assert_eq!(large.result_summary.as_ref().unwrap().len(), MAX_RESULT_BYTES);
+        let full = serde_json::to_string(&text("x".repeat(MAX_RESULT_BYTES * 2), true).unwrap()).unwrap();
+        assert_eq!(large.result_hash.as_deref(), Some(hash(full.as_bytes()).as_str()));
+        assert_eq!(large.result_size, Some(full.len() as i64));
+
+        let github = log.query(&AuditFilter { server_id: Some("github".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(github.len(), 1);
+
+        let future = log
+            .query(&AuditFilter { since: Some(Utc::now() + chrono::Duration::hours(1)), ..Default::default() })
+            .unwrap();
+        assert!(future.is_empty());
+    }
+
+    #[test]
+    fn test_export_jsonl() {
+        let log = AuditLog::in_memory().unwrap();
+        record(&log, "chat-1", "fs", Approval::NotRequired, text("one".to_string(), false));
+        record(&log, "chat-1", "fs", Approval::Inspector, text("two".to_string(), false));
+
+        let mut out = Vec::new();
+        assert_eq!(log.export_jsonl(&AuditFilter::default(), &mut out).unwrap(), 2);
+
+        let lines: Vec<AuditEntry> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].id < lines[1].id);
+        assert_eq!(lines[0].approval, Approval::NotRequired);
+        assert_eq!(lines[1].approval, Approval::Inspector);
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        assert_eq!(truncate("äöü", 3), "ä");
+        assert_eq!(truncate("abc", 10), "abc");
+    }
+}
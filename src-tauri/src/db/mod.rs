@@ -0,0 +1,31 @@
+pub mod audit;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use rusqlite::Connection;
+
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// Location of the app database in the user's data directory.
+pub fn database_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("com", "lukedesktop", "LukeDesktop")?;
+    Some(dirs.data_dir().join("chat.db"))
+}
+
+/// Opens the database and creates missing tables.
+pub fn open(path: &Path) -> rusqlite::Result<Connection> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+pub fn open_in_memory() -> rusqlite::Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
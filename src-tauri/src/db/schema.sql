@@ -28,4 +28,25 @@ CREATE TRIGGER IF NOT EXISTS update_chat_sessions_timestamp
     AFTER UPDATE ON chat_sessions
 BEGIN
     UPDATE chat_sessions SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
-END;
\ No newline at end of file
+END;
+
+-- MCP Tool Audit Log
+CREATE TABLE IF NOT EXISTS mcp_tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT,
+    server_id TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    approval TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK(outcome IN ('success', 'tool_error', 'failed', 'denied')),
+    result_summary TEXT,
+    result_hash TEXT,
+    result_size INTEGER,
+    result_truncated BOOLEAN DEFAULT FALSE,
+    error TEXT,
+    duration_ms INTEGER NOT NULL,
+    started_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_session ON mcp_tool_calls(session_id);
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_server ON mcp_tool_calls(server_id, started_at);
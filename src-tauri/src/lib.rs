@@ -3,16 +3,52 @@ use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
 #[cfg(target_os = "windows")]
 use window_vibrancy::apply_blur;
 
+mod anthropic;
+mod db;
 mod mcp;
+mod project;
+mod secrets;
+mod sse;
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use anthropic::commands::*;
+use db::audit::AuditLog;
+use mcp::approval::ApprovalBroker;
 use mcp::config::ConfigManager;
 use mcp::commands::*;
+use mcp::connections::ConnectionManager;
+use mcp::elicitation::ElicitationBroker;
+use mcp::handler::AppClientHandler;
+use mcp::health::{spawn_monitor, status_listener};
+use mcp::inspector::Inspector;
+use mcp::notifications::ActiveCalls;
+use mcp::oauth::{authorization_prompt, OAuthClients};
+use mcp::registry::ToolRegistry;
+use mcp::watcher::watch_config;
+use project::*;
+use secrets::SecretStore;
+
+/// Falls back to an in-memory log so a broken database doesn't keep the app
+/// from starting.
+fn open_audit_log() -> AuditLog {
+    let opened = db::database_path()
+        .ok_or_else(|| "no data directory".to_string())
+        .and_then(|path| AuditLog::open(&path).map_err(|e| e.to_string()));
+    opened.unwrap_or_else(|e| {
+        eprintln!("Failed to open audit log, keeping it in memory: {}", e);
+        AuditLog::in_memory().expect("Failed to create in-memory audit log")
+    })
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let secrets = Arc::new(SecretStore::detect());
+    let connection_secrets = secrets.clone();
+    let inspector_secrets = secrets.clone();
+    let oauth_secrets = secrets.clone();
+
     tauri::Builder::default()
-        .setup(|app| {
+        .setup(move |app| {
             #[cfg(target_os = "macos")]
             {
                 let window = app.get_webview_window("main").unwrap();
@@ -32,14 +68,62 @@ pub fn run() {
                     .expect("Failed to apply blur");
             }
 
+            // Both managers share the OAuth clients, so only one of them
+            // renews a server's tokens at a time.
+            let oauth = OAuthClients::new(oauth_secrets, authorization_prompt(app.handle().clone()));
+            let handle = app.handle().clone();
+            app.manage(
+                ConnectionManager::new()
+                    .with_secrets(connection_secrets)
+                    .with_status_listener(status_listener(app.handle().clone()))
+                    .with_oauth(oauth.clone())
+                    .with_handlers(Box::new(move |server| {
+                        Arc::new(AppClientHandler::new(server.id.clone(), handle.clone()))
+                    })),
+            );
+
+            app.manage(Inspector::new(
+                ConnectionManager::new()
+                    .with_secrets(inspector_secrets)
+                    .with_oauth(oauth),
+            ));
+
+            let config_path = app.state::<Mutex<ConfigManager>>()
+                .lock()
+                .map(|config| config.config_path().to_path_buf())
+                .expect("Failed to read config path");
+            match watch_config(app.handle(), &config_path) {
+                Ok(watcher) => {
+                    app.manage(watcher);
+                }
+                Err(e) => eprintln!("Failed to watch MCP config: {}", e),
+            }
+            spawn_monitor(app.handle());
+
             Ok(())
         })
+        .manage(AnthropicState::new(secrets.clone()))
+        .manage(StreamRegistry::default())
+        .manage(ApprovalBroker::default())
+        .manage(ElicitationBroker::default())
+        .manage(ActiveCalls::default())
+        .manage(ToolRegistry::default())
+        .manage(ActiveProject::default().with_secrets(secrets.clone()))
+        .manage(open_audit_log())
         .manage(Mutex::new(
-            ConfigManager::new().unwrap_or_else(|e| {
-                panic!("Failed to initialize config manager: {}", e)
-            }),
+            ConfigManager::new()
+                .and_then(|config| config.with_secrets(secrets))
+                .unwrap_or_else(|e| {
+                    panic!("Failed to initialize config manager: {}", e)
+                }),
         ))
         .invoke_handler(tauri::generate_handler![
+            set_api_key,
+            has_api_key,
+            delete_api_key,
+            send_message,
+            stream_message,
+            cancel_stream,
             get_mcp_servers,
             get_mcp_server,
             add_mcp_server,
@@ -47,7 +131,62 @@ pub fn run() {
             remove_mcp_server,
             get_default_mcp_server,
             set_default_mcp_server,
+            import_mcp_servers,
+            export_mcp_servers,
+            send_message_with_tools,
+            respond_tool_approval,
+            respond_elicitation,
+            reset_chat_tool_approvals,
+            get_mcp_tool_policies,
+            set_mcp_tool_policy,
+            get_mcp_tool_overrides,
+            set_mcp_tool_override,
+            list_registered_tools,
+            start_mcp_server,
+            stop_mcp_server,
+            sign_out_mcp_server,
+            get_mcp_server_info,
+            set_mcp_log_level,
+            list_mcp_tools,
+            call_mcp_tool,
+            list_mcp_resources,
+            list_mcp_resource_templates,
+            read_mcp_resource,
+            attach_mcp_resource,
+            subscribe_mcp_resource,
+            unsubscribe_mcp_resource,
+            list_mcp_prompts,
+            get_mcp_prompt,
+            run_mcp_slash_command,
+            get_mcp_process_state,
+            get_mcp_server_logs,
+            get_mcp_server_status,
+            query_mcp_audit_log,
+            export_mcp_audit_log,
+            inspector_connect,
+            inspector_disconnect,
+            inspector_list_capabilities,
+            inspector_call_tool,
+            inspector_read_resource,
+            get_inspector_traffic,
+            clear_inspector_traffic,
+            list_project_directories,
+            ensure_project_directory,
+            set_active_project,
+            get_project_roots,
+            set_project_roots,
+            get_project_servers,
+            set_project_servers,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Make sure no stdio MCP server outlives the app.
+                tauri::async_runtime::block_on(async {
+                    app.state::<ConnectionManager>().shutdown().await;
+                    app.state::<Inspector>().connections().shutdown().await;
+                });
+            }
+        });
 }
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::events::EventSink;
+use super::tool_loop::{Approval, ToolApprover};
+use super::types::{ApprovalMode, MCPError, ToolPolicies};
+
+pub const TOOL_APPROVAL_EVENT: &str = "mcp-tool-approval";
+
+/// A prompt nobody answers counts as a rejection.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The mode for a tool: its own entry, else the server-wide `*` entry,
+/// else ask every time.
+pub fn mode_for(policies: &ToolPolicies, server_id: &str, tool_name: &str) -> ApprovalMode {
+    policies
+        .get(server_id)
+        .and_then(|tools| tools.get(tool_name).or_else(|| tools.get("*")))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Payload of [`TOOL_APPROVAL_EVENT`]; answered via `respond_tool_approval`.
+#[derive(Debug, Serialize, Clone)]
+pub struct ApprovalRequest {
+    pub approval_id: String,
+    pub chat_id: String,
+    pub server_id: String,
+    pub tool_name: String,
+    pub arguments: Value,
+    pub mode: ApprovalMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    Reject,
+}
+
+/// Approval prompts waiting for the user, and the tools already approved for
+/// the rest of a chat.
+#[derive(Clone, Default)]
+pub struct ApprovalBroker {
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<ApprovalDecision>>>>,
+    granted: Arc<Mutex<HashSet<(String, String, String)>>>,
+}
+
+impl ApprovalBroker {
+    fn register(&self, approval_id: &str) -> oneshot::Receiver<ApprovalDecision> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(approval_id.to_string(), tx);
+        }
+        rx
+    }
+
+    fn discard(&self, approval_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(approval_id);
+        }
+    }
+
+    /// Delivers the user's answer. Returns `false` if nobody is waiting for it.
+    pub fn resolve(&self, approval_id: &str, decision: ApprovalDecision) -> bool {
+        let sender = self.pending.lock().ok().and_then(|mut p| p.remove(approval_id));
+        sender.is_some_and(|tx| tx.send(decision).is_ok())
+    }
+
+    fn is_granted(&self, chat_id: &str, server_id: &str, tool_name: &str) -> bool {
+        let key = (chat_id.to_string(), server_id.to_string(), tool_name.to_string());
+        self.granted.lock().is_ok_and(|granted| granted.contains(&key))
+    }
+
+    fn grant(&self, chat_id: &str, server_id: &str, tool_name: &str) {
+        if let Ok(mut granted) = self.granted.lock() {
+            granted.insert((chat_id.to_string(), server_id.to_string(), tool_name.to_string()));
+        }
+    }
+
+    /// Drops the once-per-chat approvals of a chat.
+    pub fn forget_chat(&self, chat_id: &str) {
+        if let Ok(mut granted) = self.granted.lock() {
+            granted.retain(|(chat, _, _)| chat != chat_id);
+        }
+    }
+}
+
+fn denied(server_id: &str, tool_name: &str, reason: &str) -> MCPError {
+    MCPError::new(
+        "TOOL_DENIED",
+        format!("The user did not allow calling {} on {}: {}", tool_name, server_id, reason),
+    )
+}
+
+/// Applies the configured policies for one chat, asking the webview where
+/// the policy says so.
+pub struct PolicyApprover {
+    events: Arc<dyn EventSink>,
+    broker: ApprovalBroker,
+    chat_id: String,
+    policies: ToolPolicies,
+}
+
+impl PolicyApprover {
+    pub fn new(
+        events: Arc<dyn EventSink>,
+        broker: ApprovalBroker,
+        chat_id: impl Into<String>,
+        policies: ToolPolicies,
+    ) -> Self {
+        Self {
+            events,
+            broker,
+            chat_id: chat_id.into(),
+            policies,
+        }
+    }
+
+    async fn ask(&self, request: ApprovalRequest) -> Result<ApprovalDecision, MCPError> {
+        let approval_id = request.approval_id.clone();
+        let (server_id, tool_name) = (request.server_id.clone(), request.tool_name.clone());
+        match ask_user(self.events.as_ref(), &self.broker, TOOL_APPROVAL_EVENT, &approval_id, request).await {
+            Ok(Some(decision)) => Ok(decision),
+            Ok(None) => Err(denied(&server_id, &tool_name, "no answer")),
+            Err(e) => Err(MCPError::new("APPROVAL_FAILED", e)),
+        }
+    }
+}
+
+/// Emits `payload` to the webview and waits for the answer to `approval_id`.
+/// `None` means nobody answered in time; a dropped prompt counts as a rejection.
+pub(super) async fn ask_user<S: Serialize>(
+    events: &dyn EventSink,
+    broker: &ApprovalBroker,
+    event: &str,
+    approval_id: &str,
+    payload: S,
+) -> Result<Option<ApprovalDecision>, String> {
+    let decision = broker.register(approval_id);
+
+    if let Err(e) = events.emit_event(event, json!(payload)) {
+        broker.discard(approval_id);
+        return Err(e);
+    }
+
+    match tokio::time::timeout(APPROVAL_TIMEOUT, decision).await {
+        Ok(Ok(decision)) => Ok(Some(decision)),
+        Ok(Err(_)) => Ok(Some(ApprovalDecision::Reject)),
+        Err(_) => {
+            broker.discard(approval_id);
+            Ok(None)
+        }
+    }
+}
+
+#[async_trait]
+impl ToolApprover for PolicyApprover {
+    async fn approve(&self, server_id: &str, tool_name: &str, arguments: &Value) -> Result<Approval, MCPError> {
+        let mode = mode_for(&self.policies, server_id, tool_name);
+        match mode {
+            ApprovalMode::AlwaysAllow => return Ok(Approval::AllowedByPolicy),
+            ApprovalMode::Deny => return Err(denied(server_id, tool_name, "denied by policy")),
+            ApprovalMode::AskOncePerChat => {
+                if self.broker.is_granted(&self.chat_id, server_id, tool_name) {
+                    return Ok(Approval::ApprovedByUser);
+                }
+            }
+            ApprovalMode::AskEveryTime => {}
+        }
+
+        let request = ApprovalRequest {
+            approval_id: Uuid::new_v4().to_string(),
+            chat_id: self.chat_id.clone(),
+            server_id: server_id.to_string(),
+            tool_name: tool_name.to_string(),
+            arguments: arguments.clone(),
+            mode,
+        };
+
+        match self.ask(request).await? {
+            ApprovalDecision::Approve => {
+                if mode == ApprovalMode::AskOncePerChat {
+                    self.broker.grant(&self.chat_id, server_id, tool_name);
+                }
+                Ok(Approval::ApprovedByUser)
+            }
+            ApprovalDecision::Reject => Err(denied(server_id, tool_name, "rejected")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::events::tests::RecordedEvents;
+    use std::collections::BTreeMap;
+
+    fn policies() -> ToolPolicies {
+        BTreeMap::from([(
+            "fs".to_string(),
+            BTreeMap::from([
+                ("read_file".to_string(), ApprovalMode::AlwaysAllow),
+                ("delete_file".to_string(), ApprovalMode::Deny),
+                ("*".to_string(), ApprovalMode::AskOncePerChat),
+            ]),
+        )])
+    }
+
+    /// Answers the next approval prompt once it shows up.
+    async fn answer(events: &RecordedEvents, broker: &ApprovalBroker, decision: ApprovalDecision) -> Value {
+        let (event, payload) = events.next().await;
+        assert_eq!(event, TOOL_APPROVAL_EVENT);
+        let approval_id = payload["approval_id"].as_str().unwrap();
+        assert!(broker.resolve(approval_id, decision));
+        payload
+    }
+
+    #[test]
+    fn test_mode_lookup() {
+        let policies = policies();
+        assert_eq!(mode_for(&policies, "fs", "read_file"), ApprovalMode::AlwaysAllow);
+        assert_eq!(mode_for(&policies, "fs", "list_dir"), ApprovalMode::AskOncePerChat);
+        assert_eq!(mode_for(&policies, "github", "create_issue"), ApprovalMode::AskEveryTime);
+    }
+
+    #[tokio::test]
+    async fn test_policy_round_trip() {
+        let events = Arc::new(RecordedEvents::default());
+        let broker = ApprovalBroker::default();
+        let approver = PolicyApprover::new(events.clone(), broker.clone(), "chat-1", policies());
+        let args = json!({ "path": "/tmp/x" });
+
+        assert_eq!(approver.approve("fs", "read_file", &args).await.unwrap(), Approval::AllowedByPolicy);
+        let error = approver.approve("fs", "delete_file", &args).await.unwrap_err();
+        assert_eq!(error.code, "TOOL_DENIED");
+        assert!(events.take().is_empty());
+
+        // Asked once per chat: the second call goes through without a prompt.
+        let (result, request) = tokio::join!(
+            approver.approve("fs", "list_dir", &args),
+            answer(&events, &broker, ApprovalDecision::Approve)
+        );
+        result.unwrap();
+        assert_eq!(request["arguments"], args);
+        assert_eq!(request["mode"], "ask_once_per_chat");
+        approver.approve("fs", "list_dir", &args).await.unwrap();
+
+        // A new chat asks again; rejecting fails the call.
+        let other_chat = PolicyApprover::new(events.clone(), broker.clone(), "chat-2", policies());
+        let (result, _) = tokio::join!(
+            other_chat.approve("fs", "list_dir", &args),
+            answer(&events, &broker, ApprovalDecision::Reject)
+        );
+        assert_eq!(result.unwrap_err().code, "TOOL_DENIED");
+    }
+}
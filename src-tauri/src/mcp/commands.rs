@@ -1,7 +1,35 @@
-use tauri::State;
-use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serde_json::{Map, Value};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+use super::approval::{ApprovalBroker, ApprovalDecision, PolicyApprover};
 use super::config::ConfigManager;
-use super::types::{MCPServer};
+use super::connections::ConnectionManager;
+use super::elicitation::{ElicitationAction, ElicitationBroker, ElicitationResponse};
+use super::health::ServerStatus;
+use super::import::{claude_desktop_config_path, ExportFormat, ImportReport};
+use super::inspector::{overview, Inspector, ServerOverview, TrafficEntry};
+use super::prompts::{aggregate, bind_arguments, parse_slash_command, render_messages, ServerPrompt};
+use super::notifications::{ActiveCalls, MessageCalls};
+use super::protocol::McpSession;
+use super::registry::{register, RegisteredTool, ToolNaming, ToolRegistry};
+use super::resources::context_blocks;
+use super::supervisor::{LogLine, ProcessState};
+use super::tool_loop::{
+    Approval, ToolCallObserver, ToolCallRecord, ToolLoop, ToolLoopResult, ToolProvider,
+    DEFAULT_MAX_ITERATIONS,
+};
+use super::types::{
+    ApprovalMode, CallToolResult, InitializeResult, LoggingLevel, MCPResource, MCPResourceTemplate,
+    MCPServer, MCPTool, ReadResourceResult, ToolOverride, ToolOverrides, ToolPolicies,
+};
+use crate::anthropic::commands::{AnthropicState, StreamRegistry};
+use crate::anthropic::types::{ContentBlock, Message, MessagesRequest};
+use crate::db::audit::{AuditEntry, AuditFilter, AuditLog, AuditRecorder};
 
 #[tauri::command]
 pub async fn get_mcp_servers(
@@ -52,7 +80,7 @@ pub async fn get_default_mcp_server(
     config: State<'_, Mutex<ConfigManager>>
 ) -> Result<Option<MCPServer>, String> {
     let config = config.lock().map_err(|e| e.to_string())?;
-    Ok(config.get_default_server().cloned())
+    Ok(config.effective_default_server())
 }
 
 #[tauri::command]
@@ -62,4 +90,599 @@ pub async fn set_default_mcp_server(
 ) -> Result<(), String> {
     let mut config = config.lock().map_err(|e| e.to_string())?;
     config.set_default_server(&id).map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+/// Imports servers from another client's config file. Without a path the
+/// local Claude Desktop config is used.
+#[tauri::command]
+pub async fn import_mcp_servers(
+    path: Option<String>,
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<ImportReport, String> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => claude_desktop_config_path()
+            .ok_or_else(|| "Claude Desktop config location unknown".to_string())?,
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut config = config.lock().map_err(|e| e.to_string())?;
+    config.import_servers(&content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_mcp_servers(
+    format: ExportFormat,
+    path: Option<String>,
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<String, String> {
+    let exported = {
+        let config = config.lock().map_err(|e| e.to_string())?;
+        config.export_servers(format).map_err(|e| e.to_string())?
+    };
+    if let Some(path) = path {
+        std::fs::write(&path, &exported).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(exported)
+}
+
+/// Runs a turn with the active servers' tools. With a `message_id`, the
+/// turn can be stopped via `cancel_stream`, and progress and log events of
+/// its tool calls carry that id.
+#[tauri::command]
+pub async fn send_message_with_tools(
+    app: AppHandle,
+    request: MessagesRequest,
+    chat_id: Option<String>,
+    message_id: Option<String>,
+    max_iterations: Option<usize>,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<ToolLoopResult, String> {
+    let client = app.state::<AnthropicState>().client()?;
+
+    let servers = active_servers(&config)?;
+    let (policies, naming) = {
+        let config = config.lock().map_err(|e| e.to_string())?;
+        let naming = ToolNaming::new(&servers, config.tool_overrides().clone());
+        (config.tool_policies().clone(), naming)
+    };
+    let registry = app.state::<ToolRegistry>();
+    let providers = connections.connect_all(&servers).await
+        .into_iter()
+        .map(|(id, session)| {
+            let provider = registry.cached_provider(&id, session);
+            (id, provider)
+        })
+        .collect();
+
+    // Without a chat id, "once per chat" approvals last for this turn only.
+    let chat_id = chat_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let recorder = AuditRecorder::new(app.state::<AuditLog>().inner().clone(), Some(chat_id.clone()));
+    let calls = app.state::<ActiveCalls>().inner().clone();
+    let tracker = MessageCalls::new(calls, chat_id.clone(), message_id.clone());
+    let approvals = app.state::<ApprovalBroker>().inner().clone();
+    let approver = PolicyApprover::new(Arc::new(app.clone()), approvals, chat_id, policies);
+
+    let tool_loop = ToolLoop::new(client, providers)
+        .with_approver(Arc::new(approver))
+        .with_observer(Arc::new(recorder))
+        .with_tracker(Arc::new(tracker))
+        .with_naming(naming)
+        .with_max_iterations(max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS));
+
+    let Some(message_id) = message_id else {
+        return tool_loop.run(request).await.map_err(|e| e.to_string());
+    };
+
+    // Aborting the task drops the loop, which cancels its pending tool calls
+    // on the servers.
+    let streams = app.state::<StreamRegistry>();
+    let (tx, rx) = oneshot::channel();
+    {
+        let mut streams = streams.0.lock().map_err(|e| e.to_string())?;
+        if streams.contains_key(&message_id) {
+            return Err(format!("A stream with id {} is already running", message_id));
+        }
+        let handle = tauri::async_runtime::spawn(async move {
+            let _ = tx.send(tool_loop.run(request).await);
+        });
+        streams.insert(message_id.clone(), handle);
+    }
+
+    let result = rx.await;
+    if let Ok(mut streams) = streams.0.lock() {
+        streams.remove(&message_id);
+    }
+    match result {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("Generation was cancelled".to_string()),
+    }
+}
+
+/// Answers a pending `mcp-tool-approval` prompt.
+#[tauri::command]
+pub async fn respond_tool_approval(
+    approval_id: String,
+    decision: ApprovalDecision,
+    approvals: State<'_, ApprovalBroker>
+) -> Result<(), String> {
+    if approvals.resolve(&approval_id, decision) {
+        Ok(())
+    } else {
+        Err(format!("No pending approval: {}", approval_id))
+    }
+}
+
+/// Answers a pending `mcp-elicitation` form. Accepted content must match
+/// the requested schema.
+#[tauri::command]
+pub async fn respond_elicitation(
+    elicitation_id: String,
+    action: ElicitationAction,
+    content: Option<Map<String, Value>>,
+    elicitations: State<'_, ElicitationBroker>
+) -> Result<(), String> {
+    elicitations.resolve(&elicitation_id, ElicitationResponse { action, content })
+}
+
+/// Drops the "once per chat" approvals of a chat, e.g. when it is deleted.
+#[tauri::command]
+pub async fn reset_chat_tool_approvals(
+    chat_id: String,
+    approvals: State<'_, ApprovalBroker>
+) -> Result<(), String> {
+    approvals.forget_chat(&chat_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_mcp_tool_policies(
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<ToolPolicies, String> {
+    let config = config.lock().map_err(|e| e.to_string())?;
+    Ok(config.tool_policies().clone())
+}
+
+/// Sets the approval mode of a tool (`*` for all tools of the server);
+/// `None` restores the default of asking every time.
+#[tauri::command]
+pub async fn set_mcp_tool_policy(
+    server_id: String,
+    tool_name: String,
+    mode: Option<ApprovalMode>,
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<(), String> {
+    let mut config = config.lock().map_err(|e| e.to_string())?;
+    config.set_tool_policy(&server_id, &tool_name, mode)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_mcp_tool_overrides(
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<ToolOverrides, String> {
+    let config = config.lock().map_err(|e| e.to_string())?;
+    Ok(config.tool_overrides().clone())
+}
+
+/// Aliases or hides a tool of a server; `None` offers it under its
+/// namespaced name again.
+#[tauri::command]
+pub async fn set_mcp_tool_override(
+    server_id: String,
+    tool_name: String,
+    tool_override: Option<ToolOverride>,
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<(), String> {
+    let mut config = config.lock().map_err(|e| e.to_string())?;
+    config.set_tool_override(&server_id, &tool_name, tool_override)
+        .map_err(|e| e.to_string())
+}
+
+/// The tools of all active servers under the names the model sees,
+/// including hidden ones.
+#[tauri::command]
+pub async fn list_registered_tools(
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>,
+    registry: State<'_, ToolRegistry>
+) -> Result<Vec<RegisteredTool>, String> {
+    let servers = active_servers(&config)?;
+    let naming = {
+        let config = config.lock().map_err(|e| e.to_string())?;
+        ToolNaming::new(&servers, config.tool_overrides().clone())
+    };
+
+    let mut listed = Vec::new();
+    for (id, session) in connections.connect_all(&servers).await {
+        let provider: Arc<dyn ToolProvider> = session;
+        match registry.list(&id, &provider).await {
+            Ok(tools) => listed.push((id, tools)),
+            Err(e) => eprintln!("Failed to list tools of MCP server {}: {}", id, e),
+        }
+    }
+    Ok(register(listed, &naming))
+}
+
+fn active_servers(config: &Mutex<ConfigManager>) -> Result<Vec<MCPServer>, String> {
+    let config = config.lock().map_err(|e| e.to_string())?;
+    Ok(config.effective_servers().into_iter().filter(|s| s.is_active).collect())
+}
+
+fn find_server(config: &Mutex<ConfigManager>, id: &str) -> Result<MCPServer, String> {
+    let config = config.lock().map_err(|e| e.to_string())?;
+    config.effective_server(id)
+        .ok_or_else(|| format!("Server not found: {}", id))
+}
+
+async fn connect_server(
+    config: &Mutex<ConfigManager>,
+    connections: &ConnectionManager,
+    id: &str
+) -> Result<Arc<McpSession>, String> {
+    let server = find_server(config, id)?;
+    connections.connect(&server).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn start_mcp_server(
+    id: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<InitializeResult, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.server_info()
+        .ok_or_else(|| format!("Server {} is not initialized", id))
+}
+
+#[tauri::command]
+pub async fn stop_mcp_server(
+    id: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<bool, String> {
+    Ok(connections.disconnect(&id).await)
+}
+
+/// Forgets the OAuth tokens of a server; the next connection signs in again.
+#[tauri::command]
+pub async fn sign_out_mcp_server(
+    id: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    connections.sign_out(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_mcp_server_info(
+    id: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<Option<InitializeResult>, String> {
+    Ok(connections.session(&id).await.and_then(|s| s.server_info()))
+}
+
+/// Sets the minimum level of log messages the server sends.
+#[tauri::command]
+pub async fn set_mcp_log_level(
+    id: String,
+    level: LoggingLevel,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.set_log_level(level).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_mcp_tools(
+    id: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<MCPTool>, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.list_tools().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn call_mcp_tool(
+    id: String,
+    name: String,
+    arguments: Value,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>,
+    audit: State<'_, AuditLog>
+) -> Result<CallToolResult, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+
+    let started = Instant::now();
+    let result = session.call_tool(&name, arguments.clone()).await;
+    AuditRecorder::new(audit.inner().clone(), None).tool_called(&ToolCallRecord {
+        server_id: &id,
+        tool_name: &name,
+        arguments: &arguments,
+        approval: Approval::NotRequired,
+        result: &result,
+        duration: started.elapsed(),
+    });
+    result.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_mcp_resources(
+    id: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<MCPResource>, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.list_resources().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_mcp_resource_templates(
+    id: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<MCPResourceTemplate>, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.list_resource_templates().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn read_mcp_resource(
+    id: String,
+    uri: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<ReadResourceResult, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.read_resource(&uri).await.map_err(|e| e.to_string())
+}
+
+/// Reads a resource and returns it as content blocks for the next user
+/// message, so its contents reach the model without a tool call.
+#[tauri::command]
+pub async fn attach_mcp_resource(
+    id: String,
+    uri: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<ContentBlock>, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    let result = session.read_resource(&uri).await.map_err(|e| e.to_string())?;
+    Ok(context_blocks(result.contents))
+}
+
+#[tauri::command]
+pub async fn subscribe_mcp_resource(
+    id: String,
+    uri: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    session.subscribe_resource(&uri).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unsubscribe_mcp_resource(
+    id: String,
+    uri: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    match connections.session(&id).await {
+        Some(session) => session.unsubscribe_resource(&uri).await.map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+async fn active_prompts(
+    config: &Mutex<ConfigManager>,
+    connections: &ConnectionManager
+) -> Result<Vec<ServerPrompt>, String> {
+    let servers = active_servers(config)?;
+    let mut prompts = Vec::new();
+    for (id, session) in connections.connect_all(&servers).await {
+        match session.list_prompts().await {
+            Ok(listed) => prompts.push((id, listed)),
+            Err(e) => eprintln!("Failed to list prompts of MCP server {}: {}", id, e),
+        }
+    }
+    Ok(aggregate(prompts))
+}
+
+#[tauri::command]
+pub async fn list_mcp_prompts(
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<ServerPrompt>, String> {
+    active_prompts(&config, &connections).await
+}
+
+#[tauri::command]
+pub async fn get_mcp_prompt(
+    id: String,
+    name: String,
+    arguments: HashMap<String, String>,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<Message>, String> {
+    let session = connect_server(&config, &connections, &id).await?;
+    let result = session.get_prompt(&name, &arguments).await.map_err(|e| e.to_string())?;
+    Ok(render_messages(result))
+}
+
+/// Resolves input such as `/review-pr 123` against the prompts of the
+/// active servers and returns the rendered messages.
+#[tauri::command]
+pub async fn run_mcp_slash_command(
+    input: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<Message>, String> {
+    let (command, args) = parse_slash_command(&input)
+        .ok_or_else(|| "Not a slash command".to_string())?;
+
+    let prompt = active_prompts(&config, &connections).await?
+        .into_iter()
+        .find(|p| p.command == command)
+        .ok_or_else(|| format!("Unknown command: /{}", command))?;
+
+    let arguments = bind_arguments(&prompt.prompt, args).map_err(|e| e.to_string())?;
+    let session = connections.session(&prompt.server_id).await
+        .ok_or_else(|| format!("Server {} is not connected", prompt.server_id))?;
+    let result = session.get_prompt(&prompt.prompt.name, &arguments).await.map_err(|e| e.to_string())?;
+    Ok(render_messages(result))
+}
+
+#[tauri::command]
+pub async fn get_mcp_process_state(
+    id: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<Option<ProcessState>, String> {
+    Ok(connections.supervisor().state(&id))
+}
+
+#[tauri::command]
+pub async fn get_mcp_server_logs(
+    id: String,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<LogLine>, String> {
+    Ok(connections.supervisor().logs(&id).unwrap_or_default())
+}
+
+/// Health of one server, or of every configured server if `id` is omitted.
+#[tauri::command]
+pub async fn get_mcp_server_status(
+    id: Option<String>,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<Vec<ServerStatus>, String> {
+    let servers = match &id {
+        Some(id) => vec![find_server(&config, id)?],
+        None => config.lock().map_err(|e| e.to_string())?.effective_servers(),
+    };
+    Ok(servers.iter().map(|server| connections.status().status_of(server)).collect())
+}
+
+/// Recorded tool calls matching `filter`, newest first.
+#[tauri::command]
+pub async fn query_mcp_audit_log(
+    filter: Option<AuditFilter>,
+    audit: State<'_, AuditLog>
+) -> Result<Vec<AuditEntry>, String> {
+    audit.query(&filter.unwrap_or_default()).map_err(|e| e.to_string())
+}
+
+/// Writes the matching tool calls to `path` as JSON Lines and returns how
+/// many were written.
+#[tauri::command]
+pub async fn export_mcp_audit_log(
+    path: PathBuf,
+    filter: Option<AuditFilter>,
+    audit: State<'_, AuditLog>
+) -> Result<usize, String> {
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    audit.export_jsonl(&filter.unwrap_or_default(), std::io::BufWriter::new(file))
+        .map_err(|e| e.to_string())
+}
+
+/// A configured server, including ones the active project leaves out.
+fn find_configured_server(config: &Mutex<ConfigManager>, id: &str) -> Result<MCPServer, String> {
+    let config = config.lock().map_err(|e| e.to_string())?;
+    config.effective_server(id)
+        .or_else(|| config.get_server(id).cloned())
+        .ok_or_else(|| format!("Server not found: {}", id))
+}
+
+async fn inspector_session(inspector: &Inspector, id: &str) -> Result<Arc<McpSession>, String> {
+    inspector.connections().session(id).await
+        .ok_or_else(|| format!("Inspector is not connected to {}", id))
+}
+
+/// Opens a fresh inspector session to a server, apart from the one chats use.
+#[tauri::command]
+pub async fn inspector_connect(
+    id: String,
+    config: State<'_, Mutex<ConfigManager>>,
+    inspector: State<'_, Inspector>
+) -> Result<ServerOverview, String> {
+    let server = find_configured_server(&config, &id)?;
+    inspector.connections().close_session(&id).await;
+    let session = inspector.connections().connect(&server).await.map_err(|e| e.to_string())?;
+    overview(&session).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn inspector_disconnect(
+    id: String,
+    inspector: State<'_, Inspector>
+) -> Result<bool, String> {
+    Ok(inspector.connections().disconnect(&id).await)
+}
+
+#[tauri::command]
+pub async fn inspector_list_capabilities(
+    id: String,
+    inspector: State<'_, Inspector>
+) -> Result<ServerOverview, String> {
+    let session = inspector_session(&inspector, &id).await?;
+    overview(&session).await.map_err(|e| e.to_string())
+}
+
+/// Calls a tool with hand-written JSON arguments, bypassing approval and
+/// schema validation. The call is still audited.
+#[tauri::command]
+pub async fn inspector_call_tool(
+    id: String,
+    name: String,
+    arguments: String,
+    inspector: State<'_, Inspector>,
+    audit: State<'_, AuditLog>
+) -> Result<CallToolResult, String> {
+    let arguments = match arguments.trim() {
+        "" => Value::Object(Map::new()),
+        raw => serde_json::from_str(raw).map_err(|e| format!("Invalid JSON arguments: {}", e))?,
+    };
+    let session = inspector_session(&inspector, &id).await?;
+
+    let started = Instant::now();
+    let result = session.call_tool(&name, arguments.clone()).await;
+    AuditRecorder::new(audit.inner().clone(), None).tool_called(&ToolCallRecord {
+        server_id: &id,
+        tool_name: &name,
+        arguments: &arguments,
+        approval: Approval::Inspector,
+        result: &result,
+        duration: started.elapsed(),
+    });
+    result.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn inspector_read_resource(
+    id: String,
+    uri: String,
+    inspector: State<'_, Inspector>
+) -> Result<ReadResourceResult, String> {
+    let session = inspector_session(&inspector, &id).await?;
+    session.read_resource(&uri).await.map_err(|e| e.to_string())
+}
+
+/// Raw JSON-RPC messages exchanged with a server in the inspector, oldest first.
+#[tauri::command]
+pub async fn get_inspector_traffic(
+    id: String,
+    inspector: State<'_, Inspector>
+) -> Result<Vec<TrafficEntry>, String> {
+    Ok(inspector.traffic().snapshot(&id))
+}
+
+#[tauri::command]
+pub async fn clear_inspector_traffic(
+    id: String,
+    inspector: State<'_, Inspector>
+) -> Result<(), String> {
+    inspector.traffic().clear(&id);
+    Ok(())
+}
@@ -1,11 +1,24 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::Local;
 use directories::ProjectDirs;
+use serde::Serialize;
 use serde_json;
 use uuid::Uuid;
 use thiserror::Error; // ← Wichtig für Fehlerbehandlung
 
-use super::types::{MCPConfig, MCPServer};
+use crate::secrets::{SecretError, SecretStore};
+
+use super::credentials;
+use super::migrations::migrate;
+use super::store::{self, FileStamp};
+use super::import::{export, plan_import, ExportFormat, ImportReport};
+use super::registry::is_valid_tool_name;
+use super::types::{
+    ApprovalMode, MCPConfig, MCPServer, MCPTransport, ProjectServers, ToolOverride, ToolOverrides, ToolPolicies,
+};
 
 #[derive(Debug, Error)] // ← Fehler-Enum mit automatischer Display-Implementierung
 pub enum ConfigError {
@@ -20,24 +33,142 @@ pub enum ConfigError {
 
     #[error("Server nicht gefunden: {0}")]
     ServerNotFound(String),
+
+    #[error("Unbekanntes Format der Server-Konfiguration")]
+    UnknownFormat,
+
+    #[error("Ungültige Konfigurationsversion: {0}")]
+    InvalidVersion(String),
+
+    #[error("Konfigurationsversion {found} ist neuer als die unterstützte Version {supported}")]
+    NewerVersion { found: u32, supported: u32 },
+
+    #[error("Konfiguration wurde extern geändert, Konflikt bei: {0}")]
+    Conflict(String),
+
+    #[error("Ungültige Konfiguration: {0}")]
+    Invalid(String),
+
+    #[error("Fehler beim Zugriff auf den Schlüsselbund: {0}")]
+    Secret(#[from] SecretError),
+}
+
+/// Servers that differ between two versions of the config.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub added: Vec<MCPServer>,
+    pub removed: Vec<MCPServer>,
+    /// New versions of servers whose launch settings changed. Other edits,
+    /// e.g. a new name, need no restart and are not listed.
+    pub changed: Vec<MCPServer>,
+}
+
+/// What a running server depends on.
+fn launch_settings(server: &MCPServer) -> (&MCPTransport, &Option<String>, bool) {
+    (&server.transport, &server.token, server.is_active)
+}
+
+impl ConfigDiff {
+    pub fn between(old: &[MCPServer], new: &[MCPServer]) -> Self {
+        let mut diff = Self::default();
+        for server in new {
+            match old.iter().find(|s| s.id == server.id) {
+                None => diff.added.push(server.clone()),
+                Some(previous) if launch_settings(previous) != launch_settings(server) => {
+                    diff.changed.push(server.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|s| !new.iter().any(|n| n.id == s.id))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
 }
 
 pub struct ConfigManager {
     config_path: PathBuf,
     config: MCPConfig,
+    /// The config as last read from or written to disk; the common ancestor
+    /// when merging external modifications.
+    base: MCPConfig,
+    stamp: Option<FileStamp>,
+    /// Where tokens and secret env values live; `mcp.json` only references them.
+    secrets: Option<Arc<SecretStore>>,
+    /// Server settings of the active project; not part of `mcp.json`.
+    project: Option<ProjectServers>,
 }
 
 impl ConfigManager {
     pub fn new() -> Result<Self, ConfigError> {
-        let config_path = Self::get_config_path()?;
-        let config = Self::load_or_create_config(&config_path)?;
+        Self::open(Self::get_config_path()?)
+    }
+
+    fn open(config_path: PathBuf) -> Result<Self, ConfigError> {
+        let (config, stamp) = store::with_lock(&config_path, || {
+            let config = Self::load_or_create_config(&config_path)?;
+            Ok::<_, ConfigError>((config, FileStamp::read(&config_path)?))
+        })?;
 
         Ok(Self {
             config_path,
+            base: config.clone(),
             config,
+            stamp,
+            secrets: None,
+            project: None,
         })
     }
 
+    /// Keeps secrets out of the config from now on and moves any plaintext
+    /// tokens or secret env values still in the file into the store.
+    pub fn with_secrets(mut self, secrets: Arc<SecretStore>) -> Result<Self, ConfigError> {
+        let mut replaced = Vec::new();
+        for server in &mut self.config.servers {
+            let before = server.clone();
+            if credentials::externalize(server, &secrets)? {
+                replaced.extend(credentials::replaced_values(&before, server));
+            }
+        }
+        self.secrets = Some(secrets);
+
+        if !replaced.is_empty() {
+            // Older copies of the file (backups, quarantined files) still hold
+            // the plaintext values; they get the references instead.
+            let replacements: Vec<(Vec<u8>, Vec<u8>)> = replaced
+                .iter()
+                .map(|(value, reference)| Ok((serde_json::to_vec(value)?, serde_json::to_vec(reference)?)))
+                .collect::<Result<_, serde_json::Error>>()
+                .map_err(ConfigError::Serialization)?;
+            for copy in store::old_copies(&self.config_path)? {
+                store::replace_in(&copy, &replacements)?;
+            }
+            self.save()?;
+        }
+        Ok(self)
+    }
+
+    fn externalize(&self, server: &mut MCPServer) -> Result<(), ConfigError> {
+        if let Some(secrets) = &self.secrets {
+            credentials::externalize(server, secrets)?;
+        }
+        Ok(())
+    }
+
+    fn forget(&self, old: &MCPServer, new: Option<&MCPServer>) -> Result<(), ConfigError> {
+        if let Some(secrets) = &self.secrets {
+            credentials::forget(old, new, secrets)?;
+        }
+        Ok(())
+    }
+
     fn get_config_path() -> Result<PathBuf, ConfigError> {
         let proj_dirs = ProjectDirs::from("com", "lukedesktop", "LukeDesktop")
             .ok_or(ConfigError::ConfigDir)?;
@@ -50,24 +181,153 @@ impl ConfigManager {
 
     fn load_or_create_config(path: &Path) -> Result<MCPConfig, ConfigError> {
         if path.exists() {
-            let content = fs::read_to_string(path).map_err(ConfigError::CreateDir)?;
-            serde_json::from_str(&content).map_err(ConfigError::Serialization)
+            match Self::read_config(path, true) {
+                Ok(config) => {
+                    store::remember_good(path)?;
+                    Ok(config)
+                }
+                Err(e @ (ConfigError::Serialization(_) | ConfigError::UnknownFormat | ConfigError::InvalidVersion(_))) => {
+                    Self::restore_last_known_good(path, e)
+                }
+                Err(e) => Err(e),
+            }
         } else {
             let config = MCPConfig::default();
             let content = serde_json::to_string_pretty(&config).map_err(ConfigError::Serialization)?;
-            fs::write(path, content).map_err(ConfigError::CreateDir)?;
+            store::write_atomic(path, content.as_bytes()).map_err(ConfigError::CreateDir)?;
             Ok(config)
         }
     }
 
-    pub fn save(&self) -> Result<(), ConfigError> {
-        let content = serde_json::to_string_pretty(&self.config).map_err(ConfigError::Serialization)?;
-        fs::write(&self.config_path, content).map_err(ConfigError::CreateDir)
+    /// Reads and migrates a config file. With `persist`, a migrated config is
+    /// written back after backing up the original.
+    fn read_config(path: &Path, persist: bool) -> Result<MCPConfig, ConfigError> {
+        let content = fs::read_to_string(path).map_err(ConfigError::CreateDir)?;
+        let mut raw: serde_json::Value = serde_json::from_str(&content).map_err(ConfigError::Serialization)?;
+
+        if migrate(&mut raw)? && persist {
+            Self::backup(path)?;
+            let migrated = serde_json::to_string_pretty(&raw).map_err(ConfigError::Serialization)?;
+            store::write_atomic(path, migrated.as_bytes()).map_err(ConfigError::CreateDir)?;
+        }
+
+        serde_json::from_value(raw).map_err(ConfigError::Serialization)
+    }
+
+    /// Replaces an unreadable config with the newest last-known-good copy
+    /// that still loads. The broken file is kept as `mcp.json.<timestamp>.corrupt`.
+    fn restore_last_known_good(path: &Path, error: ConfigError) -> Result<MCPConfig, ConfigError> {
+        for copy in store::good_copies(path) {
+            let Ok(config) = Self::read_config(&copy, false) else {
+                continue;
+            };
+
+            let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+            let corrupt = store::quarantine(path, &stamp).map_err(ConfigError::CreateDir)?;
+            let content = serde_json::to_string_pretty(&config).map_err(ConfigError::Serialization)?;
+            store::write_atomic(path, content.as_bytes()).map_err(ConfigError::CreateDir)?;
+
+            eprintln!(
+                "MCP config was unreadable ({}); restored {} and kept the broken file as {}",
+                error,
+                copy.display(),
+                corrupt.display()
+            );
+            return Ok(config);
+        }
+        Err(error)
+    }
+
+    /// Copies the config next to itself as `mcp.json.<timestamp>.bak`.
+    fn backup(path: &Path) -> Result<PathBuf, ConfigError> {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("mcp.json");
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        let backup_path = path.with_file_name(format!("{}.{}.bak", file_name, stamp));
+        fs::copy(path, &backup_path).map_err(ConfigError::CreateDir)?;
+        Ok(backup_path)
+    }
+
+    /// Writes the config atomically under the directory lock. If the file was
+    /// changed by someone else since we last saw it, non-conflicting changes
+    /// are merged; conflicting ones are rejected. If the write fails, the
+    /// config goes back to what was last saved, so the change is not sent
+    /// again with the next save.
+    pub fn save(&mut self) -> Result<(), ConfigError> {
+        let path = self.config_path.clone();
+        let saved = store::with_lock(&path, || {
+            if store::changed_since(&path, self.stamp.as_ref())? {
+                let theirs = if path.exists() {
+                    Self::read_config(&path, false)?
+                } else {
+                    MCPConfig::default()
+                };
+                self.config = merge(&self.base, &self.config, &theirs)?;
+            }
+
+            let content = serde_json::to_string_pretty(&self.config).map_err(ConfigError::Serialization)?;
+            store::write_atomic(&path, content.as_bytes())?;
+            store::remember_good(&path)?;
+
+            self.stamp = FileStamp::read(&path)?;
+            self.base = self.config.clone();
+            Ok(())
+        });
+
+        if saved.is_err() {
+            self.config = self.base.clone();
+        }
+        saved
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Picks up edits made outside the app. Returns `None` if the file is
+    /// unchanged. An invalid file is rejected and the current config stays
+    /// in place.
+    pub fn reload(&mut self) -> Result<Option<ConfigDiff>, ConfigError> {
+        let path = self.config_path.clone();
+        let reloaded = store::with_lock(&path, || {
+            if !store::changed_since(&path, self.stamp.as_ref())? {
+                return Ok(None);
+            }
+            let mut config = Self::read_config(&path, false)?;
+            validate(&config)?;
+
+            // Hand-edited tokens go into the store before a copy is kept.
+            let mut changed = false;
+            if let Some(secrets) = &self.secrets {
+                for server in &mut config.servers {
+                    changed |= credentials::externalize(server, secrets)?;
+                }
+            }
+            if changed {
+                let content = serde_json::to_string_pretty(&config).map_err(ConfigError::Serialization)?;
+                store::write_atomic(&path, content.as_bytes())?;
+            }
+            store::remember_good(&path)?;
+            Ok::<_, ConfigError>(Some((config, FileStamp::read(&path)?)))
+        })?;
+
+        let Some((config, stamp)) = reloaded else {
+            return Ok(None);
+        };
+
+        let before = self.effective_servers();
+        self.base = config.clone();
+        let old = std::mem::replace(&mut self.config, config);
+        self.stamp = stamp;
+        for server in &old.servers {
+            self.forget(server, self.get_server(&server.id))?;
+        }
+        Ok(Some(ConfigDiff::between(&before, &self.effective_servers())))
     }
 
     pub fn add_server(&mut self, server: MCPServer) -> Result<MCPServer, ConfigError> {
         let mut new_server = server;
         new_server.id = Uuid::new_v4().to_string();
+        self.externalize(&mut new_server)?;
 
         self.config.servers.push(new_server.clone());
 
@@ -75,7 +335,10 @@ impl ConfigManager {
             self.config.default_server = Some(new_server.id.clone());
         }
 
-        self.save()?;
+        if let Err(e) = self.save() {
+            self.forget(&new_server, None)?;
+            return Err(e);
+        }
         Ok(new_server)
     }
 
@@ -85,7 +348,9 @@ impl ConfigManager {
             .position(|s| s.id == id)
             .ok_or_else(|| ConfigError::ServerNotFound(id.to_string()))?;
 
-        self.config.servers.remove(pos);
+        let removed = self.config.servers.remove(pos);
+        self.config.tool_policies.remove(id);
+        self.config.tool_overrides.remove(id);
 
         if let Some(default_id) = &self.config.default_server {
             if default_id == id {
@@ -94,6 +359,7 @@ impl ConfigManager {
         }
 
         self.save()?;
+        self.forget(&removed, None)?;
         Ok(())
     }
 
@@ -103,11 +369,83 @@ impl ConfigManager {
             .position(|s| s.id == server.id)
             .ok_or_else(|| ConfigError::ServerNotFound(server.id.clone()))?;
 
-        self.config.servers[pos] = server;
-        self.save()?;
+        let mut server = server;
+        self.externalize(&mut server)?;
+        let old = std::mem::replace(&mut self.config.servers[pos], server.clone());
+        if let Err(e) = self.save() {
+            self.forget(&server, Some(&old))?;
+            return Err(e);
+        }
+        self.forget(&old, self.get_server(&old.id))?;
         Ok(())
     }
 
+    pub fn tool_policies(&self) -> &ToolPolicies {
+        &self.config.tool_policies
+    }
+
+    /// Sets the approval mode for a tool (`*` for the whole server), or
+    /// removes it so the default applies again.
+    pub fn set_tool_policy(
+        &mut self,
+        server_id: &str,
+        tool_name: &str,
+        mode: Option<ApprovalMode>,
+    ) -> Result<(), ConfigError> {
+        if self.get_server(server_id).is_none() {
+            return Err(ConfigError::ServerNotFound(server_id.to_string()));
+        }
+
+        let tools = self.config.tool_policies.entry(server_id.to_string()).or_default();
+        match mode {
+            Some(mode) => {
+                tools.insert(tool_name.to_string(), mode);
+            }
+            None => {
+                tools.remove(tool_name);
+                if tools.is_empty() {
+                    self.config.tool_policies.remove(server_id);
+                }
+            }
+        }
+        self.save()
+    }
+
+    pub fn tool_overrides(&self) -> &ToolOverrides {
+        &self.config.tool_overrides
+    }
+
+    /// Aliases or hides a tool, or removes its override again.
+    pub fn set_tool_override(
+        &mut self,
+        server_id: &str,
+        tool_name: &str,
+        tool_override: Option<ToolOverride>,
+    ) -> Result<(), ConfigError> {
+        if self.get_server(server_id).is_none() {
+            return Err(ConfigError::ServerNotFound(server_id.to_string()));
+        }
+        if let Some(alias) = tool_override.as_ref().and_then(|o| o.alias.as_deref()) {
+            if !is_valid_tool_name(alias) {
+                return Err(ConfigError::Invalid(format!("ungültiger Alias {}", alias)));
+            }
+        }
+
+        let tools = self.config.tool_overrides.entry(server_id.to_string()).or_default();
+        match tool_override.filter(|o| *o != ToolOverride::default()) {
+            Some(tool_override) => {
+                tools.insert(tool_name.to_string(), tool_override);
+            }
+            None => {
+                tools.remove(tool_name);
+                if tools.is_empty() {
+                    self.config.tool_overrides.remove(server_id);
+                }
+            }
+        }
+        self.save()
+    }
+
     pub fn get_server(&self, id: &str) -> Option<&MCPServer> {
         self.config.servers.iter().find(|s| s.id == id)
     }
@@ -131,4 +469,488 @@ impl ConfigManager {
         self.save()?;
         Ok(())
     }
+
+    /// Switches to the server settings of another project, or back to the
+    /// global config. Returns how the effective servers changed.
+    pub fn set_project(&mut self, project: Option<ProjectServers>) -> ConfigDiff {
+        let before = self.effective_servers();
+        self.project = project;
+        ConfigDiff::between(&before, &self.effective_servers())
+    }
+
+    /// All servers as the active project sees them: enabled per the project
+    /// (or globally, without one) and with its overrides applied.
+    pub fn effective_servers(&self) -> Vec<MCPServer> {
+        self.config
+            .servers
+            .iter()
+            .map(|server| self.effective(server))
+            .collect()
+    }
+
+    pub fn effective_server(&self, id: &str) -> Option<MCPServer> {
+        self.get_server(id).map(|server| self.effective(server))
+    }
+
+    fn effective(&self, server: &MCPServer) -> MCPServer {
+        let Some(project) = &self.project else {
+            return server.clone();
+        };
+
+        let mut server = server.clone();
+        if let Some(enabled) = &project.enabled {
+            server.is_active = enabled.contains(&server.id);
+        }
+        if let (Some(changes), MCPTransport::Stdio { args, env, .. }) =
+            (project.overrides.get(&server.id), &mut server.transport)
+        {
+            if let Some(project_args) = &changes.args {
+                *args = project_args.clone();
+            }
+            env.extend(changes.env.clone());
+        }
+        server
+    }
+
+    /// The active project's default server, falling back to the global one.
+    pub fn effective_default_server(&self) -> Option<MCPServer> {
+        self.project
+            .as_ref()
+            .and_then(|project| project.default_server.as_deref())
+            .and_then(|id| self.effective_server(id))
+            .or_else(|| self.get_default_server().cloned())
+    }
+
+    /// Imports servers from a Claude Desktop, Cursor or VS Code config.
+    pub fn import_servers(&mut self, content: &str) -> Result<ImportReport, ConfigError> {
+        let mut report = plan_import(&self.config.servers, content)?;
+        if report.added.is_empty() {
+            return Ok(report);
+        }
+        for server in &mut report.added {
+            self.externalize(server)?;
+        }
+
+        self.config.servers.extend(report.added.iter().cloned());
+        if self.config.default_server.is_none() {
+            self.config.default_server = report.added.first().map(|s| s.id.clone());
+        }
+
+        if let Err(e) = self.save() {
+            for server in &report.added {
+                self.forget(server, None)?;
+            }
+            return Err(e);
+        }
+        Ok(report)
+    }
+
+    pub fn export_servers(&self, format: ExportFormat) -> Result<String, ConfigError> {
+        let exported = export(&self.config.servers, format);
+        serde_json::to_string_pretty(&exported).map_err(ConfigError::Serialization)
+    }
+}
+
+/// Checks what serde can't: unique ids, names, commands/urls and a default
+/// that points at an existing server.
+fn validate(config: &MCPConfig) -> Result<(), ConfigError> {
+    let invalid = |message: String| Err(ConfigError::Invalid(message));
+
+    for (i, server) in config.servers.iter().enumerate() {
+        if server.id.trim().is_empty() {
+            return invalid(format!("servers[{}]: id ist leer", i));
+        }
+        if config.servers[..i].iter().any(|s| s.id == server.id) {
+            return invalid(format!("servers[{}]: doppelte id {}", i, server.id));
+        }
+        if server.name.trim().is_empty() {
+            return invalid(format!("servers[{}]: name ist leer", i));
+        }
+        match &server.transport {
+            MCPTransport::Stdio { command, .. } if command.trim().is_empty() => {
+                return invalid(format!("servers[{}]: command ist leer", i));
+            }
+            MCPTransport::Http { url } if reqwest::Url::parse(url).is_err() => {
+                return invalid(format!("servers[{}]: ungültige url {}", i, url));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(id) = &config.default_server {
+        if !config.servers.iter().any(|s| &s.id == id) {
+            return invalid(format!("defaultServer {} existiert nicht", id));
+        }
+    }
+    Ok(())
+}
+
+/// The side that changed relative to `base`, or `None` if both did.
+fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == base || ours == theirs {
+        Some(theirs.clone())
+    } else if theirs == base {
+        Some(ours.clone())
+    } else {
+        None
+    }
+}
+
+/// Three-way merge of our in-memory config with one changed on disk, per
+/// server id. A side that left an entry untouched takes the other side's
+/// version; servers changed differently on both sides are a conflict.
+fn merge(base: &MCPConfig, ours: &MCPConfig, theirs: &MCPConfig) -> Result<MCPConfig, ConfigError> {
+    let find = |config: &MCPConfig, id: &str| config.servers.iter().find(|s| s.id == id).cloned();
+
+    let mut ids: Vec<String> = Vec::new();
+    for server in theirs.servers.iter().chain(&ours.servers) {
+        if !ids.contains(&server.id) {
+            ids.push(server.id.clone());
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    let mut servers = Vec::new();
+    for id in ids {
+        match pick(&find(base, &id), &find(ours, &id), &find(theirs, &id)) {
+            Some(Some(server)) => servers.push(server),
+            Some(None) => {}
+            None => conflicts.push(id),
+        }
+    }
+
+    // A competing default is not worth rejecting the save; the file wins.
+    let default_server = pick(&base.default_server, &ours.default_server, &theirs.default_server)
+        .unwrap_or_else(|| theirs.default_server.clone());
+
+    if !conflicts.is_empty() {
+        return Err(ConfigError::Conflict(conflicts.join(", ")));
+    }
+
+    // Policies and overrides merge per tool; competing edits resolve like
+    // the default.
+    let keep = |server_id: &String| servers.iter().any(|s| &s.id == server_id);
+    let mut tool_policies = merge_per_tool(base, ours, theirs, |c| &c.tool_policies);
+    tool_policies.retain(|server_id, _| keep(server_id));
+    let mut tool_overrides = merge_per_tool(base, ours, theirs, |c| &c.tool_overrides);
+    tool_overrides.retain(|server_id, _| keep(server_id));
+
+    Ok(MCPConfig {
+        version: ours.version.clone(),
+        default_server: default_server.filter(|id| servers.iter().any(|s| &s.id == id)),
+        servers,
+        tool_policies,
+        tool_overrides,
+    })
+}
+
+type PerTool<T> = BTreeMap<String, BTreeMap<String, T>>;
+
+fn merge_per_tool<T: PartialEq + Clone>(
+    base: &MCPConfig,
+    ours: &MCPConfig,
+    theirs: &MCPConfig,
+    entries: impl Fn(&MCPConfig) -> &PerTool<T>,
+) -> PerTool<T> {
+    let entry = |config: &MCPConfig, server_id: &str, tool: &str| {
+        entries(config).get(server_id).and_then(|tools| tools.get(tool)).cloned()
+    };
+
+    let mut merged = PerTool::new();
+    for config in [base, ours, theirs] {
+        for (server_id, tools) in entries(config) {
+            for tool in tools.keys() {
+                let value = pick(
+                    &entry(base, server_id, tool),
+                    &entry(ours, server_id, tool),
+                    &entry(theirs, server_id, tool),
+                )
+                .unwrap_or_else(|| entry(theirs, server_id, tool));
+                if let Some(value) = value {
+                    merged.entry(server_id.clone()).or_default().insert(tool.clone(), value);
+                }
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::migrations::CURRENT_VERSION;
+    use super::super::types::ServerOverride;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_migration_writes_backup() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+        let original = r#"{ "version": "1.0", "default_server": null, "servers": [] }"#;
+        fs::write(&path, original).unwrap();
+
+        let config = ConfigManager::load_or_create_config(&path).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION.to_string());
+
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".bak"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(backups[0].path()).unwrap(), original);
+        assert!(fs::read_to_string(&path).unwrap().contains("\"defaultServer\""));
+
+        // A config from a newer build is left alone.
+        fs::write(&path, r#"{ "version": "99", "servers": [] }"#).unwrap();
+        assert!(matches!(
+            ConfigManager::load_or_create_config(&path),
+            Err(ConfigError::NewerVersion { found: 99, .. })
+        ));
+        assert!(fs::read_to_string(&path).unwrap().contains("99"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn server(name: &str) -> MCPServer {
+        MCPServer {
+            id: String::new(),
+            name: name.to_string(),
+            transport: MCPTransport::Http { url: format!("http://localhost/{}", name) },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }
+    }
+
+    #[test]
+    fn test_save_merges_external_changes() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+
+        let mut first = ConfigManager::open(path.clone()).unwrap();
+        let mut second = ConfigManager::open(path.clone()).unwrap();
+
+        let a = first.add_server(server("a")).unwrap();
+        let b = second.add_server(server("b")).unwrap();
+
+        let reopened = ConfigManager::open(path.clone()).unwrap();
+        let names: Vec<&str> = reopened.get_servers().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        // Both set their own server as default; the first writer won.
+        assert_eq!(reopened.get_default_server().unwrap().id, a.id);
+
+        // Editing the same server differently on both sides is rejected.
+        let mut first = ConfigManager::open(path.clone()).unwrap();
+        let mut second = ConfigManager::open(path.clone()).unwrap();
+        first.update_server(MCPServer { name: "b1".to_string(), ..b.clone() }).unwrap();
+        let error = second.update_server(MCPServer { name: "b2".to_string(), ..b.clone() }).unwrap_err();
+        assert!(matches!(error, ConfigError::Conflict(ref ids) if ids == &b.id));
+
+        // Tool policies set concurrently are merged per tool.
+        let mut first = ConfigManager::open(path.clone()).unwrap();
+        let mut second = ConfigManager::open(path.clone()).unwrap();
+        first.set_tool_policy(&a.id, "read_file", Some(ApprovalMode::AlwaysAllow)).unwrap();
+        second.set_tool_policy(&a.id, "write_file", Some(ApprovalMode::Deny)).unwrap();
+        let policies = ConfigManager::open(path.clone()).unwrap().tool_policies()[&a.id].clone();
+        assert_eq!(policies["read_file"], ApprovalMode::AlwaysAllow);
+        assert_eq!(policies["write_file"], ApprovalMode::Deny);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejected_save_leaves_config_unchanged() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+
+        let mut first = ConfigManager::open(path.clone()).unwrap();
+        let a = first.add_server(server("a")).unwrap();
+        let mut second = ConfigManager::open(path.clone()).unwrap();
+        first.update_server(MCPServer { name: "a1".to_string(), ..a.clone() }).unwrap();
+
+        let before = second.config.clone();
+        let error = second.update_server(MCPServer { name: "a2".to_string(), ..a.clone() }).unwrap_err();
+        assert!(matches!(error, ConfigError::Conflict(_)));
+        assert_eq!(second.config, before);
+
+        // The next save does not send the rejected change again.
+        second.add_server(server("b")).unwrap();
+        let reopened = ConfigManager::open(path.clone()).unwrap();
+        let names: Vec<&str> = reopened.get_servers().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["a1", "b"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_falls_back_to_last_known_good() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+
+        let mut manager = ConfigManager::open(path.clone()).unwrap();
+        manager.add_server(server("a")).unwrap();
+
+        fs::write(&path, "{ \"version\": \"2\", \"servers\": [").unwrap();
+
+        let restored = ConfigManager::open(path.clone()).unwrap();
+        assert_eq!(restored.get_servers()[0].name, "a");
+        assert!(fs::read_dir(&dir)
+            .unwrap()
+            .any(|e| e.unwrap().file_name().to_string_lossy().ends_with(".corrupt")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_diffs_and_rejects_invalid_edits() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+
+        let mut manager = ConfigManager::open(path.clone()).unwrap();
+        let a = manager.add_server(server("a")).unwrap();
+        let b = manager.add_server(server("b")).unwrap();
+        assert_eq!(manager.reload().unwrap(), None);
+
+        let mut edited = manager.config.clone();
+        edited.servers[0].name = "renamed".to_string();
+        edited.servers[1].last_connected = Some("2024-01-01T00:00:00Z".to_string());
+        fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+        // Nothing a running server depends on changed.
+        assert!(manager.reload().unwrap().unwrap().is_empty());
+        assert_eq!(manager.get_server(&a.id).unwrap().name, "renamed");
+
+        edited.servers.retain(|s| s.id != a.id);
+        edited.servers[0].is_active = false;
+        edited.servers.push(MCPServer { id: "c".to_string(), ..server("c") });
+        edited.default_server = Some("c".to_string());
+        fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+
+        let diff = manager.reload().unwrap().unwrap();
+        assert_eq!(diff.removed[0].id, a.id);
+        assert_eq!(diff.changed[0].id, b.id);
+        assert_eq!(diff.added[0].id, "c");
+        assert_eq!(manager.get_default_server().unwrap().id, "c");
+
+        fs::write(&path, "{ \"version\": \"2\", \"servers\": [{ \"id\": \"x\" }] }").unwrap();
+        assert!(matches!(manager.reload(), Err(ConfigError::Serialization(_))));
+
+        let mut duplicate = edited.clone();
+        duplicate.servers.push(duplicate.servers[0].clone());
+        fs::write(&path, serde_json::to_string(&duplicate).unwrap()).unwrap();
+        assert!(matches!(manager.reload(), Err(ConfigError::Invalid(ref m)) if m.contains("doppelte id")));
+
+        // The previous config is still live.
+        assert_eq!(manager.get_servers().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_project_server_sets() {
+        let dir = store::temp_dir();
+        let mut manager = ConfigManager::open(dir.join("mcp.json")).unwrap();
+        let fs_server = manager.add_server(MCPServer {
+            transport: MCPTransport::Stdio {
+                command: "mcp-fs".to_string(),
+                args: vec!["/home".to_string()],
+                env: HashMap::from([("LOG".to_string(), "info".to_string())]),
+                cwd: None,
+            },
+            is_active: false,
+            ..server("fs")
+        }).unwrap();
+        let web = manager.add_server(server("web")).unwrap();
+        assert_eq!(manager.effective_servers(), manager.get_servers());
+
+        let diff = manager.set_project(Some(ProjectServers {
+            enabled: Some(vec![fs_server.id.clone()]),
+            default_server: Some(fs_server.id.clone()),
+            overrides: BTreeMap::from([(fs_server.id.clone(), ServerOverride {
+                args: Some(vec!["/srv/monorepo".to_string()]),
+                env: HashMap::from([("TOKEN".to_string(), "secret://ops".to_string())]),
+            })]),
+        }));
+        assert_eq!(diff.changed.len(), 2);
+
+        let effective = manager.effective_server(&fs_server.id).unwrap();
+        assert!(effective.is_active);
+        let MCPTransport::Stdio { args, env, .. } = &effective.transport else {
+            panic!("expected stdio");
+        };
+        assert_eq!(args, &["/srv/monorepo"]);
+        assert_eq!(env.len(), 2);
+        assert!(!manager.effective_server(&web.id).unwrap().is_active);
+        assert_eq!(manager.effective_default_server().unwrap().id, fs_server.id);
+
+        // The project's settings never reach mcp.json.
+        assert!(!manager.get_server(&fs_server.id).unwrap().is_active);
+        assert!(!fs::read_to_string(dir.join("mcp.json")).unwrap().contains("monorepo"));
+
+        // Without an enabled list, the global selection applies.
+        let diff = manager.set_project(Some(ProjectServers::default()));
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(manager.effective_default_server().unwrap().id, fs_server.id);
+        assert!(manager.set_project(None).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_moves_plaintext_secrets_into_store() {
+        let dir = store::temp_dir();
+        let path = dir.join("mcp.json");
+        let secrets = Arc::new(SecretStore::new(crate::secrets::MemoryBackend::default()));
+
+        let mut manager = ConfigManager::open(path.clone()).unwrap();
+        let remote = manager
+            .add_server(MCPServer { token: Some("abc".to_string()), ..server("remote") })
+            .unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("abc"));
+        let backup = ConfigManager::backup(&path).unwrap();
+        let corrupt = dir.join("mcp.json.20240101-000000.corrupt");
+        fs::write(&corrupt, "{ \"servers\": [{ \"token\": \"abc\", ").unwrap();
+
+        let mut manager = ConfigManager::open(path.clone()).unwrap().with_secrets(secrets.clone()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("abc"));
+        assert!(content.contains("secret://mcp/"));
+        assert!(store::good_copies(&path).iter().all(|p| !fs::read_to_string(p).unwrap().contains("abc")));
+        // Backups and quarantined files are kept, without the plaintext.
+        for copy in [&backup, &corrupt] {
+            let content = fs::read_to_string(copy).unwrap();
+            assert!(!content.contains("\"abc\"") && content.contains("secret://mcp/"), "{}", content);
+        }
+
+        let stored = manager.get_server(&remote.id).unwrap().clone();
+        assert_eq!(secrets.resolve(stored.token.as_deref().unwrap()).unwrap(), "abc");
+
+        // New servers never reach the file in plaintext either.
+        let other = manager
+            .add_server(MCPServer { token: Some("xyz".to_string()), ..server("other") })
+            .unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("xyz"));
+
+        manager.remove_server(&other.id).unwrap();
+        assert!(secrets.resolve(other.token.as_deref().unwrap()).is_err());
+
+        // Neither do tokens added by hand, and removing a server by hand
+        // drops its secrets.
+        let mut edited = manager.config.clone();
+        edited.servers[0].token = Some("qrs".to_string());
+        fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+        manager.reload().unwrap().unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("qrs"));
+        assert!(store::good_copies(&path).iter().all(|p| !fs::read_to_string(p).unwrap().contains("qrs")));
+        let stored = manager.get_server(&remote.id).unwrap().clone();
+        assert_eq!(secrets.resolve(stored.token.as_deref().unwrap()).unwrap(), "qrs");
+
+        edited.servers.clear();
+        edited.default_server = None;
+        fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+        manager.reload().unwrap().unwrap();
+        assert!(secrets.resolve(stored.token.as_deref().unwrap()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
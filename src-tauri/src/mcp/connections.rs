@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use tokio::sync::Mutex;
+
+use crate::secrets::SecretStore;
+
+use super::credentials;
+use super::health::{StatusBoard, StatusListener};
+use super::http::{is_legacy_fallback, LegacySseTransport, StreamableHttpTransport};
+use super::inspector::{RecordingTransport, TrafficLog};
+use super::oauth::{OAuthClient, OAuthClients, SIGN_IN_REQUIRED};
+use super::protocol::{ClientHandler, DefaultClientHandler, McpSession};
+use super::supervisor::{ProcessSupervisor, RestartPolicy, StdioCommand};
+use super::transport::{StdioTransport, Transport};
+use super::types::{MCPError, MCPServer, MCPTransport};
+
+/// Builds the handler for server-initiated messages of a new session.
+pub type HandlerFactory = Box<dyn Fn(&MCPServer) -> Arc<dyn ClientHandler> + Send + Sync>;
+
+/// Serializes connection attempts to one server. Closing the server bumps
+/// `epoch`, so an attempt that was overtaken throws its session away.
+#[derive(Default)]
+struct ConnectSlot {
+    lock: Mutex<()>,
+    epoch: AtomicU64,
+}
+
+/// Owns the live MCP sessions and the processes behind stdio servers.
+#[derive(Default)]
+pub struct ConnectionManager {
+    supervisor: ProcessSupervisor,
+    sessions: Mutex<HashMap<String, Arc<McpSession>>>,
+    connecting: StdMutex<HashMap<String, Arc<ConnectSlot>>>,
+    handlers: Option<HandlerFactory>,
+    secrets: Option<Arc<SecretStore>>,
+    oauth: Option<OAuthClients>,
+    traffic: Option<TrafficLog>,
+    status: StatusBoard,
+}
+
+impl ConnectionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_handlers(mut self, factory: HandlerFactory) -> Self {
+        self.handlers = Some(factory);
+        self
+    }
+
+    /// Resolves secret references in server configs before connecting.
+    pub fn with_secrets(mut self, secrets: Arc<SecretStore>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Signs in to HTTP servers without a static token via OAuth.
+    pub fn with_oauth(mut self, clients: OAuthClients) -> Self {
+        self.oauth = Some(clients);
+        self
+    }
+
+    /// Records every message exchanged with a server in `traffic`.
+    pub fn with_traffic(mut self, traffic: TrafficLog) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+
+    /// Reports every state change of a server to `listener`.
+    pub fn with_status_listener(mut self, listener: StatusListener) -> Self {
+        self.status = StatusBoard::with_listener(listener);
+        self
+    }
+
+    pub fn status(&self) -> &StatusBoard {
+        &self.status
+    }
+
+    pub fn supervisor(&self) -> &ProcessSupervisor {
+        &self.supervisor
+    }
+
+    fn slot(&self, server_id: &str) -> Arc<ConnectSlot> {
+        let mut slots = self.connecting.lock().unwrap_or_else(|e| e.into_inner());
+        slots.entry(server_id.to_string()).or_default().clone()
+    }
+
+    /// Returns the live session for a server, connecting and running the
+    /// handshake first if necessary. Connecting can take minutes with an
+    /// OAuth sign-in, so only callers for the same server wait for it.
+    pub async fn connect(&self, server: &MCPServer) -> Result<Arc<McpSession>, MCPError> {
+        if let Some(session) = self.session(&server.id).await {
+            return Ok(session);
+        }
+        let slot = self.slot(&server.id);
+        let _connecting = slot.lock.lock().await;
+        if let Some(session) = self.session(&server.id).await {
+            return Ok(session);
+        }
+
+        let epoch = slot.epoch.load(Ordering::SeqCst);
+        self.status.connecting(&server.id);
+        let session = match self.open(server).await {
+            Ok(session) => session,
+            Err(e) if e.code == SIGN_IN_REQUIRED => {
+                self.status.sign_in_required(&server.id, e.message.clone());
+                return Err(e);
+            }
+            Err(e) => {
+                self.status.connect_failed(&server.id, e.message.clone());
+                return Err(e);
+            }
+        };
+
+        let mut sessions = self.sessions.lock().await;
+        if slot.epoch.load(Ordering::SeqCst) != epoch {
+            drop(sessions);
+            session.close().await;
+            self.supervisor.stop(&server.id).await;
+            let error = MCPError::new(
+                "CONNECTION_CANCELLED",
+                format!("MCP server {} was closed while connecting", server.id),
+            );
+            if !self.status.get(&server.id).is_some_and(|status| status.is_stopped()) {
+                self.status.failed(&server.id, error.message.clone());
+            }
+            return Err(error);
+        }
+        sessions.insert(server.id.clone(), session.clone());
+        self.status.connected(&server.id);
+        Ok(session)
+    }
+
+    async fn open(&self, server: &MCPServer) -> Result<Arc<McpSession>, MCPError> {
+        let resolved;
+        let server = match &self.secrets {
+            Some(secrets) => {
+                resolved = credentials::resolve(server, secrets)
+                    .map_err(|e| MCPError::new("SECRET_UNAVAILABLE", e.to_string()))?;
+                &resolved
+            }
+            None => server,
+        };
+
+        let handler = match &self.handlers {
+            Some(factory) => factory(server),
+            None => Arc::new(DefaultClientHandler),
+        };
+
+        match &server.transport {
+            MCPTransport::Stdio { .. } => {
+                let command = StdioCommand::from_transport(&server.transport)
+                    .ok_or_else(|| MCPError::new("INVALID_CONFIG", "Missing command"))?;
+                // A previous session may have left its process behind.
+                self.supervisor.stop(&server.id).await;
+                let channel = self
+                    .supervisor
+                    .spawn(&server.id, command, RestartPolicy::default())?;
+
+                let transport = self.wrap(&server.id, Arc::new(StdioTransport::new(channel)));
+                let session = McpSession::connect(transport, handler).await;
+                if session.is_err() {
+                    self.supervisor.stop(&server.id).await;
+                }
+                session
+            }
+            MCPTransport::Http { url } => {
+                let oauth = self.oauth_client(server, url);
+                self.connect_http(&server.id, url, server.token.clone(), oauth, handler).await
+            }
+        }
+    }
+
+    fn wrap(&self, server_id: &str, transport: Arc<dyn Transport>) -> Arc<dyn Transport> {
+        match &self.traffic {
+            Some(traffic) => Arc::new(RecordingTransport::new(transport, server_id, traffic.clone())),
+            None => transport,
+        }
+    }
+
+    /// Tries Streamable HTTP first and falls back to the older HTTP+SSE
+    /// transport when the server rejects the initial POST.
+    async fn connect_http(
+        &self,
+        server_id: &str,
+        url: &str,
+        token: Option<String>,
+        oauth: Option<Arc<OAuthClient>>,
+        handler: Arc<dyn ClientHandler>,
+    ) -> Result<Arc<McpSession>, MCPError> {
+        let streamable = match &oauth {
+            Some(oauth) => StreamableHttpTransport::with_oauth(url, oauth.clone()),
+            None => StreamableHttpTransport::new(url, token.clone()),
+        };
+        match McpSession::connect(self.wrap(server_id, Arc::new(streamable)), handler.clone()).await {
+            Err(e) if is_legacy_fallback(&e) => {
+                let token = token.or_else(|| oauth.and_then(|o| o.access_token()));
+                let legacy = LegacySseTransport::connect(url, token).await?;
+                McpSession::connect(self.wrap(server_id, Arc::new(legacy)), handler).await
+            }
+            result => result,
+        }
+    }
+
+    /// The OAuth client of an HTTP server without a static token.
+    fn oauth_client(&self, server: &MCPServer, url: &str) -> Option<Arc<OAuthClient>> {
+        if server.token.is_some() {
+            return None;
+        }
+        Some(self.oauth.as_ref()?.client(&server.id, url))
+    }
+
+    /// Closes the session and forgets the server's OAuth tokens.
+    pub async fn sign_out(&self, server_id: &str) -> Result<(), MCPError> {
+        if let Some(oauth) = &self.oauth {
+            oauth.remove(server_id);
+        }
+        self.close_session(server_id).await;
+        if let Some(secrets) = &self.secrets {
+            credentials::forget_oauth(server_id, secrets)
+                .map_err(|e| MCPError::new("SECRET_UNAVAILABLE", e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Connects to every server, skipping (and logging) the ones that fail.
+    pub async fn connect_all(&self, servers: &[MCPServer]) -> Vec<(String, Arc<McpSession>)> {
+        let mut connected = Vec::new();
+        for server in servers {
+            match self.connect(server).await {
+                Ok(session) => connected.push((server.id.clone(), session)),
+                Err(e) => eprintln!("Failed to connect to MCP server {}: {}", server.id, e),
+            }
+        }
+        connected
+    }
+
+    pub async fn session(&self, server_id: &str) -> Option<Arc<McpSession>> {
+        let sessions = self.sessions.lock().await;
+        sessions.get(server_id).filter(|s| !s.is_closed()).cloned()
+    }
+
+    /// Sends a notification to every open session, e.g. when the roots change.
+    pub async fn notify_all(&self, method: &str) {
+        let sessions: Vec<_> = self.sessions.lock().await
+            .iter()
+            .map(|(id, session)| (id.clone(), session.clone()))
+            .collect();
+        for (server_id, session) in sessions.into_iter().filter(|(_, s)| !s.is_closed()) {
+            if let Err(e) = session.notify(method, None).await {
+                eprintln!("Failed to send {} to MCP server {}: {}", method, server_id, e);
+            }
+        }
+    }
+
+    /// Stops a server on request. The health monitor leaves it down until it
+    /// is connected again.
+    pub async fn disconnect(&self, server_id: &str) -> bool {
+        let disconnected = self.close_session(server_id).await;
+        self.status.stopped(server_id);
+        disconnected
+    }
+
+    /// Closes the session and stops the server process, if any.
+    pub async fn close_session(&self, server_id: &str) -> bool {
+        self.slot(server_id).epoch.fetch_add(1, Ordering::SeqCst);
+        let session = self.sessions.lock().await.remove(server_id);
+        if let Some(session) = &session {
+            session.close().await;
+        }
+        let stopped = self.supervisor.stop(server_id).await;
+        session.is_some() || stopped
+    }
+
+    /// Closes all sessions and terminates every child process.
+    pub async fn shutdown(&self) {
+        if let Ok(slots) = self.connecting.lock() {
+            for slot in slots.values() {
+                slot.epoch.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let sessions: Vec<_> = self.sessions.lock().await.drain().map(|(_, s)| s).collect();
+        for session in sessions {
+            session.close().await;
+        }
+        self.supervisor.shutdown_all().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// A server whose handshake takes `delay`.
+    async fn slow_server(delay: Duration) -> MockServer {
+        let server = MockServer::start().await;
+        let result = json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "serverInfo": { "name": "slow", "version": "1.0" }
+        });
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "initialize" })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(delay)
+                    .set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "notifications/initialized" })))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn http_server(id: &str, url: String) -> MCPServer {
+        MCPServer {
+            id: id.to_string(),
+            name: id.to_string(),
+            transport: MCPTransport::Http { url },
+            token: Some("token".to_string()),
+            is_active: true,
+            last_connected: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connecting_does_not_block_other_servers() {
+        let remote = slow_server(Duration::from_millis(500)).await;
+        let connections = Arc::new(ConnectionManager::new());
+        let slow = http_server("slow", format!("{}/mcp", remote.uri()));
+
+        let connecting = tokio::spawn({
+            let (connections, slow) = (connections.clone(), slow.clone());
+            async move { connections.connect(&slow).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let quick = async {
+            assert!(connections.session("other").await.is_none());
+            connections.notify_all("notifications/roots/list_changed").await;
+            connections.close_session("other").await;
+        };
+        tokio::time::timeout(Duration::from_millis(200), quick).await.unwrap();
+
+        // Closing the server mid-handshake discards the session it produced.
+        connections.close_session("slow").await;
+        let cancelled = connecting.await.unwrap().map(|_| ()).unwrap_err();
+        assert_eq!(cancelled.code, "CONNECTION_CANCELLED");
+        assert!(connections.session("slow").await.is_none());
+
+        let session = connections.connect(&slow).await.unwrap();
+        assert!(Arc::ptr_eq(&session, &connections.connect(&slow).await.unwrap()));
+    }
+}
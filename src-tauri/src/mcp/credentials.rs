@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::secrets::{reference_key, SecretError, SecretStore};
+
+use super::types::{MCPServer, MCPTransport};
+
+/// Substrings of environment variable names whose values are treated as
+/// secrets and kept out of `mcp.json`.
+const SECRET_ENV_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+pub fn is_secret_env(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    name.ends_with("_PAT") || SECRET_ENV_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+fn token_key(server_id: &str) -> String {
+    format!("mcp/{}/token", server_id)
+}
+
+fn env_key(server_id: &str, name: &str) -> String {
+    format!("mcp/{}/env/{}", server_id, name)
+}
+
+fn project_env_key(server_id: &str, project_id: &str, name: &str) -> String {
+    format!("mcp/{}/project/{}/env/{}", server_id, project_id, name)
+}
+
+/// Whether `key` lies in the server's own `mcp/<id>/` namespace. Servers
+/// may not refer to anything else, so an imported or hand-edited config
+/// cannot send the Anthropic key or another server's credentials somewhere.
+fn owns(server_id: &str, key: &str) -> bool {
+    !server_id.contains('/')
+        && key
+            .strip_prefix("mcp/")
+            .and_then(|rest| rest.strip_prefix(server_id))
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Resolves `value` if it is a plain value or a reference the server owns.
+fn resolve_owned(server_id: &str, value: &str, store: &SecretStore) -> Result<String, SecretError> {
+    match reference_key(value) {
+        Some(key) if !owns(server_id, key) => Err(SecretError::Forbidden(key.to_string())),
+        _ => store.resolve(value),
+    }
+}
+
+/// Keys the OAuth client keeps per server.
+const OAUTH_KEYS: &[&str] = &["refresh_token", "client"];
+
+pub(super) fn oauth_key(server_id: &str, name: &str) -> String {
+    format!("mcp/{}/oauth/{}", server_id, name)
+}
+
+/// Drops the server's OAuth client and refresh token, e.g. on sign-out.
+pub fn forget_oauth(server_id: &str, store: &SecretStore) -> Result<(), SecretError> {
+    for name in OAUTH_KEYS {
+        store.delete(&oauth_key(server_id, name))?;
+    }
+    Ok(())
+}
+
+/// Moves a plaintext token and secret env values into the store, leaving
+/// references behind. Returns whether the server changed.
+pub fn externalize(server: &mut MCPServer, store: &SecretStore) -> Result<bool, SecretError> {
+    let mut changed = false;
+
+    if let Some(token) = &server.token {
+        if reference_key(token).is_none() {
+            server.token = Some(store.store(&token_key(&server.id), token)?);
+            changed = true;
+        }
+    }
+
+    if let MCPTransport::Stdio { env, .. } = &mut server.transport {
+        for (name, value) in env.iter_mut() {
+            if is_secret_env(name) && !value.is_empty() && reference_key(value).is_none() {
+                *value = store.store(&env_key(&server.id, name), value)?;
+                changed = true;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Refuses references to secrets outside the server's namespace.
+pub fn check_references<'a>(
+    server_id: &str,
+    values: impl IntoIterator<Item = &'a String>,
+) -> Result<(), SecretError> {
+    for value in values {
+        if let Some(key) = reference_key(value).filter(|key| !owns(server_id, key)) {
+            return Err(SecretError::Forbidden(key.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Moves plaintext secret values of a project's env override into the
+/// store, under the server's namespace.
+pub fn externalize_override(
+    server_id: &str,
+    project_id: &str,
+    env: &mut HashMap<String, String>,
+    store: &SecretStore,
+) -> Result<(), SecretError> {
+    for (name, value) in env.iter_mut() {
+        if is_secret_env(name) && !value.is_empty() && reference_key(value).is_none() {
+            let key = project_env_key(server_id, project_id, name);
+            if !owns(server_id, &key) {
+                return Err(SecretError::Forbidden(key));
+            }
+            *value = store.store(&key, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the project's override secrets that `new` no longer refers to.
+pub fn forget_override(
+    server_id: &str,
+    project_id: &str,
+    old: &HashMap<String, String>,
+    new: Option<&HashMap<String, String>>,
+    store: &SecretStore,
+) -> Result<(), SecretError> {
+    let prefix = project_env_key(server_id, project_id, "");
+    let kept: HashSet<&str> = new.into_iter().flat_map(|env| env.values()).filter_map(|v| reference_key(v)).collect();
+    for key in old.values().filter_map(|v| reference_key(v)) {
+        if key.starts_with(&prefix) && !kept.contains(key) {
+            store.delete(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// The plaintext values `externalize` turned from `before` into `after`,
+/// each with the reference that replaced it.
+pub fn replaced_values(before: &MCPServer, after: &MCPServer) -> Vec<(String, String)> {
+    let mut replaced = Vec::new();
+    if let (Some(old), Some(new)) = (&before.token, &after.token) {
+        if old != new {
+            replaced.push((old.clone(), new.clone()));
+        }
+    }
+    if let (MCPTransport::Stdio { env: old, .. }, MCPTransport::Stdio { env: new, .. }) =
+        (&before.transport, &after.transport)
+    {
+        for (name, value) in old {
+            if let Some(reference) = new.get(name).filter(|new| *new != value) {
+                replaced.push((value.clone(), reference.clone()));
+            }
+        }
+    }
+    replaced
+}
+
+/// Returns a copy of the server with all references replaced by the secrets
+/// they point to. Only ever used for connecting, never persisted. References
+/// outside the server's namespace are refused.
+pub fn resolve(server: &MCPServer, store: &SecretStore) -> Result<MCPServer, SecretError> {
+    let mut resolved = server.clone();
+
+    if let Some(token) = &server.token {
+        resolved.token = Some(resolve_owned(&server.id, token, store)?);
+    }
+    if let MCPTransport::Stdio { env, .. } = &mut resolved.transport {
+        for value in env.values_mut() {
+            *value = resolve_owned(&server.id, value, store)?;
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Keys of the secrets a server refers to within its own namespace.
+fn referenced_keys(server: &MCPServer) -> HashSet<String> {
+    let env_values = match &server.transport {
+        MCPTransport::Stdio { env, .. } => env.values().collect(),
+        MCPTransport::Http { .. } => Vec::new(),
+    };
+
+    server
+        .token
+        .iter()
+        .chain(env_values)
+        .filter_map(|value| reference_key(value))
+        .filter(|key| owns(&server.id, key))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Deletes the secrets `old` referred to that `new` (if any) no longer uses.
+/// OAuth credentials go when the server is removed or moves elsewhere.
+pub fn forget(old: &MCPServer, new: Option<&MCPServer>, store: &SecretStore) -> Result<(), SecretError> {
+    let kept = new.map(referenced_keys).unwrap_or_default();
+    for key in referenced_keys(old).difference(&kept) {
+        store.delete(key)?;
+    }
+    if new.map(|s| &s.transport) != Some(&old.transport) {
+        forget_oauth(&old.id, store)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::MemoryBackend;
+
+    fn github() -> MCPServer {
+        MCPServer {
+            id: "gh".to_string(),
+            name: "github".to_string(),
+            transport: MCPTransport::Stdio {
+                command: "npx".to_string(),
+                args: vec!["-y".to_string(), "@modelcontextprotocol/server-github".to_string()],
+                env: HashMap::from([
+                    ("GITHUB_PERSONAL_ACCESS_TOKEN".to_string(), "ghp_x".to_string()),
+                    ("GITHUB_HOST".to_string(), "github.com".to_string()),
+                ]),
+                cwd: None,
+            },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }
+    }
+
+    #[test]
+    fn test_externalize_and_resolve() {
+        let store = SecretStore::new(MemoryBackend::default());
+        let original = github();
+
+        let mut server = original.clone();
+        assert!(externalize(&mut server, &store).unwrap());
+        // A second pass finds nothing left to move.
+        assert!(!externalize(&mut server, &store).unwrap());
+
+        let MCPTransport::Stdio { env, .. } = &server.transport else {
+            unreachable!()
+        };
+        assert_eq!(env["GITHUB_PERSONAL_ACCESS_TOKEN"], "secret://mcp/gh/env/GITHUB_PERSONAL_ACCESS_TOKEN");
+        assert_eq!(env["GITHUB_HOST"], "github.com");
+
+        assert_eq!(resolve(&server, &store).unwrap(), original);
+
+        forget(&server, None, &store).unwrap();
+        assert!(matches!(resolve(&server, &store), Err(SecretError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_forget_keeps_secrets_still_in_use() {
+        let store = SecretStore::new(MemoryBackend::default());
+        let mut old = MCPServer {
+            id: "remote".to_string(),
+            name: "remote".to_string(),
+            transport: MCPTransport::Http { url: "https://mcp.example.com".to_string() },
+            token: Some("abc".to_string()),
+            is_active: true,
+            last_connected: None,
+        };
+        externalize(&mut old, &store).unwrap();
+
+        forget(&old, Some(&old), &store).unwrap();
+        assert_eq!(resolve(&old, &store).unwrap().token.as_deref(), Some("abc"));
+
+        assert!(is_secret_env("OPENAI_API_KEY"));
+        assert!(is_secret_env("gitlab_pat"));
+        assert!(!is_secret_env("PATH"));
+    }
+
+    #[test]
+    fn test_references_outside_the_server_are_refused() {
+        let store = SecretStore::new(MemoryBackend::default());
+        store.set("anthropic-api-key", "sk-ant").unwrap();
+        store.set("mcp/other/oauth/refresh_token", "rt").unwrap();
+        store.set("mcp/gh-x/token", "ghp_other").unwrap();
+
+        let foreign = [
+            "secret://anthropic-api-key",
+            "secret://mcp/other/oauth/refresh_token",
+            "secret://mcp/gh-x/token",
+        ];
+        for reference in foreign {
+            let mut server = github();
+            server.token = Some(reference.to_string());
+            assert!(matches!(resolve(&server, &store), Err(SecretError::Forbidden(_))), "{}", reference);
+
+            // Removing the server leaves secrets it does not own alone.
+            forget(&server, None, &store).unwrap();
+        }
+        assert_eq!(store.get("anthropic-api-key").unwrap().as_deref(), Some("sk-ant"));
+        assert_eq!(store.get("mcp/other/oauth/refresh_token").unwrap().as_deref(), Some("rt"));
+        assert_eq!(store.get("mcp/gh-x/token").unwrap().as_deref(), Some("ghp_other"));
+    }
+}
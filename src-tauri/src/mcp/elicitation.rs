@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::events::EventSink;
+use super::notifications::{ActiveCall, ActiveCalls};
+use super::protocol::JsonRpcError;
+
+pub const ELICITATION_EVENT: &str = "mcp-elicitation";
+
+/// A form nobody fills in is cancelled. The tool call waiting on it does
+/// not time out meanwhile.
+const ELICITATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const STRING_FORMATS: &[&str] = &["email", "uri", "date", "date-time"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitationAction {
+    Accept,
+    Decline,
+    Cancel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FieldOption {
+    pub value: String,
+    pub label: String,
+}
+
+/// One input of the form, taken from a property of the requested schema.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub required: bool,
+    pub format: Option<String>,
+    /// Set for enum fields; the user picks one of these.
+    pub options: Vec<FieldOption>,
+    pub min_length: Option<u64>,
+    pub max_length: Option<u64>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub default: Option<Value>,
+}
+
+/// The property schema the spec allows for elicitation: a single
+/// primitive, no nesting.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PropertySchema {
+    #[serde(rename = "type")]
+    field_type: FieldType,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default, rename = "enum")]
+    options: Option<Vec<String>>,
+    #[serde(default)]
+    enum_names: Option<Vec<String>>,
+    #[serde(default)]
+    min_length: Option<u64>,
+    #[serde(default)]
+    max_length: Option<u64>,
+    #[serde(default)]
+    minimum: Option<f64>,
+    #[serde(default)]
+    maximum: Option<f64>,
+    #[serde(default)]
+    default: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ElicitParams {
+    message: String,
+    requested_schema: Value,
+}
+
+/// Turns the requested schema into form fields, required ones first.
+/// Anything beyond an object of primitive properties is rejected.
+pub fn form_fields(schema: &Value) -> Result<Vec<FormField>, JsonRpcError> {
+    let invalid = |message: String| JsonRpcError::invalid_params(format!("Invalid requested schema: {}", message));
+
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        return Err(invalid("the root must be an object".to_string()));
+    }
+    let empty = Map::new();
+    let properties = match schema.get("properties") {
+        Some(Value::Object(properties)) => properties,
+        None => &empty,
+        Some(_) => return Err(invalid("properties must be an object".to_string())),
+    };
+    let required: Vec<&str> = match schema.get("required") {
+        Some(Value::Array(names)) => names
+            .iter()
+            .map(|name| name.as_str().ok_or_else(|| invalid("required must list property names".to_string())))
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+        Some(_) => return Err(invalid("required must be an array".to_string())),
+    };
+    if let Some(name) = required.iter().find(|name| !properties.contains_key(**name)) {
+        return Err(invalid(format!("required property {} is not defined", name)));
+    }
+
+    let mut fields = properties
+        .iter()
+        .map(|(name, property)| {
+            let property: PropertySchema = serde_json::from_value(property.clone())
+                .map_err(|e| invalid(format!("{} is not a primitive field: {}", name, e)))?;
+
+            if let Some(format) = &property.format {
+                if property.field_type != FieldType::String || !STRING_FORMATS.contains(&format.as_str()) {
+                    return Err(invalid(format!("{} has an unsupported format {}", name, format)));
+                }
+            }
+            let options = match (property.options, property.enum_names) {
+                (None, None) => Vec::new(),
+                (Some(values), labels) if property.field_type == FieldType::String => {
+                    let labels = labels.unwrap_or_else(|| values.clone());
+                    if labels.len() != values.len() {
+                        return Err(invalid(format!("{} needs one enumName per enum value", name)));
+                    }
+                    values
+                        .into_iter()
+                        .zip(labels)
+                        .map(|(value, label)| FieldOption { value, label })
+                        .collect()
+                }
+                _ => return Err(invalid(format!("{} has enumNames or a non-string enum", name))),
+            };
+
+            Ok(FormField {
+                name: name.clone(),
+                field_type: property.field_type,
+                title: property.title,
+                description: property.description,
+                required: required.contains(&name.as_str()),
+                format: property.format,
+                options,
+                min_length: property.min_length,
+                max_length: property.max_length,
+                minimum: property.minimum,
+                maximum: property.maximum,
+                default: property.default,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    fields.sort_by_key(|field| !field.required);
+    Ok(fields)
+}
+
+/// Payload of [`ELICITATION_EVENT`]; answered via `respond_elicitation`.
+/// The call fields are set when a tool call of the server is running.
+#[derive(Debug, Serialize, Clone)]
+pub struct ElicitationRequest {
+    pub elicitation_id: String,
+    pub server_id: String,
+    pub message: String,
+    pub fields: Vec<FormField>,
+    pub call: Option<ActiveCall>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElicitationResponse {
+    pub action: ElicitationAction,
+    pub content: Option<Map<String, Value>>,
+}
+
+struct PendingForm {
+    schema: Value,
+    reply: oneshot::Sender<ElicitationResponse>,
+}
+
+/// Forms waiting for the user.
+#[derive(Default)]
+pub struct ElicitationBroker {
+    pending: Mutex<HashMap<String, PendingForm>>,
+}
+
+impl ElicitationBroker {
+    fn register(&self, elicitation_id: &str, schema: Value) -> oneshot::Receiver<ElicitationResponse> {
+        let (reply, rx) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(elicitation_id.to_string(), PendingForm { schema, reply });
+        }
+        rx
+    }
+
+    fn discard(&self, elicitation_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(elicitation_id);
+        }
+    }
+
+    /// Delivers the user's answer. Accepted content that does not match the
+    /// schema is refused and the form stays open.
+    pub fn resolve(&self, elicitation_id: &str, response: ElicitationResponse) -> Result<(), String> {
+        let mut pending = self.pending.lock().map_err(|e| e.to_string())?;
+        let form = pending
+            .get(elicitation_id)
+            .ok_or_else(|| format!("No pending elicitation: {}", elicitation_id))?;
+
+        let response = match response.action {
+            ElicitationAction::Accept => {
+                let content = Value::Object(response.content.clone().unwrap_or_default());
+                let validator = jsonschema::validator_for(&form.schema).map_err(|e| e.to_string())?;
+                let errors: Vec<String> = validator.iter_errors(&content).map(|e| e.to_string()).collect();
+                if !errors.is_empty() {
+                    return Err(errors.join("; "));
+                }
+                response
+            }
+            action => ElicitationResponse { action, content: None },
+        };
+
+        let form = pending.remove(elicitation_id).expect("checked above");
+        form.reply
+            .send(response)
+            .map_err(|_| format!("Elicitation {} is no longer awaited", elicitation_id))
+    }
+}
+
+fn reply(response: ElicitationResponse) -> Value {
+    match response.content {
+        Some(content) => json!({ "action": response.action, "content": content }),
+        None => json!({ "action": response.action }),
+    }
+}
+
+/// Answers `elicitation/create` from `server_id` with what the user entered.
+pub async fn create(
+    events: &dyn EventSink,
+    broker: &ElicitationBroker,
+    calls: &ActiveCalls,
+    server_id: &str,
+    params: Option<Value>,
+) -> Result<Value, JsonRpcError> {
+    let params: ElicitParams = serde_json::from_value(params.unwrap_or(Value::Null))
+        .map_err(|e| JsonRpcError::invalid_params(e.to_string()))?;
+    let fields = form_fields(&params.requested_schema)?;
+
+    let elicitation_id = Uuid::new_v4().to_string();
+    let response = broker.register(&elicitation_id, params.requested_schema);
+
+    let request = ElicitationRequest {
+        elicitation_id: elicitation_id.clone(),
+        server_id: server_id.to_string(),
+        message: params.message,
+        fields,
+        call: calls.latest_on(server_id),
+    };
+    if let Err(e) = events.emit_event(ELICITATION_EVENT, json!(request)) {
+        broker.discard(&elicitation_id);
+        return Err(JsonRpcError::internal(e));
+    }
+
+    let cancel = ElicitationResponse {
+        action: ElicitationAction::Cancel,
+        content: None,
+    };
+    match tokio::time::timeout(ELICITATION_TIMEOUT, response).await {
+        Ok(Ok(response)) => Ok(reply(response)),
+        Ok(Err(_)) => Ok(reply(cancel)),
+        Err(_) => {
+            broker.discard(&elicitation_id);
+            Ok(reply(cancel))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::events::tests::RecordedEvents;
+    use crate::mcp::protocol::INVALID_PARAMS;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "environment": {
+                    "type": "string", "title": "Environment",
+                    "enum": ["staging", "production"], "enumNames": ["Staging", "Production"]
+                },
+                "replicas": { "type": "integer", "minimum": 1, "maximum": 10 },
+                "confirm": { "type": "boolean", "default": false }
+            },
+            "required": ["environment", "confirm"]
+        })
+    }
+
+    fn accept(content: Value) -> ElicitationResponse {
+        ElicitationResponse {
+            action: ElicitationAction::Accept,
+            content: content.as_object().cloned(),
+        }
+    }
+
+    #[test]
+    fn test_only_flat_primitive_schemas_are_accepted() {
+        let fields = form_fields(&schema()).unwrap();
+        let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["confirm", "environment", "replicas"]);
+        assert_eq!(fields[1].options[1], FieldOption { value: "production".to_string(), label: "Production".to_string() });
+        assert!(fields[1].required && !fields[2].required);
+        assert_eq!(fields[0].default, Some(json!(false)));
+
+        let rejected = [
+            json!({ "type": "array" }),
+            json!({ "type": "object", "properties": { "address": { "type": "object", "properties": {} } } }),
+            json!({ "type": "object", "properties": { "tags": { "type": "array", "items": { "type": "string" } } } }),
+            json!({ "type": "object", "properties": { "at": { "type": "string", "format": "hostname" } } }),
+            json!({ "type": "object", "properties": { "n": { "type": "number", "enum": [1, 2] } } }),
+            json!({ "type": "object", "properties": {}, "required": ["missing"] }),
+        ];
+        for schema in rejected {
+            assert_eq!(form_fields(&schema).unwrap_err().code, INVALID_PARAMS, "{}", schema);
+        }
+    }
+
+    /// Waits for the form to show up.
+    async fn form(events: &RecordedEvents) -> Value {
+        let (event, payload) = events.next().await;
+        assert_eq!(event, ELICITATION_EVENT);
+        payload
+    }
+
+    #[tokio::test]
+    async fn test_elicitation_round_trip() {
+        let events = RecordedEvents::default();
+        let broker = ElicitationBroker::default();
+        let calls = ActiveCalls::default();
+        let params = json!({ "message": "Deploy which environment?", "requestedSchema": schema() });
+
+        let user = async {
+            let form = form(&events).await;
+            assert_eq!(form["message"], "Deploy which environment?");
+            assert_eq!(form["fields"][2]["type"], "integer");
+            let id = form["elicitation_id"].as_str().unwrap();
+
+            // Invalid content is refused and the form stays open.
+            let error = broker.resolve(id, accept(json!({ "environment": "dev", "confirm": true }))).unwrap_err();
+            assert!(error.contains("dev"));
+            broker
+                .resolve(id, accept(json!({ "environment": "staging", "replicas": 2, "confirm": true })))
+                .unwrap();
+        };
+        let (result, ()) = tokio::join!(create(&events, &broker, &calls, "deploy", Some(params.clone())), user);
+        assert_eq!(
+            result.unwrap(),
+            json!({ "action": "accept", "content": { "environment": "staging", "replicas": 2, "confirm": true } })
+        );
+
+        // Declining drops whatever content came along.
+        let user = async {
+            let id = form(&events).await["elicitation_id"].as_str().unwrap().to_string();
+            let response = ElicitationResponse {
+                action: ElicitationAction::Decline,
+                content: Some(Map::new()),
+            };
+            broker.resolve(&id, response).unwrap();
+        };
+        let (result, ()) = tokio::join!(create(&events, &broker, &calls, "deploy", Some(params)), user);
+        assert_eq!(result.unwrap(), json!({ "action": "decline" }));
+    }
+}
@@ -0,0 +1,51 @@
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Runtime};
+
+/// Where prompts and notifications for the webview go. The app emits them
+/// as Tauri events.
+pub trait EventSink: Send + Sync {
+    fn emit_event(&self, event: &str, payload: Value) -> Result<(), String>;
+}
+
+impl<R: Runtime> EventSink for AppHandle<R> {
+    fn emit_event(&self, event: &str, payload: Value) -> Result<(), String> {
+        self.emit(event, payload).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Keeps emitted events for assertions.
+    #[derive(Default)]
+    pub struct RecordedEvents(Mutex<Vec<(String, Value)>>);
+
+    impl RecordedEvents {
+        pub fn take(&self) -> Vec<(String, Value)> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+
+        /// Waits for the next event.
+        pub async fn next(&self) -> (String, Value) {
+            loop {
+                let event = {
+                    let mut events = self.0.lock().unwrap();
+                    (!events.is_empty()).then(|| events.remove(0))
+                };
+                if let Some(event) = event {
+                    return event;
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
+    impl EventSink for RecordedEvents {
+        fn emit_event(&self, event: &str, payload: Value) -> Result<(), String> {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+            Ok(())
+        }
+    }
+}
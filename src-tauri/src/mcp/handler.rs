@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::project::ActiveProject;
+
+use super::connections::ConnectionManager;
+use super::elicitation::{self, ElicitationBroker};
+use super::notifications::{report_log, report_progress, ActiveCalls};
+use super::protocol::{ClientHandler, JsonRpcError};
+use super::registry::{ToolRegistry, TOOLS_CHANGED_EVENT};
+use super::sampling;
+use super::tool_loop::ToolProvider;
+
+pub const RESOURCE_UPDATED_EVENT: &str = "mcp-resource-updated";
+pub const RESOURCES_CHANGED_EVENT: &str = "mcp-resources-changed";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResourceUpdatedPayload {
+    pub server_id: String,
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ServerEventPayload {
+    pub server_id: String,
+}
+
+/// Client-side handler of a connected server that forwards server
+/// notifications to the webview.
+pub struct AppClientHandler {
+    server_id: String,
+    app: AppHandle,
+}
+
+impl AppClientHandler {
+    pub fn new(server_id: impl Into<String>, app: AppHandle) -> Self {
+        Self {
+            server_id: server_id.into(),
+            app,
+        }
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        emit(&self.app, event, payload);
+    }
+
+    /// Re-fetches the server's tools in the background; requests cannot be
+    /// answered while a notification is being handled.
+    fn refresh_tools(&self) {
+        let registry = self.app.state::<ToolRegistry>();
+        registry.invalidate(&self.server_id);
+
+        let registry = registry.inner().clone();
+        let app = self.app.clone();
+        let server_id = self.server_id.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Some(session) = app.state::<ConnectionManager>().session(&server_id).await {
+                let provider: Arc<dyn ToolProvider> = session;
+                if let Err(e) = registry.refresh(&server_id, &provider).await {
+                    eprintln!("Failed to refresh tools of MCP server {}: {}", server_id, e);
+                }
+            }
+            emit(&app, TOOLS_CHANGED_EVENT, ServerEventPayload { server_id });
+        });
+    }
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("Failed to emit {}: {}", event, e);
+    }
+}
+
+#[async_trait]
+impl ClientHandler for AppClientHandler {
+    fn capabilities(&self) -> Value {
+        json!({ "roots": { "listChanged": true }, "sampling": {}, "elicitation": {} })
+    }
+
+    async fn handle_request(&self, method: &str, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        match method {
+            "ping" => Ok(json!({})),
+            "roots/list" => Ok(json!({ "roots": self.app.state::<ActiveProject>().roots() })),
+            "sampling/createMessage" => sampling::create_message(&self.app, &self.server_id, params).await,
+            "elicitation/create" => {
+                let app = &self.app;
+                let (broker, calls) = (app.state::<ElicitationBroker>(), app.state::<ActiveCalls>());
+                elicitation::create(app, &broker, &calls, &self.server_id, params).await
+            }
+            _ => Err(JsonRpcError::method_not_found(method)),
+        }
+    }
+
+    async fn handle_notification(&self, method: &str, params: Option<Value>) {
+        match method {
+            "notifications/resources/updated" => {
+                let Some(uri) = params.as_ref().and_then(|p| p["uri"].as_str()) else {
+                    return;
+                };
+                self.emit(
+                    RESOURCE_UPDATED_EVENT,
+                    ResourceUpdatedPayload {
+                        server_id: self.server_id.clone(),
+                        uri: uri.to_string(),
+                    },
+                );
+            }
+            "notifications/resources/list_changed" => {
+                self.emit(
+                    RESOURCES_CHANGED_EVENT,
+                    ServerEventPayload {
+                        server_id: self.server_id.clone(),
+                    },
+                );
+            }
+            "notifications/tools/list_changed" => self.refresh_tools(),
+            "notifications/progress" => {
+                report_progress(&self.app, &self.app.state::<ActiveCalls>(), &self.server_id, params)
+            }
+            "notifications/message" => {
+                report_log(&self.app, &self.app.state::<ActiveCalls>(), &self.server_id, params)
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::config::ConfigManager;
+use super::connections::ConnectionManager;
+use super::oauth;
+use super::supervisor::ProcessState;
+use super::types::MCPServer;
+
+pub const SERVER_STATUS_EVENT: &str = "mcp-server-status";
+
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pings slower than this mark a server as degraded.
+const DEGRADED_LATENCY: Duration = Duration::from_secs(2);
+/// Consecutive failed pings after which the session is given up.
+const MAX_MISSED_PINGS: u32 = 3;
+/// The monitor waits this long after a failed reconnect, doubling with
+/// every further failure up to `MAX_RECONNECT_DELAY`.
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(15);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30 * 60);
+
+fn reconnect_delay(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    RECONNECT_BACKOFF.saturating_mul(factor).min(MAX_RECONNECT_DELAY)
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerState {
+    Connecting,
+    Ready,
+    /// Connected, but pings are slow or some went unanswered.
+    Degraded,
+    Failed,
+    /// An OAuth server whose tokens are gone. Left alone until the user
+    /// connects it and signs in.
+    SignInRequired,
+    /// Inactive in the config or stopped by the user.
+    Disabled,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServerStatus {
+    pub server_id: String,
+    pub state: ServerState,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_checked: Option<String>,
+    pub last_connected: Option<String>,
+    #[serde(skip)]
+    missed_pings: u32,
+    /// Stopped on request; the monitor leaves it down until started again.
+    #[serde(skip)]
+    stopped: bool,
+    /// Failed connection attempts in a row.
+    #[serde(skip)]
+    connect_failures: u32,
+    /// The monitor does not reconnect before this.
+    #[serde(skip)]
+    retry_at: Option<Instant>,
+}
+
+impl ServerStatus {
+    fn new(server_id: &str, state: ServerState) -> Self {
+        Self {
+            server_id: server_id.to_string(),
+            state,
+            latency_ms: None,
+            last_error: None,
+            last_checked: None,
+            last_connected: None,
+            missed_pings: 0,
+            stopped: false,
+            connect_failures: 0,
+            retry_at: None,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Whether the monitor may try to connect the server now.
+    fn may_reconnect(&self, now: Instant) -> bool {
+        !self.stopped
+            && self.state != ServerState::SignInRequired
+            && self.retry_at.is_none_or(|at| at <= now)
+    }
+
+    /// Whether the UI needs to hear about the change from `other`.
+    fn differs_from(&self, other: &Self) -> bool {
+        self.state != other.state
+            || self.last_error != other.last_error
+            || self.last_connected != other.last_connected
+    }
+}
+
+/// Called with the new status whenever a server's state changes.
+pub type StatusListener = Box<dyn Fn(&ServerStatus) + Send + Sync>;
+
+/// Current health of every server the app has dealt with.
+#[derive(Default)]
+pub struct StatusBoard {
+    statuses: Mutex<HashMap<String, ServerStatus>>,
+    listener: Option<StatusListener>,
+}
+
+impl StatusBoard {
+    pub fn with_listener(listener: StatusListener) -> Self {
+        Self {
+            statuses: Mutex::default(),
+            listener: Some(listener),
+        }
+    }
+
+    fn map(&self) -> std::sync::MutexGuard<'_, HashMap<String, ServerStatus>> {
+        self.statuses.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn get(&self, server_id: &str) -> Option<ServerStatus> {
+        self.map().get(server_id).cloned()
+    }
+
+    /// The status of a configured server, including ones not seen yet.
+    pub fn status_of(&self, server: &MCPServer) -> ServerStatus {
+        self.get(&server.id).unwrap_or_else(|| {
+            let state = if server.is_active {
+                ServerState::Connecting
+            } else {
+                ServerState::Disabled
+            };
+            ServerStatus {
+                last_connected: server.last_connected.clone(),
+                ..ServerStatus::new(&server.id, state)
+            }
+        })
+    }
+
+    fn update(&self, server_id: &str, f: impl FnOnce(&mut ServerStatus)) -> ServerStatus {
+        let (before, after) = {
+            let mut statuses = self.map();
+            let before = statuses.get(server_id).cloned();
+            let status = statuses
+                .entry(server_id.to_string())
+                .or_insert_with(|| ServerStatus::new(server_id, ServerState::Connecting));
+            f(status);
+            (before, status.clone())
+        };
+
+        if before.is_none_or(|before| after.differs_from(&before)) {
+            if let Some(listener) = &self.listener {
+                listener(&after);
+            }
+        }
+        after
+    }
+
+    pub fn connecting(&self, server_id: &str) {
+        self.update(server_id, |status| {
+            status.state = ServerState::Connecting;
+            status.stopped = false;
+        });
+    }
+
+    pub fn connected(&self, server_id: &str) {
+        let now = Utc::now().to_rfc3339();
+        self.update(server_id, |status| {
+            status.state = ServerState::Ready;
+            status.last_error = None;
+            status.missed_pings = 0;
+            status.connect_failures = 0;
+            status.retry_at = None;
+            status.last_checked = Some(now.clone());
+            status.last_connected = Some(now);
+        });
+    }
+
+    /// Records a failed connection attempt and backs off further ones.
+    pub fn connect_failed(&self, server_id: &str, error: impl Into<String>) {
+        self.failed(server_id, error);
+        self.update(server_id, |status| {
+            status.connect_failures += 1;
+            status.retry_at = Some(Instant::now() + reconnect_delay(status.connect_failures));
+        });
+    }
+
+    pub fn sign_in_required(&self, server_id: &str, error: impl Into<String>) {
+        self.update(server_id, |status| {
+            status.state = ServerState::SignInRequired;
+            status.latency_ms = None;
+            status.last_error = Some(error.into());
+            status.last_checked = Some(Utc::now().to_rfc3339());
+        });
+    }
+
+    pub fn failed(&self, server_id: &str, error: impl Into<String>) {
+        self.update(server_id, |status| {
+            status.state = ServerState::Failed;
+            status.latency_ms = None;
+            status.last_error = Some(error.into());
+            status.last_checked = Some(Utc::now().to_rfc3339());
+        });
+    }
+
+    pub fn stopped(&self, server_id: &str) {
+        self.update(server_id, |status| {
+            status.state = ServerState::Disabled;
+            status.latency_ms = None;
+            status.stopped = true;
+        });
+    }
+
+    fn disabled(&self, server_id: &str) {
+        self.update(server_id, |status| {
+            status.state = ServerState::Disabled;
+            status.latency_ms = None;
+        });
+    }
+
+    fn answered(&self, server_id: &str, latency: Duration) {
+        self.update(server_id, |status| {
+            status.state = if latency > DEGRADED_LATENCY {
+                ServerState::Degraded
+            } else {
+                ServerState::Ready
+            };
+            status.latency_ms = Some(latency.as_millis() as u64);
+            status.last_error = None;
+            status.missed_pings = 0;
+            status.last_checked = Some(Utc::now().to_rfc3339());
+        });
+    }
+
+    /// Records an unanswered ping and returns how many were missed in a row.
+    fn missed(&self, server_id: &str, error: impl Into<String>) -> u32 {
+        self.update(server_id, |status| {
+            status.state = ServerState::Degraded;
+            status.last_error = Some(error.into());
+            status.missed_pings += 1;
+            status.last_checked = Some(Utc::now().to_rfc3339());
+        })
+        .missed_pings
+    }
+
+    fn retain(&self, servers: &[MCPServer]) {
+        self.map().retain(|id, _| servers.iter().any(|s| &s.id == id));
+    }
+}
+
+/// Emits status changes to the webview. Connection times stay on the board;
+/// the user's config is not rewritten on every connect.
+pub fn status_listener(app: AppHandle) -> StatusListener {
+    Box::new(move |status| {
+        if let Err(e) = app.emit(SERVER_STATUS_EVENT, status.clone()) {
+            eprintln!("Failed to emit {}: {}", SERVER_STATUS_EVENT, e);
+        }
+    })
+}
+
+/// Pings every connected server and (re)connects active servers that are
+/// down, unless they were stopped on purpose, need a sign-in, or failed
+/// recently. Neither pings nor reconnects ever ask the user to sign in.
+pub async fn check(connections: &ConnectionManager, servers: &[MCPServer]) {
+    let board = connections.status();
+    board.retain(servers);
+
+    for server in servers {
+        match connections.session(&server.id).await {
+            Some(session) => match oauth::unattended(session.ping(PING_TIMEOUT)).await {
+                Ok(latency) => board.answered(&server.id, latency),
+                Err(e) if e.code == oauth::SIGN_IN_REQUIRED => {
+                    connections.close_session(&server.id).await;
+                    board.sign_in_required(&server.id, e.message);
+                }
+                Err(e) => {
+                    if board.missed(&server.id, e.message.clone()) >= MAX_MISSED_PINGS {
+                        connections.close_session(&server.id).await;
+                        board.failed(&server.id, e.message);
+                    }
+                }
+            },
+            None if !server.is_active => board.disabled(&server.id),
+            None => {
+                let status = board.get(&server.id);
+                if status.as_ref().is_some_and(|s| !s.may_reconnect(Instant::now())) {
+                    continue;
+                }
+                // The supervisor is already bringing the process back.
+                if matches!(
+                    connections.supervisor().state(&server.id),
+                    Some(ProcessState::Starting | ProcessState::Restarting { .. })
+                ) {
+                    continue;
+                }
+                if status.is_some_and(|s| matches!(s.state, ServerState::Ready | ServerState::Degraded)) {
+                    board.failed(&server.id, "Connection lost");
+                }
+                // Records its own status.
+                let _ = oauth::unattended(connections.connect(server)).await;
+            }
+        }
+    }
+}
+
+/// Starts the background health monitor.
+pub fn spawn_monitor(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(PING_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let servers = match app.state::<Mutex<ConfigManager>>().lock() {
+                Ok(config) => config.effective_servers(),
+                Err(e) => {
+                    eprintln!("Health monitor cannot read MCP config: {}", e);
+                    continue;
+                }
+            };
+            check(&app.state::<ConnectionManager>(), &servers).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::mcp::oauth::{AuthorizationPrompt, OAuthClients};
+    use crate::mcp::types::MCPTransport;
+    use crate::secrets::{MemoryBackend, SecretStore};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn recording_board() -> (StatusBoard, Arc<Mutex<Vec<ServerState>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let board = StatusBoard::with_listener(Box::new(move |status| {
+            sink.lock().unwrap().push(status.state);
+        }));
+        (board, seen)
+    }
+
+    #[test]
+    fn test_status_transitions_are_reported_once() {
+        let (board, seen) = recording_board();
+
+        board.connecting("a");
+        board.connected("a");
+        board.answered("a", Duration::from_millis(5));
+        board.answered("a", Duration::from_millis(7));
+        assert_eq!(board.get("a").unwrap().latency_ms, Some(7));
+
+        board.answered("a", Duration::from_secs(3));
+        assert_eq!(board.missed("a", "timeout"), 1);
+        assert_eq!(board.missed("a", "timeout"), 2);
+        board.failed("a", "timeout");
+        board.stopped("a");
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ServerState::Connecting,
+                ServerState::Ready,
+                ServerState::Degraded,
+                // Same state, but the error changed.
+                ServerState::Degraded,
+                ServerState::Failed,
+                ServerState::Disabled,
+            ]
+        );
+
+        let status = board.get("a").unwrap();
+        assert!(status.stopped);
+        assert!(status.last_connected.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_check_respects_inactive_and_stopped_servers() {
+        let connections = ConnectionManager::new();
+        let server = |id: &str, is_active| MCPServer {
+            id: id.to_string(),
+            name: id.to_string(),
+            // Nothing listens on port 1, so connecting fails right away.
+            transport: MCPTransport::Http { url: "http://127.0.0.1:1/mcp".to_string() },
+            token: None,
+            is_active,
+            last_connected: None,
+        };
+        let servers = vec![server("inactive", false), server("stopped", true), server("broken", true)];
+        connections.status().stopped("stopped");
+        connections.status().stopped("gone");
+
+        check(&connections, &servers).await;
+
+        let board = connections.status();
+        assert_eq!(board.get("inactive").unwrap().state, ServerState::Disabled);
+        assert_eq!(board.get("stopped").unwrap().state, ServerState::Disabled);
+        let broken = board.get("broken").unwrap();
+        assert_eq!(broken.state, ServerState::Failed);
+        assert!(broken.last_error.is_some());
+        assert!(board.get("gone").is_none());
+
+        // A failed server is left alone until its backoff has passed.
+        assert_eq!(broken.connect_failures, 1);
+        check(&connections, &servers).await;
+        assert_eq!(board.get("broken").unwrap(), broken);
+        assert!(broken.may_reconnect(Instant::now() + reconnect_delay(1)));
+        assert_eq!(reconnect_delay(3), Duration::from_secs(60));
+        assert_eq!(reconnect_delay(30), MAX_RECONNECT_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_never_starts_a_sign_in() {
+        let remote = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&remote)
+            .await;
+        let prompts = Arc::new(AtomicUsize::new(0));
+        let counter = prompts.clone();
+        let secrets = Arc::new(SecretStore::new(MemoryBackend::default()));
+        let prompt: AuthorizationPrompt = Arc::new(move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err("no browser here".to_string())
+        });
+        let connections = ConnectionManager::new()
+            .with_secrets(secrets.clone())
+            .with_oauth(OAuthClients::new(secrets, prompt));
+        let servers = vec![MCPServer {
+            id: "remote".to_string(),
+            name: "remote".to_string(),
+            transport: MCPTransport::Http { url: format!("{}/mcp", remote.uri()) },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }];
+
+        check(&connections, &servers).await;
+        let status = connections.status().get("remote").unwrap();
+        assert_eq!(status.state, ServerState::SignInRequired);
+        assert_eq!(prompts.load(Ordering::SeqCst), 0);
+
+        // Later checks wait for the user instead of trying again.
+        let requests = remote.received_requests().await.unwrap().len();
+        check(&connections, &servers).await;
+        assert_eq!(remote.received_requests().await.unwrap().len(), requests);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_during_a_ping_needs_a_sign_in() {
+        let remote = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "initialize" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "serverInfo": { "name": "remote", "version": "1.0" }
+            } })))
+            .mount(&remote)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&remote)
+            .await;
+
+        let prompts = Arc::new(AtomicUsize::new(0));
+        let counter = prompts.clone();
+        let secrets = Arc::new(SecretStore::new(MemoryBackend::default()));
+        let prompt: AuthorizationPrompt = Arc::new(move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Err("no browser here".to_string())
+        });
+        let connections = ConnectionManager::new()
+            .with_secrets(secrets.clone())
+            .with_oauth(OAuthClients::new(secrets, prompt));
+        let servers = vec![MCPServer {
+            id: "remote".to_string(),
+            name: "remote".to_string(),
+            transport: MCPTransport::Http { url: format!("{}/mcp", remote.uri()) },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }];
+
+        check(&connections, &servers).await;
+        assert_eq!(connections.status().get("remote").unwrap().state, ServerState::Ready);
+
+        // The server now rejects the session's token.
+        remote.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&remote)
+            .await;
+
+        check(&connections, &servers).await;
+        assert_eq!(connections.status().get("remote").unwrap().state, ServerState::SignInRequired);
+        assert!(connections.session("remote").await.is_none());
+        assert_eq!(prompts.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,702 @@
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, WWW_AUTHENTICATE};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+use super::oauth::OAuthClient;
+use super::protocol::{JsonRpcError, JsonRpcMessage, RequestId};
+use super::transport::{Incoming, Transport};
+use super::types::MCPError;
+use crate::sse::SseParser;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+const EVENT_STREAM: &str = "text/event-stream";
+
+const DEFAULT_RETRY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_ATTEMPTS: usize = 3;
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn connection_error(e: reqwest::Error) -> MCPError {
+    MCPError::new("CONNECTION_ERROR", e.to_string())
+}
+
+fn http_error(status: StatusCode) -> MCPError {
+    let mut error = MCPError::new("HTTP_ERROR", format!("Server responded with status {}", status));
+    error.details = Some([("status".to_string(), Value::from(status.as_u16()))].into());
+    error
+}
+
+/// Whether a failed Streamable HTTP handshake suggests the server only speaks
+/// the older HTTP+SSE transport.
+pub fn is_legacy_fallback(error: &MCPError) -> bool {
+    let status = error
+        .details
+        .as_ref()
+        .and_then(|d| d.get("status"))
+        .and_then(Value::as_u64);
+    error.code == "HTTP_ERROR" && matches!(status, Some(400 | 404 | 405))
+}
+
+/// A body may hold a single message or a batch.
+fn parse_messages(data: &[u8]) -> Result<Vec<JsonRpcMessage>, serde_json::Error> {
+    match serde_json::from_slice(data)? {
+        Value::Array(items) => items.into_iter().map(serde_json::from_value).collect(),
+        value => Ok(vec![serde_json::from_value(value)?]),
+    }
+}
+
+struct Shared {
+    http: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    oauth: Option<Arc<OAuthClient>>,
+    session_id: StdMutex<Option<String>>,
+    protocol_version: StdMutex<Option<String>>,
+    incoming: mpsc::UnboundedSender<Incoming>,
+}
+
+impl Shared {
+    fn session_id(&self) -> Option<String> {
+        self.session_id.lock().ok()?.clone()
+    }
+
+    fn set_session_id(&self, id: Option<String>) {
+        if let Ok(mut session_id) = self.session_id.lock() {
+            *session_id = id;
+        }
+    }
+
+    fn bearer(&self) -> Option<String> {
+        match &self.oauth {
+            Some(oauth) => oauth.access_token(),
+            None => self.token.clone(),
+        }
+    }
+
+    fn request(&self, method: Method) -> RequestBuilder {
+        let mut request = self.http.request(method, &self.url);
+        if let Some(token) = self.bearer() {
+            request = request.bearer_auth(token);
+        }
+        if let Some(id) = self.session_id() {
+            request = request.header(SESSION_ID_HEADER, id);
+        }
+        if let Some(version) = self.protocol_version.lock().ok().and_then(|v| v.clone()) {
+            request = request.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        request
+    }
+
+    fn deliver(&self, message: JsonRpcMessage) {
+        let _ = self.incoming.send(Incoming::Message(message));
+    }
+
+    /// Opens (or resumes) a server-to-client SSE stream with a GET request.
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Option<Response> {
+        let mut request = self.request(Method::GET).header(ACCEPT, EVENT_STREAM);
+        if let Some(id) = last_event_id {
+            request = request.header(LAST_EVENT_ID_HEADER, id);
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Some(response),
+            // The server does not offer a standalone stream.
+            Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => None,
+            Ok(response) => {
+                eprintln!("Failed to open MCP event stream: {}", response.status());
+                None
+            }
+            Err(e) => {
+                eprintln!("Failed to open MCP event stream: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Reads one SSE response until it ends. Returns whether the awaited
+/// response arrived.
+async fn pump(
+    shared: &Shared,
+    mut response: Response,
+    awaiting: Option<&RequestId>,
+    last_event_id: &mut Option<String>,
+    retry: &mut Duration,
+) -> bool {
+    let mut parser = SseParser::new();
+    let mut answered = false;
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("MCP event stream interrupted: {}", e);
+                break;
+            }
+        };
+
+        let events = parser.push(&chunk);
+        // Priming events carry only an id, so take it from the parser.
+        if let Some(id) = parser.last_event_id() {
+            *last_event_id = Some(id.to_string());
+        }
+        if let Some(ms) = parser.retry() {
+            *retry = Duration::from_millis(ms);
+        }
+
+        for event in events {
+            match parse_messages(event.data.as_bytes()) {
+                Ok(messages) => {
+                    for message in messages {
+                        if let (JsonRpcMessage::Response(response), Some(id)) = (&message, awaiting) {
+                            answered |= response.id == *id;
+                        }
+                        shared.deliver(message);
+                    }
+                }
+                Err(e) => eprintln!("Ignoring malformed MCP event: {}", e),
+            }
+        }
+    }
+
+    answered
+}
+
+/// Follows an SSE stream, resuming it with `Last-Event-ID` when it breaks
+/// off. Streams opened for a request end once its response has arrived.
+async fn follow_stream(shared: Arc<Shared>, mut response: Response, awaiting: Option<RequestId>) {
+    let mut last_event_id = None;
+    let mut retry = DEFAULT_RETRY;
+
+    loop {
+        if pump(&shared, response, awaiting.as_ref(), &mut last_event_id, &mut retry).await {
+            return;
+        }
+        if awaiting.is_some() && last_event_id.is_none() {
+            break;
+        }
+
+        let mut resumed = None;
+        for _ in 0..MAX_RECONNECT_ATTEMPTS {
+            tokio::time::sleep(retry).await;
+            resumed = shared.open_stream(last_event_id.as_deref()).await;
+            if resumed.is_some() {
+                break;
+            }
+        }
+        match resumed {
+            Some(next) => response = next,
+            None => break,
+        }
+    }
+
+    if let Some(id) = awaiting {
+        shared.deliver(JsonRpcMessage::response(
+            id,
+            Err(JsonRpcError::internal("Event stream closed before the response arrived")),
+        ));
+    }
+}
+
+/// The Streamable HTTP transport: every message is POSTed to the server,
+/// which answers with JSON or an SSE stream. Server-initiated messages
+/// arrive on an optional GET stream.
+pub struct StreamableHttpTransport {
+    shared: Arc<Shared>,
+    incoming: Mutex<mpsc::UnboundedReceiver<Incoming>>,
+    streams: StdMutex<Vec<JoinHandle<()>>>,
+    listener: StdMutex<Option<JoinHandle<()>>>,
+}
+
+impl StreamableHttpTransport {
+    pub fn new(url: impl Into<String>, token: Option<String>) -> Self {
+        Self::build(url.into(), token, None)
+    }
+
+    /// Authorizes with `oauth`, signing in when the server asks for it.
+    pub fn with_oauth(url: impl Into<String>, oauth: Arc<OAuthClient>) -> Self {
+        Self::build(url.into(), None, Some(oauth))
+    }
+
+    fn build(url: String, token: Option<String>, oauth: Option<Arc<OAuthClient>>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            shared: Arc::new(Shared {
+                http: reqwest::Client::new(),
+                url,
+                token,
+                oauth,
+                session_id: StdMutex::new(None),
+                protocol_version: StdMutex::new(None),
+                incoming: tx,
+            }),
+            incoming: Mutex::new(rx),
+            streams: StdMutex::new(Vec::new()),
+            listener: StdMutex::new(None),
+        }
+    }
+
+    fn track(&self, task: JoinHandle<()>) {
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.retain(|t| !t.is_finished());
+            streams.push(task);
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError> {
+        let had_session = self.shared.session_id().is_some();
+        let post = || {
+            self.shared
+                .request(Method::POST)
+                .header(ACCEPT, format!("application/json, {}", EVENT_STREAM))
+                .json(&message)
+                .send()
+        };
+        let mut response = post().await.map_err(connection_error)?;
+
+        // An expired or missing token: renew it and try once more.
+        if let (StatusCode::UNAUTHORIZED, Some(oauth)) = (response.status(), &self.shared.oauth) {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let rejected = self.shared.bearer();
+            oauth.authorize(rejected.as_deref(), challenge.as_deref()).await?;
+            response = post().await.map_err(connection_error)?;
+        }
+
+        if let Some(id) = response.headers().get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) {
+            self.shared.set_session_id(Some(id.to_string()));
+        }
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND && had_session {
+            // The server dropped our session; a fresh handshake is needed.
+            self.shared.set_session_id(None);
+            let _ = self.shared.incoming.send(Incoming::Reconnected);
+            return Err(MCPError::new("SESSION_EXPIRED", "MCP session expired"));
+        }
+        if !status.is_success() {
+            return Err(http_error(status));
+        }
+        if status == StatusCode::ACCEPTED {
+            return Ok(());
+        }
+
+        let is_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with(EVENT_STREAM));
+
+        if is_stream {
+            let awaiting = match &message {
+                JsonRpcMessage::Request(request) => Some(request.id.clone()),
+                _ => None,
+            };
+            self.track(tokio::spawn(follow_stream(self.shared.clone(), response, awaiting)));
+            return Ok(());
+        }
+
+        let body = response.bytes().await.map_err(connection_error)?;
+        if body.is_empty() {
+            return Ok(());
+        }
+        let messages = parse_messages(&body).map_err(|e| MCPError::new("INVALID_RESPONSE", e.to_string()))?;
+        for message in messages {
+            self.shared.deliver(message);
+        }
+        Ok(())
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        self.incoming.lock().await.recv().await
+    }
+
+    async fn close(&self) {
+        if let Ok(mut listener) = self.listener.lock() {
+            if let Some(task) = listener.take() {
+                task.abort();
+            }
+        }
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.drain(..).for_each(|t| t.abort());
+        }
+
+        if self.shared.session_id().is_some() {
+            let _ = self.shared.request(Method::DELETE).send().await;
+            self.shared.set_session_id(None);
+        }
+    }
+
+    fn set_protocol_version(&self, version: &str) {
+        if let Ok(mut protocol_version) = self.shared.protocol_version.lock() {
+            *protocol_version = Some(version.to_string());
+        }
+
+        // Open the standalone stream for server-initiated messages. A
+        // re-initialized session replaces the previous listener.
+        let shared = self.shared.clone();
+        let task = tokio::spawn(async move {
+            if let Some(response) = shared.open_stream(None).await {
+                follow_stream(shared, response, None).await;
+            }
+        });
+        if let Ok(mut listener) = self.listener.lock() {
+            if let Some(previous) = listener.replace(task) {
+                previous.abort();
+            }
+        }
+    }
+}
+
+/// The HTTP+SSE transport from protocol version 2024-11-05: a long-lived GET
+/// stream announces a POST endpoint and carries all server messages.
+pub struct LegacySseTransport {
+    http: reqwest::Client,
+    endpoint: String,
+    token: Option<String>,
+    incoming: Mutex<mpsc::UnboundedReceiver<Incoming>>,
+    reader: JoinHandle<()>,
+}
+
+impl LegacySseTransport {
+    pub async fn connect(url: &str, token: Option<String>) -> Result<Self, MCPError> {
+        let http = reqwest::Client::new();
+        let mut request = http.get(url).header(ACCEPT, EVENT_STREAM);
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(connection_error)?;
+        if !response.status().is_success() {
+            return Err(http_error(response.status()));
+        }
+
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let reader = tokio::spawn(read_legacy_stream(response, endpoint_tx, tx));
+
+        let endpoint = match tokio::time::timeout(ENDPOINT_TIMEOUT, endpoint_rx).await {
+            Ok(Ok(endpoint)) => endpoint,
+            _ => {
+                reader.abort();
+                return Err(MCPError::new("INVALID_RESPONSE", "Server did not announce an endpoint"));
+            }
+        };
+        let endpoint = reqwest::Url::parse(url)
+            .and_then(|base| base.join(&endpoint))
+            .map_err(|e| MCPError::new("INVALID_RESPONSE", e.to_string()))?;
+
+        Ok(Self {
+            http,
+            endpoint: endpoint.to_string(),
+            token,
+            incoming: Mutex::new(rx),
+            reader,
+        })
+    }
+}
+
+async fn read_legacy_stream(
+    mut response: Response,
+    endpoint: oneshot::Sender<String>,
+    incoming: mpsc::UnboundedSender<Incoming>,
+) {
+    let mut endpoint = Some(endpoint);
+    let mut parser = SseParser::new();
+
+    while let Ok(Some(chunk)) = response.chunk().await {
+        for event in parser.push(&chunk) {
+            match event.event.as_deref() {
+                Some("endpoint") => {
+                    if let Some(tx) = endpoint.take() {
+                        let _ = tx.send(event.data);
+                    }
+                }
+                None | Some("message") => match parse_messages(event.data.as_bytes()) {
+                    Ok(messages) => {
+                        for message in messages {
+                            let _ = incoming.send(Incoming::Message(message));
+                        }
+                    }
+                    Err(e) => eprintln!("Ignoring malformed MCP event: {}", e),
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for LegacySseTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError> {
+        let mut request = self.http.post(&self.endpoint).json(&message);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.map_err(connection_error)?;
+        if !response.status().is_success() {
+            return Err(http_error(response.status()));
+        }
+        Ok(())
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        self.incoming.lock().await.recv().await
+    }
+
+    async fn close(&self) {
+        self.reader.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::protocol::{DefaultClientHandler, McpSession};
+    use crate::mcp::types::ToolContent;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use wiremock::matchers::{body_partial_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn initialize_result() -> Value {
+        json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "remote", "version": "1.0" }
+        })
+    }
+
+    fn sse(events: &[(Option<&str>, Value)]) -> String {
+        events
+            .iter()
+            .map(|(id, data)| match id {
+                Some(id) => format!("retry: 10\nid: {}\ndata: {}\n\n", id, data),
+                None => format!("data: {}\n\n", data),
+            })
+            .collect()
+    }
+
+    async fn mount_handshake(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_partial_json(json!({ "method": "initialize" })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Mcp-Session-Id", "session-1")
+                    .set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": initialize_result() })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(header("mcp-session-id", "session-1"))
+            .and(body_partial_json(json!({ "method": "notifications/initialized" })))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_json_and_sse_responses() {
+        let server = MockServer::start().await;
+        mount_handshake(&server).await;
+
+        Mock::given(method("POST"))
+            .and(header("mcp-session-id", "session-1"))
+            .and(header("mcp-protocol-version", "2025-06-18"))
+            .and(body_partial_json(json!({ "method": "tools/list" })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                sse(&[
+                    (None, json!({ "jsonrpc": "2.0", "method": "notifications/message", "params": {} })),
+                    (None, json!({ "jsonrpc": "2.0", "id": 2, "result": { "tools": [
+                        { "name": "search", "inputSchema": { "type": "object" } }
+                    ] } })),
+                ]),
+                EVENT_STREAM,
+            ))
+            .mount(&server)
+            .await;
+
+        let transport = StreamableHttpTransport::new(server.uri(), Some("secret".to_string()));
+        let session = McpSession::connect(Arc::new(transport), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        let tools = session.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "search");
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_resumes_with_last_event_id() {
+        let server = MockServer::start().await;
+        mount_handshake(&server).await;
+
+        // The POST stream breaks off before the response is sent...
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "tools/call" })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                sse(&[(
+                    Some("evt-1"),
+                    json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": { "progress": 1 } }),
+                )]),
+                EVENT_STREAM,
+            ))
+            .mount(&server)
+            .await;
+
+        // ...and is resumed on a GET carrying the last event id.
+        Mock::given(method("GET"))
+            .and(header("last-event-id", "evt-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                sse(&[(
+                    Some("evt-2"),
+                    json!({ "jsonrpc": "2.0", "id": 2, "result": { "content": [{ "type": "text", "text": "done" }] } }),
+                )]),
+                EVENT_STREAM,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+
+        let transport = StreamableHttpTransport::new(server.uri(), Some("secret".to_string()));
+        let session = McpSession::connect(Arc::new(transport), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        let result = session.call_tool("slow", json!({})).await.unwrap();
+        assert_eq!(result.content, vec![ToolContent::Text { text: "done".to_string() }]);
+    }
+
+    /// Stand-in for a server that only speaks HTTP+SSE: POSTs to the base
+    /// URL are rejected, GET opens the event stream and POSTs to /messages
+    /// are answered on that stream.
+    async fn spawn_legacy_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (events_tx, events_rx) = mpsc::unbounded_channel::<String>();
+        let events_rx = Arc::new(Mutex::new(events_rx));
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let events_tx = events_tx.clone();
+                let events_rx = events_rx.clone();
+                tokio::spawn(handle_legacy_connection(socket, events_tx, events_rx));
+            }
+        });
+
+        format!("http://{}/sse", address)
+    }
+
+    async fn handle_legacy_connection(
+        mut socket: TcpStream,
+        events_tx: mpsc::UnboundedSender<String>,
+        events_rx: Arc<Mutex<mpsc::UnboundedReceiver<String>>>,
+    ) {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        let request_line = head.lines().next().unwrap_or_default().to_string();
+        let content_length = head
+            .lines()
+            .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        while buffer.len() < header_end + content_length {
+            let n = socket.read(&mut chunk).await.unwrap();
+            buffer.extend_from_slice(&chunk[..n]);
+        }
+        let body = &buffer[header_end..header_end + content_length];
+
+        if request_line.starts_with("GET /sse") {
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            socket.write_all(b"event: endpoint\ndata: /messages?session=1\n\n").await.unwrap();
+            let mut events = events_rx.lock().await;
+            while let Some(event) = events.recv().await {
+                if socket.write_all(event.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+        } else if request_line.starts_with("POST /messages?session=1") {
+            let message: Value = serde_json::from_slice(body).unwrap();
+            let result = match message["method"].as_str() {
+                Some("initialize") => Some(json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "legacy", "version": "0.1" }
+                })),
+                Some("tools/list") => Some(json!({ "tools": [{ "name": "old", "inputSchema": {} }] })),
+                _ => None,
+            };
+            if let (Some(result), Some(id)) = (result, message.get("id")) {
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                let _ = events_tx.send(format!("event: message\ndata: {}\n\n", response));
+            }
+            let _ = socket
+                .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        } else {
+            let _ = socket
+                .write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_legacy_sse() {
+        let url = spawn_legacy_server().await;
+
+        let streamable = StreamableHttpTransport::new(url.clone(), None);
+        let error = McpSession::connect(Arc::new(streamable), Arc::new(DefaultClientHandler))
+            .await
+            .err()
+            .unwrap();
+        assert!(is_legacy_fallback(&error));
+
+        let legacy = LegacySseTransport::connect(&url, None).await.unwrap();
+        let session = McpSession::connect(Arc::new(legacy), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        assert_eq!(session.server_info().unwrap().server_info.name, "legacy");
+        assert_eq!(session.list_tools().await.unwrap()[0].name, "old");
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::secrets::reference_key;
+
+use super::config::ConfigError;
+use super::types::{MCPServer, MCPTransport};
+
+/// A server entry as written by Claude Desktop, Cursor or VS Code.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalServer {
+    #[serde(rename = "type")]
+    server_type: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    cwd: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SkippedServer {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Outcome of an import: the servers that were added and the ones left out.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    pub added: Vec<MCPServer>,
+    pub skipped: Vec<SkippedServer>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// `claude_desktop_config.json`; only stdio servers.
+    ClaudeDesktop,
+    /// `.cursor/mcp.json`
+    Cursor,
+    /// `.vscode/mcp.json`
+    VsCode,
+}
+
+/// Location of the Claude Desktop config on this platform.
+pub fn claude_desktop_config_path() -> Option<PathBuf> {
+    let dirs = BaseDirs::new()?;
+    Some(dirs.config_dir().join("Claude").join("claude_desktop_config.json"))
+}
+
+/// Finds the server map in any of the supported layouts: `mcpServers`
+/// (Claude Desktop, Cursor), `servers` (VS Code `mcp.json`) or
+/// `mcp.servers` (VS Code settings).
+fn server_map(root: &Value) -> Option<&Map<String, Value>> {
+    root.get("mcpServers")
+        .or_else(|| root.get("servers"))
+        .or_else(|| root.get("mcp").and_then(|mcp| mcp.get("servers")))
+        .and_then(Value::as_object)
+}
+
+fn bearer_token(headers: &HashMap<String, String>) -> Option<String> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+        .and_then(|(_, value)| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+fn convert(name: &str, entry: ExternalServer) -> Result<MCPServer, String> {
+    let transport = match (entry.command, entry.url) {
+        (Some(command), _) => MCPTransport::Stdio {
+            command,
+            args: entry.args,
+            env: entry.env,
+            cwd: entry.cwd,
+        },
+        (None, Some(url)) => MCPTransport::Http { url },
+        (None, None) => return Err("Neither command nor url given".to_string()),
+    };
+
+    if let Some(server_type) = entry.server_type.as_deref() {
+        let matches = match &transport {
+            MCPTransport::Stdio { .. } => server_type == "stdio",
+            MCPTransport::Http { .. } => matches!(server_type, "http" | "sse" | "streamable-http"),
+        };
+        if !matches {
+            return Err(format!("Unsupported server type: {}", server_type));
+        }
+    }
+
+    Ok(MCPServer {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        transport,
+        token: bearer_token(&entry.headers),
+        is_active: !entry.disabled,
+        last_connected: None,
+    })
+}
+
+fn same_transport(a: &MCPTransport, b: &MCPTransport) -> bool {
+    match (a, b) {
+        (
+            MCPTransport::Stdio { command: a_cmd, args: a_args, .. },
+            MCPTransport::Stdio { command: b_cmd, args: b_args, .. },
+        ) => a_cmd == b_cmd && a_args == b_args,
+        (MCPTransport::Http { url: a }, MCPTransport::Http { url: b }) => {
+            a.trim_end_matches('/') == b.trim_end_matches('/')
+        }
+        _ => false,
+    }
+}
+
+/// Parses an external config and works out which of its servers are new.
+/// Servers whose name or command/url matches an existing (or earlier
+/// imported) server are skipped.
+pub fn plan_import(existing: &[MCPServer], content: &str) -> Result<ImportReport, ConfigError> {
+    let root: Value = serde_json::from_str(content)?;
+    let entries = server_map(&root).ok_or(ConfigError::UnknownFormat)?;
+
+    let mut report = ImportReport::default();
+    for (name, value) in entries {
+        let skip = |reason: String| SkippedServer { name: name.clone(), reason };
+
+        let entry: ExternalServer = match serde_json::from_value(value.clone()) {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.skipped.push(skip(e.to_string()));
+                continue;
+            }
+        };
+        let server = match convert(name, entry) {
+            Ok(server) => server,
+            Err(reason) => {
+                report.skipped.push(skip(reason));
+                continue;
+            }
+        };
+
+        let known = existing.iter().chain(report.added.iter());
+        let mut duplicate = None;
+        for other in known {
+            if other.name.eq_ignore_ascii_case(&server.name) {
+                duplicate = Some(format!("A server named {} already exists", other.name));
+                break;
+            }
+            if same_transport(&other.transport, &server.transport) {
+                duplicate = Some(format!("Same command or url as {}", other.name));
+                break;
+            }
+        }
+
+        match duplicate {
+            Some(reason) => report.skipped.push(skip(reason)),
+            None => report.added.push(server),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Renders servers in another client's format. Tokens and secret env values
+/// are left out so the result can be shared.
+pub fn export(servers: &[MCPServer], format: ExportFormat) -> Value {
+    let mut entries = Map::new();
+
+    for server in servers {
+        let mut entry = match &server.transport {
+            MCPTransport::Stdio { command, args, env, cwd } => {
+                let mut entry = json!({ "command": command, "args": args });
+                // Secret references only mean something to this app.
+                let env: HashMap<_, _> = env.iter().filter(|(_, v)| reference_key(v).is_none()).collect();
+                if !env.is_empty() {
+                    entry["env"] = json!(env);
+                }
+                if let Some(cwd) = cwd {
+                    entry["cwd"] = json!(cwd);
+                }
+                if format == ExportFormat::VsCode {
+                    entry["type"] = json!("stdio");
+                }
+                entry
+            }
+            MCPTransport::Http { url } => match format {
+                ExportFormat::ClaudeDesktop => continue,
+                ExportFormat::Cursor => json!({ "url": url }),
+                ExportFormat::VsCode => json!({ "type": "http", "url": url }),
+            },
+        };
+        if !server.is_active && format != ExportFormat::VsCode {
+            entry["disabled"] = json!(true);
+        }
+
+        let mut name = server.name.clone();
+        let mut suffix = 2;
+        while entries.contains_key(&name) {
+            name = format!("{} ({})", server.name, suffix);
+            suffix += 1;
+        }
+        entries.insert(name, entry);
+    }
+
+    match format {
+        ExportFormat::VsCode => json!({ "servers": entries }),
+        ExportFormat::ClaudeDesktop | ExportFormat::Cursor => json!({ "mcpServers": entries }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing() -> Vec<MCPServer> {
+        vec![MCPServer {
+            id: "1".to_string(),
+            name: "filesystem".to_string(),
+            transport: MCPTransport::Stdio {
+                command: "npx".to_string(),
+                args: vec!["-y".to_string(), "@modelcontextprotocol/server-filesystem".to_string()],
+                env: HashMap::new(),
+                cwd: None,
+            },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }]
+    }
+
+    #[test]
+    fn test_import_claude_desktop_config() {
+        let content = r#"{
+            "mcpServers": {
+                "github": {
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-github"],
+                    "env": { "GITHUB_TOKEN": "ghp_x" }
+                },
+                "FileSystem": { "command": "node", "args": ["fs.js"] },
+                "fs-copy": { "command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem"] },
+                "broken": { "args": ["x"] }
+            }
+        }"#;
+
+        let report = plan_import(&existing(), content).unwrap();
+
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.added[0].name, "github");
+        assert!(matches!(&report.added[0].transport, MCPTransport::Stdio { env, .. } if env["GITHUB_TOKEN"] == "ghp_x"));
+        assert_ne!(report.added[0].id, "");
+
+        let skipped: Vec<&str> = report.skipped.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped.contains(&"FileSystem"));
+        assert!(skipped.contains(&"fs-copy"));
+        assert!(skipped.contains(&"broken"));
+    }
+
+    #[test]
+    fn test_import_vscode_and_cursor_variants() {
+        let vscode = r#"{
+            "servers": {
+                "remote": { "type": "http", "url": "https://mcp.example.com/",
+                            "headers": { "Authorization": "Bearer abc" } },
+                "remote-copy": { "type": "sse", "url": "https://mcp.example.com" }
+            }
+        }"#;
+        let report = plan_import(&[], vscode).unwrap();
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.added[0].token.as_deref(), Some("abc"));
+        assert_eq!(report.skipped[0].name, "remote-copy");
+
+        let settings = r#"{ "mcp": { "servers": { "local": { "command": "uvx", "args": ["tool"] } } } }"#;
+        assert_eq!(plan_import(&[], settings).unwrap().added.len(), 1);
+
+        let cursor = r#"{ "mcpServers": { "docs": { "url": "https://docs.example.com/mcp", "disabled": true } } }"#;
+        assert!(!plan_import(&[], cursor).unwrap().added[0].is_active);
+
+        assert!(matches!(plan_import(&[], "{}"), Err(ConfigError::UnknownFormat)));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut servers = existing();
+        servers.push(MCPServer {
+            id: "2".to_string(),
+            name: "remote".to_string(),
+            transport: MCPTransport::Http { url: "https://mcp.example.com".to_string() },
+            token: Some("secret".to_string()),
+            is_active: true,
+            last_connected: None,
+        });
+
+        let claude = export(&servers, ExportFormat::ClaudeDesktop);
+        assert_eq!(claude["mcpServers"].as_object().unwrap().len(), 1);
+        assert_eq!(claude["mcpServers"]["filesystem"]["command"], "npx");
+
+        let vscode = export(&servers, ExportFormat::VsCode);
+        assert_eq!(vscode["servers"]["remote"], json!({ "type": "http", "url": "https://mcp.example.com" }));
+
+        let report = plan_import(&[], &vscode.to_string()).unwrap();
+        assert_eq!(report.added.len(), 2);
+        assert!(report.added.iter().all(|s| s.token.is_none()));
+    }
+}
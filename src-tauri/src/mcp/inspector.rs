@@ -0,0 +1,227 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::connections::ConnectionManager;
+use super::protocol::{JsonRpcMessage, McpSession};
+use super::transport::{Incoming, Transport};
+use super::types::{InitializeResult, MCPError, MCPPrompt, MCPResource, MCPResourceTemplate, MCPTool};
+
+/// Messages kept per server; older ones are dropped.
+const TRAFFIC_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficEntry {
+    pub timestamp: String,
+    pub direction: Direction,
+    pub message: Value,
+}
+
+/// The most recent JSON-RPC messages exchanged with each server.
+#[derive(Clone)]
+pub struct TrafficLog {
+    capacity: usize,
+    servers: Arc<Mutex<HashMap<String, VecDeque<TrafficEntry>>>>,
+}
+
+impl Default for TrafficLog {
+    fn default() -> Self {
+        Self::with_capacity(TRAFFIC_CAPACITY)
+    }
+}
+
+impl TrafficLog {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            servers: Arc::default(),
+        }
+    }
+
+    pub fn record(&self, server_id: &str, direction: Direction, message: &JsonRpcMessage) {
+        let Ok(message) = serde_json::to_value(message) else {
+            return;
+        };
+        if let Ok(mut servers) = self.servers.lock() {
+            let entries = servers.entry(server_id.to_string()).or_default();
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(TrafficEntry {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                direction,
+                message,
+            });
+        }
+    }
+
+    pub fn snapshot(&self, server_id: &str) -> Vec<TrafficEntry> {
+        self.servers
+            .lock()
+            .ok()
+            .and_then(|servers| servers.get(server_id).map(|e| e.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self, server_id: &str) {
+        if let Ok(mut servers) = self.servers.lock() {
+            servers.remove(server_id);
+        }
+    }
+}
+
+/// Passes messages through to `inner` and records them in the log.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    server_id: String,
+    log: TrafficLog,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn Transport>, server_id: impl Into<String>, log: TrafficLog) -> Self {
+        Self {
+            inner,
+            server_id: server_id.into(),
+            log,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError> {
+        self.log.record(&self.server_id, Direction::Sent, &message);
+        self.inner.send(message).await
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        let incoming = self.inner.receive().await;
+        if let Some(Incoming::Message(message)) = &incoming {
+            self.log.record(&self.server_id, Direction::Received, message);
+        }
+        incoming
+    }
+
+    async fn close(&self) {
+        self.inner.close().await
+    }
+
+    fn set_protocol_version(&self, version: &str) {
+        self.inner.set_protocol_version(version)
+    }
+}
+
+/// Sessions opened for debugging, separate from the ones chats use, with
+/// all their traffic recorded.
+pub struct Inspector {
+    connections: ConnectionManager,
+    traffic: TrafficLog,
+}
+
+impl Inspector {
+    pub fn new(connections: ConnectionManager) -> Self {
+        let traffic = TrafficLog::default();
+        Self {
+            connections: connections.with_traffic(traffic.clone()),
+            traffic,
+        }
+    }
+
+    pub fn connections(&self) -> &ConnectionManager {
+        &self.connections
+    }
+
+    pub fn traffic(&self) -> &TrafficLog {
+        &self.traffic
+    }
+}
+
+/// Everything a server offers, for the inspector's overview.
+#[derive(Debug, Serialize, Clone)]
+pub struct ServerOverview {
+    pub server: InitializeResult,
+    pub tools: Vec<MCPTool>,
+    pub resources: Vec<MCPResource>,
+    pub resource_templates: Vec<MCPResourceTemplate>,
+    pub prompts: Vec<MCPPrompt>,
+}
+
+/// Lists what the server advertised in its capabilities.
+pub async fn overview(session: &McpSession) -> Result<ServerOverview, MCPError> {
+    let server = session
+        .server_info()
+        .ok_or_else(|| MCPError::new("NOT_INITIALIZED", "Handshake has not completed"))?;
+    let capabilities = &server.capabilities;
+
+    let mut overview = ServerOverview {
+        server: server.clone(),
+        tools: Vec::new(),
+        resources: Vec::new(),
+        resource_templates: Vec::new(),
+        prompts: Vec::new(),
+    };
+    if capabilities.tools.is_some() {
+        overview.tools = session.list_tools().await?;
+    }
+    if capabilities.resources.is_some() {
+        overview.resources = session.list_resources().await?;
+        overview.resource_templates = session.list_resource_templates().await?;
+    }
+    if capabilities.prompts.is_some() {
+        overview.prompts = session.list_prompts().await?;
+    }
+    Ok(overview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::protocol::tests::spawn_fake_server;
+    use crate::mcp::protocol::{DefaultClientHandler, McpSession, LATEST_PROTOCOL_VERSION};
+    use crate::mcp::transport::ChannelTransport;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_traffic_is_recorded_in_a_ring_buffer() {
+        let (client, server) = ChannelTransport::pair();
+        spawn_fake_server(server, LATEST_PROTOCOL_VERSION, |_, _| Ok(json!({ "content": [] })));
+
+        let log = TrafficLog::with_capacity(4);
+        let transport = RecordingTransport::new(Arc::new(client), "fake", log.clone());
+        let session = McpSession::connect(Arc::new(transport), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        let handshake = log.snapshot("fake");
+        let methods: Vec<_> = handshake.iter().map(|e| (e.direction, e.message["method"].clone())).collect();
+        assert_eq!(
+            methods,
+            [
+                (Direction::Sent, json!("initialize")),
+                (Direction::Received, Value::Null),
+                (Direction::Sent, json!("notifications/initialized")),
+            ]
+        );
+        assert_eq!(handshake[1].message["result"]["serverInfo"]["name"], "fake");
+
+        session.call_tool("echo", json!({})).await.unwrap();
+        let entries = log.snapshot("fake");
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1].message["method"], "notifications/initialized");
+        assert_eq!(entries[2].message["params"]["name"], "echo");
+        assert_eq!(entries[3].direction, Direction::Received);
+
+        log.clear("fake");
+        assert!(log.snapshot("fake").is_empty());
+    }
+}
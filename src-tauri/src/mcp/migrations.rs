@@ -0,0 +1,128 @@
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::config::ConfigError;
+
+/// Schema version written by this build.
+pub const CURRENT_VERSION: u32 = 2;
+
+type Migration = fn(&mut Value);
+
+/// `MIGRATIONS[n]` upgrades a config from version `n + 1` to `n + 2`.
+const MIGRATIONS: &[Migration] = &[v1_to_v2];
+
+/// Reads the major version. Configs from before versioning count as 1.
+fn parse_version(raw: &Value) -> Result<u32, ConfigError> {
+    let version = match raw.get("version") {
+        None | Some(Value::Null) => return Ok(1),
+        Some(Value::Number(n)) => n.to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => return Err(ConfigError::InvalidVersion(other.to_string())),
+    };
+
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.trim().parse().ok())
+        .ok_or(ConfigError::InvalidVersion(version))
+}
+
+/// Upgrades a raw config to the current schema. Returns whether anything
+/// changed. Configs written by a newer build are rejected rather than
+/// silently downgraded.
+pub fn migrate(raw: &mut Value) -> Result<bool, ConfigError> {
+    if !raw.is_object() {
+        return Err(ConfigError::UnknownFormat);
+    }
+
+    let version = parse_version(raw)?.max(1);
+    if version > CURRENT_VERSION {
+        return Err(ConfigError::NewerVersion {
+            found: version,
+            supported: CURRENT_VERSION,
+        });
+    }
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(raw);
+    }
+
+    let changed = version < CURRENT_VERSION;
+    if changed {
+        raw["version"] = json!(CURRENT_VERSION.to_string());
+    }
+    Ok(changed)
+}
+
+/// 1 → 2: `default_server` becomes `defaultServer` (the spelling the bundled
+/// `config/mcp.json` and the frontend already used), and every server gets
+/// an id and an explicit `is_active`.
+fn v1_to_v2(raw: &mut Value) {
+    let Some(config) = raw.as_object_mut() else {
+        return;
+    };
+
+    if let Some(default_server) = config.remove("default_server") {
+        config.entry("defaultServer").or_insert(default_server);
+    }
+    config.entry("defaultServer").or_insert(Value::Null);
+
+    let servers = config.entry("servers").or_insert_with(|| json!([]));
+    for server in servers.as_array_mut().into_iter().flatten() {
+        let Some(server) = server.as_object_mut() else {
+            continue;
+        };
+        if !server.get("id").is_some_and(Value::is_string) {
+            server.insert("id".to_string(), json!(Uuid::new_v4().to_string()));
+        }
+        server.entry("is_active").or_insert(json!(true));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrates_v1_snake_case() {
+        let mut raw = json!({
+            "version": "1.0",
+            "default_server": "a",
+            "servers": [{ "id": "a", "name": "A", "url": "http://localhost:3000" }, { "name": "B", "command": "b" }]
+        });
+
+        assert!(migrate(&mut raw).unwrap());
+        assert_eq!(raw["version"], "2");
+        assert_eq!(raw["defaultServer"], "a");
+        assert!(raw.get("default_server").is_none());
+        assert_eq!(raw["servers"][0]["is_active"], true);
+        assert!(raw["servers"][1]["id"].is_string());
+    }
+
+    #[test]
+    fn test_migrates_bundled_camel_case_config() {
+        let mut raw = json!({ "version": "1.0", "defaultServer": null, "servers": [] });
+        assert!(migrate(&mut raw).unwrap());
+        assert_eq!(raw, json!({ "version": "2", "defaultServer": null, "servers": [] }));
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut raw = json!({ "version": "2", "defaultServer": null, "servers": [] });
+        let before = raw.clone();
+        assert!(!migrate(&mut raw).unwrap());
+        assert_eq!(raw, before);
+    }
+
+    #[test]
+    fn test_refuses_newer_version() {
+        let mut raw = json!({ "version": "3", "servers": [] });
+        assert!(matches!(
+            migrate(&mut raw),
+            Err(ConfigError::NewerVersion { found: 3, supported: 2 })
+        ));
+
+        let mut raw = json!({ "version": "banana" });
+        assert!(matches!(migrate(&mut raw), Err(ConfigError::InvalidVersion(_))));
+    }
+}
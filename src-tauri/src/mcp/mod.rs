@@ -1,3 +1,27 @@
 pub mod types;
 pub mod config;
-pub mod commands;
\ No newline at end of file
+pub mod migrations;
+pub mod store;
+pub mod credentials;
+pub mod import;
+pub mod commands;
+pub mod protocol;
+pub mod transport;
+pub mod http;
+pub mod oauth;
+pub mod connections;
+pub mod handler;
+pub mod resources;
+pub mod prompts;
+pub mod tool_loop;
+pub mod registry;
+pub mod validation;
+pub mod events;
+pub mod approval;
+pub mod sampling;
+pub mod elicitation;
+pub mod notifications;
+pub mod supervisor;
+pub mod watcher;
+pub mod health;
+pub mod inspector;
@@ -0,0 +1,241 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::events::EventSink;
+use super::tool_loop::CallTracker;
+use super::types::LoggingLevel;
+
+pub const PROGRESS_EVENT: &str = "mcp-progress";
+pub const LOG_EVENT: &str = "mcp-log";
+
+/// A tool call in flight and the chat message it belongs to.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ActiveCall {
+    pub server_id: String,
+    pub tool_name: String,
+    pub chat_id: String,
+    pub message_id: Option<String>,
+}
+
+/// Running tool calls by progress token, oldest first.
+#[derive(Clone, Default)]
+pub struct ActiveCalls {
+    calls: Arc<Mutex<Vec<(String, ActiveCall)>>>,
+}
+
+impl ActiveCalls {
+    fn insert(&self, token: String, call: ActiveCall) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push((token, call));
+        }
+    }
+
+    fn remove(&self, token: &str) {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.retain(|(t, _)| t != token);
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<ActiveCall> {
+        let calls = self.calls.lock().ok()?;
+        calls.iter().find(|(t, _)| t == token).map(|(_, call)| call.clone())
+    }
+
+    /// The most recently started call on a server. Log messages carry no
+    /// request id, so they are attributed to it.
+    pub fn latest_on(&self, server_id: &str) -> Option<ActiveCall> {
+        let calls = self.calls.lock().ok()?;
+        calls
+            .iter()
+            .rev()
+            .find(|(_, call)| call.server_id == server_id)
+            .map(|(_, call)| call.clone())
+    }
+}
+
+/// Tracks the tool calls of one generation. Calls still registered when it
+/// is dropped, e.g. because the generation was stopped, are forgotten.
+pub struct MessageCalls {
+    calls: ActiveCalls,
+    chat_id: String,
+    message_id: Option<String>,
+    tokens: Mutex<Vec<String>>,
+}
+
+impl MessageCalls {
+    pub fn new(calls: ActiveCalls, chat_id: impl Into<String>, message_id: Option<String>) -> Self {
+        Self {
+            calls,
+            chat_id: chat_id.into(),
+            message_id,
+            tokens: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl CallTracker for MessageCalls {
+    fn started(&self, server_id: &str, tool_name: &str) -> String {
+        let token = Uuid::new_v4().to_string();
+        self.calls.insert(
+            token.clone(),
+            ActiveCall {
+                server_id: server_id.to_string(),
+                tool_name: tool_name.to_string(),
+                chat_id: self.chat_id.clone(),
+                message_id: self.message_id.clone(),
+            },
+        );
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.push(token.clone());
+        }
+        token
+    }
+
+    fn finished(&self, progress_token: &str) {
+        self.calls.remove(progress_token);
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.retain(|t| t != progress_token);
+        }
+    }
+}
+
+impl Drop for MessageCalls {
+    fn drop(&mut self) {
+        if let Ok(tokens) = self.tokens.lock() {
+            for token in tokens.iter() {
+                self.calls.remove(token);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressParams {
+    progress_token: Value,
+    progress: f64,
+    #[serde(default)]
+    total: Option<f64>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Payload of [`PROGRESS_EVENT`].
+#[derive(Debug, Serialize, Clone)]
+pub struct ProgressPayload {
+    #[serde(flatten)]
+    pub call: ActiveCall,
+    pub progress: f64,
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogParams {
+    level: LoggingLevel,
+    #[serde(default)]
+    logger: Option<String>,
+    #[serde(default)]
+    data: Value,
+}
+
+/// Payload of [`LOG_EVENT`]. The chat fields are set while a tool call of
+/// the server is running.
+#[derive(Debug, Serialize, Clone)]
+pub struct LogPayload {
+    pub server_id: String,
+    pub level: LoggingLevel,
+    pub logger: Option<String>,
+    pub data: Value,
+    pub tool_name: Option<String>,
+    pub chat_id: Option<String>,
+    pub message_id: Option<String>,
+}
+
+/// Forwards `notifications/progress` for a call we are tracking on the
+/// server that sent it. Servers cannot report on each other's calls.
+pub fn report_progress(events: &dyn EventSink, calls: &ActiveCalls, server_id: &str, params: Option<Value>) {
+    let Some(params) = params.and_then(|p| serde_json::from_value::<ProgressParams>(p).ok()) else {
+        return;
+    };
+    let token = match &params.progress_token {
+        Value::String(token) => token.clone(),
+        token => token.to_string(),
+    };
+    let Some(call) = calls.get(&token).filter(|call| call.server_id == server_id) else {
+        return;
+    };
+
+    let payload = ProgressPayload {
+        call,
+        progress: params.progress,
+        total: params.total,
+        message: params.message,
+    };
+    if let Err(e) = events.emit_event(PROGRESS_EVENT, json!(payload)) {
+        eprintln!("Failed to emit {}: {}", PROGRESS_EVENT, e);
+    }
+}
+
+/// Forwards a `notifications/message` log entry.
+pub fn report_log(events: &dyn EventSink, calls: &ActiveCalls, server_id: &str, params: Option<Value>) {
+    let Some(params) = params.and_then(|p| serde_json::from_value::<LogParams>(p).ok()) else {
+        return;
+    };
+    let call = calls.latest_on(server_id);
+
+    let payload = LogPayload {
+        server_id: server_id.to_string(),
+        level: params.level,
+        logger: params.logger,
+        data: params.data,
+        tool_name: call.as_ref().map(|c| c.tool_name.clone()),
+        chat_id: call.as_ref().map(|c| c.chat_id.clone()),
+        message_id: call.and_then(|c| c.message_id),
+    };
+    if let Err(e) = events.emit_event(LOG_EVENT, json!(payload)) {
+        eprintln!("Failed to emit {}: {}", LOG_EVENT, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::events::tests::RecordedEvents;
+
+    #[test]
+    fn test_notifications_are_tied_to_the_message() {
+        let events = RecordedEvents::default();
+        let calls = ActiveCalls::default();
+
+        let tracker = MessageCalls::new(calls.clone(), "chat-1", Some("msg-7".to_string()));
+        let token = tracker.started("fs", "search");
+
+        report_progress(&events, &calls, "fs", Some(json!({ "progressToken": token, "progress": 5, "total": 10 })));
+        report_progress(&events, &calls, "fs", Some(json!({ "progressToken": "unknown", "progress": 1 })));
+        report_progress(&events, &calls, "github", Some(json!({ "progressToken": token, "progress": 9 })));
+        report_log(&events, &calls, "fs", Some(json!({ "level": "warning", "data": "slow disk" })));
+        report_log(&events, &calls, "github", Some(json!({ "level": "info", "logger": "api", "data": { "rate": 10 } })));
+
+        let emitted = events.take();
+        assert_eq!(emitted.len(), 3);
+        assert_eq!(emitted[0].0, PROGRESS_EVENT);
+        assert_eq!(emitted[0].1["message_id"], "msg-7");
+        assert_eq!(emitted[0].1["tool_name"], "search");
+        assert_eq!(emitted[0].1["total"], 10.0);
+        assert_eq!(emitted[1].0, LOG_EVENT);
+        assert_eq!(emitted[1].1["chat_id"], "chat-1");
+        assert_eq!(emitted[1].1["level"], "warning");
+        assert_eq!(emitted[2].1["chat_id"], Value::Null);
+
+        // Stopping the generation drops the tracker and its calls.
+        let _ = tracker.started("fs", "index");
+        tracker.finished(&token);
+        assert!(calls.latest_on("fs").is_some());
+        drop(tracker);
+        assert!(calls.latest_on("fs").is_none());
+    }
+}
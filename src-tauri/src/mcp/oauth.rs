@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand_core::{OsRng, RngCore};
+use reqwest::header::ACCEPT;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+
+use super::credentials::oauth_key;
+use super::types::MCPError;
+use crate::secrets::SecretStore;
+
+pub const AUTHORIZE_EVENT: &str = "mcp-oauth-authorize";
+
+/// Error code of a connection that needs the user to sign in again.
+pub const SIGN_IN_REQUIRED: &str = "SIGN_IN_REQUIRED";
+
+const CLIENT_NAME: &str = "Luke Desktop";
+const CALLBACK_PATH: &str = "/callback";
+
+/// How long the user has to finish signing in in the browser.
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Connections to the callback listener that send no request in time,
+/// e.g. browser preconnects, are dropped.
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CALLBACK_PAGE: &str = "<!doctype html><html><body><p>You can close this window and return to Luke Desktop.</p></body></html>";
+
+/// Shows the user the authorization URL of a server, e.g. by opening it in
+/// the browser. Called with the server id and the URL.
+pub type AuthorizationPrompt = Arc<dyn Fn(&str, &str) -> Result<(), String> + Send + Sync>;
+
+/// Payload of [`AUTHORIZE_EVENT`]; the webview opens `url` in the browser.
+#[derive(Debug, Serialize, Clone)]
+pub struct AuthorizationRequest {
+    pub server_id: String,
+    pub url: String,
+}
+
+/// Prompts by emitting [`AUTHORIZE_EVENT`] to the webview.
+pub fn authorization_prompt(app: AppHandle) -> AuthorizationPrompt {
+    Arc::new(move |server_id, url| {
+        let request = AuthorizationRequest {
+            server_id: server_id.to_string(),
+            url: url.to_string(),
+        };
+        app.emit(AUTHORIZE_EVENT, request).map_err(|e| e.to_string())
+    })
+}
+
+fn auth_error(message: impl Into<String>) -> MCPError {
+    MCPError::new("AUTH_ERROR", message)
+}
+
+/// Whether the token endpoint rejected the grant itself, as opposed to
+/// being unreachable or failing.
+fn is_invalid_grant(error: &MCPError) -> bool {
+    let Some(details) = &error.details else {
+        return false;
+    };
+    matches!(details.get("status").and_then(Value::as_u64), Some(400 | 401))
+        && details.get("error").and_then(Value::as_str) == Some("invalid_grant")
+}
+
+/// Protected resource metadata (RFC 9728).
+#[derive(Debug, Deserialize)]
+struct ResourceMetadata {
+    #[serde(default)]
+    resource: Option<String>,
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+    #[serde(default)]
+    scopes_supported: Vec<String>,
+}
+
+/// Authorization server metadata (RFC 8414).
+#[derive(Debug, Deserialize, Clone)]
+struct ServerMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    #[serde(default)]
+    registration_endpoint: Option<String>,
+    #[serde(default)]
+    code_challenge_methods_supported: Vec<String>,
+}
+
+/// What discovery found out about a server.
+#[derive(Debug, Clone)]
+struct Discovery {
+    metadata: ServerMetadata,
+    resource: String,
+    scope: Option<String>,
+}
+
+/// A client registered dynamically (RFC 7591), kept for later sign-ins.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Registration {
+    client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    redirect_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// A parameter of a `WWW-Authenticate: Bearer` challenge.
+pub fn challenge_param(challenge: &str, name: &str) -> Option<String> {
+    let challenge = challenge.trim_start();
+    let scheme = challenge.get(..6)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut rest = &challenge[6..];
+    loop {
+        rest = rest.trim_start_matches([' ', ',']);
+        let (key, value) = rest.split_once('=')?;
+        let value = value.trim_start();
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => {
+                let end = value.find(',').unwrap_or(value.len());
+                (value[..end].trim(), &value[end..])
+            }
+        };
+        if key.trim().eq_ignore_ascii_case(name) {
+            return Some(value.to_string());
+        }
+        rest = next;
+    }
+}
+
+/// The RFC 8414 / RFC 9728 location of a well-known document for `url`:
+/// the suffix goes between the origin and the path.
+fn well_known(url: &Url, document: &str) -> String {
+    let path = url.path().trim_end_matches('/');
+    format!("{}/.well-known/{}{}", url.origin().ascii_serialization(), document, path)
+}
+
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The S256 PKCE challenge for a code verifier.
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Receives the authorization response on a loopback redirect URI.
+struct CallbackListener {
+    listener: TcpListener,
+    redirect_uri: String,
+}
+
+impl CallbackListener {
+    /// Binds `port` if given, so a previous registration can be reused, and
+    /// any free port otherwise.
+    async fn bind(port: Option<u16>) -> Result<Self, MCPError> {
+        let preferred = match port {
+            Some(port) => TcpListener::bind(("127.0.0.1", port)).await.ok(),
+            None => None,
+        };
+        let listener = match preferred {
+            Some(listener) => listener,
+            None => TcpListener::bind(("127.0.0.1", 0))
+                .await
+                .map_err(|e| auth_error(e.to_string()))?,
+        };
+        let port = listener.local_addr().map_err(|e| auth_error(e.to_string()))?.port();
+        Ok(Self {
+            listener,
+            redirect_uri: format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH),
+        })
+    }
+
+    /// Waits for the redirect carrying `state` and returns its code. Each
+    /// connection is served on its own, so one that sends nothing cannot
+    /// hold up the real redirect; requests with another state are turned
+    /// away without ending the sign-in.
+    async fn wait(self, state: &str) -> Result<String, MCPError> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (socket, _) = accepted.map_err(|e| auth_error(e.to_string()))?;
+                    let (tx, state) = (tx.clone(), state.to_string());
+                    tokio::spawn(async move {
+                        let answered = tokio::time::timeout(CALLBACK_READ_TIMEOUT, answer_callback(socket, &state));
+                        if let Ok(Some(url)) = answered.await {
+                            let _ = tx.send(url);
+                        }
+                    });
+                }
+                Some(url) = rx.recv() => {
+                    let param = |name: &str| url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.to_string());
+                    if let Some(error) = param("error") {
+                        let description = param("error_description").unwrap_or_default();
+                        return Err(auth_error(format!("Authorization failed: {} {}", error, description).trim_end().to_string()));
+                    }
+                    return param("code").ok_or_else(|| auth_error("Authorization response has no code"));
+                }
+            }
+        }
+    }
+}
+
+/// Reads one request to the callback listener and answers it. Returns the
+/// request URL if it is the redirect carrying `state`.
+async fn answer_callback(socket: TcpStream, state: &str) -> Option<Url> {
+    let mut reader = BufReader::new(socket);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await.ok()?;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let url = Url::parse(&format!("http://127.0.0.1{}", target)).ok();
+    let mut socket = reader.into_inner();
+    let Some(url) = url.filter(|url| url.path() == CALLBACK_PATH) else {
+        let _ = socket
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await;
+        return None;
+    };
+    if !url.query_pairs().any(|(k, v)| k == "state" && v == state) {
+        let _ = socket
+            .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await;
+        return None;
+    }
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        CALLBACK_PAGE.len(),
+        CALLBACK_PAGE
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    Some(url)
+}
+
+/// OAuth clients by server. Every connection manager uses the same ones,
+/// so a rotating refresh token is only ever renewed by one flow at a time.
+#[derive(Clone)]
+pub struct OAuthClients {
+    secrets: Arc<SecretStore>,
+    prompt: AuthorizationPrompt,
+    clients: Arc<StdMutex<HashMap<String, Arc<OAuthClient>>>>,
+}
+
+impl OAuthClients {
+    pub fn new(secrets: Arc<SecretStore>, prompt: AuthorizationPrompt) -> Self {
+        Self {
+            secrets,
+            prompt,
+            clients: Arc::default(),
+        }
+    }
+
+    /// The client of a server, kept across sessions so its access token
+    /// survives a reconnect. A new URL gets a new client.
+    pub fn client(&self, server_id: &str, url: &str) -> Arc<OAuthClient> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        match clients.get(server_id).filter(|c| c.url() == url) {
+            Some(client) => client.clone(),
+            None => {
+                let client = Arc::new(OAuthClient::new(server_id, url, self.secrets.clone(), self.prompt.clone()));
+                clients.insert(server_id.to_string(), client.clone());
+                client
+            }
+        }
+    }
+
+    pub fn remove(&self, server_id: &str) {
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.remove(server_id);
+        }
+    }
+}
+
+tokio::task_local! {
+    static UNATTENDED: ();
+}
+
+/// Runs `f` without interactive sign-ins, for connections nobody is
+/// watching. Servers that need one fail with [`SIGN_IN_REQUIRED`] instead
+/// of opening the browser; refresh tokens are still used.
+pub async fn unattended<F: Future>(f: F) -> F::Output {
+    UNATTENDED.scope((), f).await
+}
+
+/// The OAuth 2.1 client side of the MCP authorization spec for one remote
+/// server. Access tokens live in memory; the refresh token and the client
+/// registration go to the secret store.
+pub struct OAuthClient {
+    http: reqwest::Client,
+    server_id: String,
+    url: String,
+    secrets: Arc<SecretStore>,
+    prompt: AuthorizationPrompt,
+    access_token: StdMutex<Option<String>>,
+    /// Serializes sign-ins, so concurrent 401s lead to a single flow.
+    flow: Mutex<()>,
+}
+
+impl OAuthClient {
+    pub fn new(server_id: &str, url: &str, secrets: Arc<SecretStore>, prompt: AuthorizationPrompt) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            server_id: server_id.to_string(),
+            url: url.to_string(),
+            secrets,
+            prompt,
+            access_token: StdMutex::new(None),
+            flow: Mutex::new(()),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn access_token(&self) -> Option<String> {
+        self.access_token.lock().ok()?.clone()
+    }
+
+    /// Gets a new access token after the server rejected `rejected` with
+    /// `challenge` (its `WWW-Authenticate` header). Uses the stored refresh
+    /// token if there is one and asks the user to sign in otherwise.
+    pub async fn authorize(&self, rejected: Option<&str>, challenge: Option<&str>) -> Result<String, MCPError> {
+        let _flow = self.flow.lock().await;
+        // Another request may have renewed the token while we waited.
+        if let Some(current) = self.access_token() {
+            if Some(current.as_str()) != rejected {
+                return Ok(current);
+            }
+        }
+
+        let discovery = self.discover(challenge).await?;
+        let tokens = match self.refresh(&discovery).await {
+            Some(tokens) => tokens,
+            None if UNATTENDED.try_with(|_| ()).is_ok() => {
+                return Err(MCPError::new(
+                    SIGN_IN_REQUIRED,
+                    format!("MCP server {} needs you to sign in again", self.server_id),
+                ));
+            }
+            None => self.sign_in(&discovery).await?,
+        };
+
+        if let Some(refresh_token) = &tokens.refresh_token {
+            if let Err(e) = self.secrets.set(&oauth_key(&self.server_id, "refresh_token"), refresh_token) {
+                eprintln!("Failed to store refresh token of MCP server {}: {}", self.server_id, e);
+            }
+        }
+        if let Ok(mut access_token) = self.access_token.lock() {
+            *access_token = Some(tokens.access_token.clone());
+        }
+        Ok(tokens.access_token)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Option<T> {
+        let response = self.http.get(url).header(ACCEPT, "application/json").send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.json().await.ok()
+    }
+
+    async fn first_json<T: DeserializeOwned>(&self, urls: &[String]) -> Option<T> {
+        for url in urls {
+            if let Some(document) = self.get_json(url).await {
+                return Some(document);
+            }
+        }
+        None
+    }
+
+    /// Finds the authorization server via the protected resource metadata.
+    /// Servers without it are their own authorization server, as in the
+    /// 2025-03-26 revision of the spec.
+    async fn discover(&self, challenge: Option<&str>) -> Result<Discovery, MCPError> {
+        let server_url = Url::parse(&self.url).map_err(|e| auth_error(e.to_string()))?;
+
+        let mut candidates: Vec<String> = challenge
+            .and_then(|c| challenge_param(c, "resource_metadata"))
+            .into_iter()
+            .collect();
+        candidates.push(well_known(&server_url, "oauth-protected-resource"));
+        candidates.push(format!(
+            "{}/.well-known/oauth-protected-resource",
+            server_url.origin().ascii_serialization()
+        ));
+        let resource: Option<ResourceMetadata> = self.first_json(&candidates).await;
+
+        let issuer = match resource.as_ref().and_then(|r| r.authorization_servers.first()) {
+            Some(issuer) => Url::parse(issuer).map_err(|e| auth_error(e.to_string()))?,
+            None => Url::parse(&server_url.origin().ascii_serialization()).map_err(|e| auth_error(e.to_string()))?,
+        };
+        let candidates = [
+            well_known(&issuer, "oauth-authorization-server"),
+            well_known(&issuer, "openid-configuration"),
+            format!("{}/.well-known/openid-configuration", issuer.as_str().trim_end_matches('/')),
+        ];
+        let metadata = match self.first_json::<ServerMetadata>(&candidates).await {
+            Some(metadata) => metadata,
+            None => {
+                let origin = issuer.origin().ascii_serialization();
+                ServerMetadata {
+                    authorization_endpoint: format!("{}/authorize", origin),
+                    token_endpoint: format!("{}/token", origin),
+                    registration_endpoint: Some(format!("{}/register", origin)),
+                    code_challenge_methods_supported: Vec::new(),
+                }
+            }
+        };
+        let methods = &metadata.code_challenge_methods_supported;
+        if !methods.is_empty() && !methods.iter().any(|m| m == "S256") {
+            return Err(auth_error("Authorization server does not support PKCE with S256"));
+        }
+
+        let scope = challenge.and_then(|c| challenge_param(c, "scope")).or_else(|| {
+            let scopes = &resource.as_ref()?.scopes_supported;
+            (!scopes.is_empty()).then(|| scopes.join(" "))
+        });
+        Ok(Discovery {
+            metadata,
+            resource: resource.and_then(|r| r.resource).unwrap_or_else(|| self.url.clone()),
+            scope,
+        })
+    }
+
+    fn registration(&self) -> Option<Registration> {
+        let stored = self.secrets.get(&oauth_key(&self.server_id, "client")).ok()??;
+        serde_json::from_str(&stored).ok()
+    }
+
+    /// The stored registration if it fits `redirect_uri`, else a new one.
+    async fn register(&self, discovery: &Discovery, redirect_uri: &str) -> Result<Registration, MCPError> {
+        if let Some(registration) = self.registration().filter(|r| r.redirect_uri == redirect_uri) {
+            return Ok(registration);
+        }
+        let endpoint = discovery
+            .metadata
+            .registration_endpoint
+            .as_deref()
+            .ok_or_else(|| auth_error("Authorization server does not support dynamic client registration"))?;
+
+        let response = self
+            .http
+            .post(endpoint)
+            .json(&json!({
+                "client_name": CLIENT_NAME,
+                "redirect_uris": [redirect_uri],
+                "grant_types": ["authorization_code", "refresh_token"],
+                "response_types": ["code"],
+                "token_endpoint_auth_method": "none",
+            }))
+            .send()
+            .await
+            .map_err(|e| auth_error(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(auth_error(format!("Client registration failed with status {}", response.status())));
+        }
+        let registered: Value = response.json().await.map_err(|e| auth_error(e.to_string()))?;
+        let registration = Registration {
+            client_id: registered["client_id"]
+                .as_str()
+                .ok_or_else(|| auth_error("Client registration returned no client_id"))?
+                .to_string(),
+            client_secret: registered["client_secret"].as_str().map(str::to_string),
+            redirect_uri: redirect_uri.to_string(),
+        };
+
+        let stored = serde_json::to_string(&registration).map_err(|e| auth_error(e.to_string()))?;
+        if let Err(e) = self.secrets.set(&oauth_key(&self.server_id, "client"), &stored) {
+            eprintln!("Failed to store OAuth client of MCP server {}: {}", self.server_id, e);
+        }
+        Ok(registration)
+    }
+
+    async fn request_token(
+        &self,
+        discovery: &Discovery,
+        registration: &Registration,
+        mut params: Vec<(&str, String)>,
+    ) -> Result<TokenResponse, MCPError> {
+        params.push(("client_id", registration.client_id.clone()));
+        params.push(("resource", discovery.resource.clone()));
+        if let Some(secret) = &registration.client_secret {
+            params.push(("client_secret", secret.clone()));
+        }
+
+        let response = self
+            .http
+            .post(&discovery.metadata.token_endpoint)
+            .header(ACCEPT, "application/json")
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| auth_error(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: Value = response.json().await.unwrap_or_default();
+            let mut error = auth_error(format!("Token request failed with status {}", status));
+            error.details = Some(
+                [
+                    ("status".to_string(), Value::from(status.as_u16())),
+                    ("error".to_string(), body["error"].clone()),
+                ]
+                .into(),
+            );
+            return Err(error);
+        }
+        response.json().await.map_err(|e| auth_error(e.to_string()))
+    }
+
+    /// Trades the stored refresh token for new tokens. A token the server
+    /// no longer accepts is forgotten.
+    async fn refresh(&self, discovery: &Discovery) -> Option<TokenResponse> {
+        let key = oauth_key(&self.server_id, "refresh_token");
+        let refresh_token = self.secrets.get(&key).ok()??;
+        let registration = self.registration()?;
+
+        let params = vec![("grant_type", "refresh_token".to_string()), ("refresh_token", refresh_token.clone())];
+        match self.request_token(discovery, &registration, params).await {
+            Ok(mut tokens) => {
+                // Servers that don't rotate refresh tokens omit them.
+                tokens.refresh_token.get_or_insert(refresh_token);
+                Some(tokens)
+            }
+            Err(e) => {
+                eprintln!("Failed to refresh token of MCP server {}: {}", self.server_id, e);
+                if is_invalid_grant(&e) {
+                    let _ = self.secrets.delete(&key);
+                }
+                None
+            }
+        }
+    }
+
+    /// The authorization code flow with PKCE, redirecting to a loopback
+    /// listener.
+    async fn sign_in(&self, discovery: &Discovery) -> Result<TokenResponse, MCPError> {
+        let port = self
+            .registration()
+            .and_then(|r| Url::parse(&r.redirect_uri).ok())
+            .and_then(|url| url.port());
+        let listener = CallbackListener::bind(port).await?;
+        let registration = self.register(discovery, &listener.redirect_uri).await?;
+
+        let verifier = random_string();
+        let state = random_string();
+        let mut url = Url::parse(&discovery.metadata.authorization_endpoint).map_err(|e| auth_error(e.to_string()))?;
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("response_type", "code")
+                .append_pair("client_id", &registration.client_id)
+                .append_pair("redirect_uri", &registration.redirect_uri)
+                .append_pair("code_challenge", &code_challenge(&verifier))
+                .append_pair("code_challenge_method", "S256")
+                .append_pair("state", &state)
+                .append_pair("resource", &discovery.resource);
+            if let Some(scope) = &discovery.scope {
+                query.append_pair("scope", scope);
+            }
+        }
+
+        (self.prompt)(&self.server_id, url.as_str()).map_err(auth_error)?;
+        let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, listener.wait(&state))
+            .await
+            .map_err(|_| auth_error("Timed out waiting for the authorization in the browser"))??;
+
+        let params = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", registration.redirect_uri.clone()),
+            ("code_verifier", verifier),
+        ];
+        self.request_token(discovery, &registration, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::http::StreamableHttpTransport;
+    use crate::mcp::protocol::{DefaultClientHandler, McpSession};
+    use crate::secrets::MemoryBackend;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_pkce_and_challenge_parsing() {
+        // RFC 7636, appendix B.
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let challenge = r#"Bearer error="invalid_token", resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource", scope=files"#;
+        assert_eq!(
+            challenge_param(challenge, "resource_metadata").as_deref(),
+            Some("https://mcp.example.com/.well-known/oauth-protected-resource")
+        );
+        assert_eq!(challenge_param(challenge, "scope").as_deref(), Some("files"));
+        assert_eq!(challenge_param(challenge, "realm"), None);
+        assert_eq!(challenge_param("Basic realm=\"x\"", "realm"), None);
+
+        let url = Url::parse("https://auth.example.com/tenant/").unwrap();
+        assert_eq!(
+            well_known(&url, "oauth-authorization-server"),
+            "https://auth.example.com/.well-known/oauth-authorization-server/tenant"
+        );
+    }
+
+    /// A browser that approves right away by following the redirect.
+    fn approving_browser(prompts: Arc<AtomicUsize>) -> AuthorizationPrompt {
+        Arc::new(move |_, url| {
+            prompts.fetch_add(1, Ordering::SeqCst);
+            let url = Url::parse(url).unwrap();
+            let param = |name: &str| url.query_pairs().find(|(k, _)| k == name).unwrap().1.to_string();
+            assert_eq!(param("code_challenge_method"), "S256");
+            assert_eq!(param("client_id"), "client-1");
+            let callback = param("redirect_uri");
+            let redirect = format!("{}?code=code-1&state={}", callback, param("state"));
+            tokio::spawn(async move {
+                // A preconnect that never sends anything, and a stray
+                // request with the wrong state, come first.
+                let listener = Url::parse(&callback).unwrap();
+                let _idle = TcpStream::connect(("127.0.0.1", listener.port().unwrap())).await.unwrap();
+                let stray = reqwest::get(format!("{}?code=evil&state=forged", callback)).await.unwrap();
+                reqwest::get(redirect).await.unwrap();
+                assert_eq!(stray.status(), 400);
+            });
+            Ok(())
+        })
+    }
+
+    async fn mount_mcp_server(server: &MockServer, token: &str) {
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(header("authorization", format!("Bearer {}", token).as_str()))
+            .and(body_partial_json(json!({ "method": "initialize" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "serverInfo": { "name": "remote", "version": "1.0" }
+            } })))
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .and(header("authorization", format!("Bearer {}", token).as_str()))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_sign_in_and_refresh_against_a_fake_authorization_server() {
+        let server = MockServer::start().await;
+        let uri = server.uri();
+
+        Mock::given(method("GET"))
+            .and(path("/mcp"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .respond_with(ResponseTemplate::new(401).insert_header(
+                "WWW-Authenticate",
+                format!(r#"Bearer resource_metadata="{}/.well-known/oauth-protected-resource/mcp""#, uri).as_str(),
+            ))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-protected-resource/mcp"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "resource": format!("{}/mcp", uri),
+                "authorization_servers": [format!("{}/auth", uri)],
+                "scopes_supported": ["tools"]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-authorization-server/auth"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": format!("{}/auth", uri),
+                "authorization_endpoint": format!("{}/auth/authorize", uri),
+                "token_endpoint": format!("{}/auth/token", uri),
+                "registration_endpoint": format!("{}/auth/register", uri),
+                "code_challenge_methods_supported": ["S256"]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/register"))
+            .and(body_partial_json(json!({ "token_endpoint_auth_method": "none" })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "client_id": "client-1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .and(body_string_contains("grant_type=authorization_code"))
+            .and(body_string_contains("code=code-1"))
+            .and(body_string_contains("code_verifier="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "at-1", "token_type": "Bearer", "expires_in": 3600, "refresh_token": "rt-1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_mcp_server(&server, "at-1").await;
+
+        let secrets = Arc::new(SecretStore::new(MemoryBackend::default()));
+        let prompts = Arc::new(AtomicUsize::new(0));
+        let url = format!("{}/mcp", uri);
+
+        // The first connection signs in through the browser.
+        let oauth = Arc::new(OAuthClient::new("remote", &url, secrets.clone(), approving_browser(prompts.clone())));
+        let transport = StreamableHttpTransport::with_oauth(url.clone(), oauth.clone());
+        let session = McpSession::connect(Arc::new(transport), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+        assert_eq!(session.server_info().unwrap().server_info.name, "remote");
+        assert_eq!(oauth.access_token().as_deref(), Some("at-1"));
+        assert_eq!(
+            secrets.get(&oauth_key("remote", "refresh_token")).unwrap().as_deref(),
+            Some("rt-1")
+        );
+
+        // After a restart the refresh token is used; nobody is asked again.
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/mcp"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/mcp"))
+            .respond_with(ResponseTemplate::new(401))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-protected-resource/mcp"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "resource": format!("{}/mcp", uri),
+                "authorization_servers": [format!("{}/auth", uri)]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-authorization-server/auth"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorization_endpoint": format!("{}/auth/authorize", uri),
+                "token_endpoint": format!("{}/auth/token", uri)
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=rt-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "access_token": "at-2", "token_type": "Bearer" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_mcp_server(&server, "at-2").await;
+
+        let oauth = Arc::new(OAuthClient::new("remote", &url, secrets.clone(), approving_browser(prompts.clone())));
+        let transport = StreamableHttpTransport::with_oauth(url, oauth.clone());
+        McpSession::connect(Arc::new(transport), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+        assert_eq!(oauth.access_token().as_deref(), Some("at-2"));
+        assert_eq!(prompts.load(Ordering::SeqCst), 1);
+        // The server did not rotate the refresh token, so it is kept.
+        assert_eq!(
+            secrets.get(&oauth_key("remote", "refresh_token")).unwrap().as_deref(),
+            Some("rt-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_is_only_dropped_when_the_grant_is_invalid() {
+        let server = MockServer::start().await;
+        let secrets = Arc::new(SecretStore::new(MemoryBackend::default()));
+        let key = oauth_key("remote", "refresh_token");
+        secrets.set(&key, "rt-1").unwrap();
+        let registration = json!({ "client_id": "client-1", "client_secret": null, "redirect_uri": "http://127.0.0.1:1/callback" });
+        secrets.set(&oauth_key("remote", "client"), &registration.to_string()).unwrap();
+
+        let prompt: AuthorizationPrompt = Arc::new(|_, _| Ok(()));
+        let clients = OAuthClients::new(secrets.clone(), prompt);
+        let oauth = clients.client("remote", "https://mcp.example.com/mcp");
+        assert!(Arc::ptr_eq(&oauth, &clients.client("remote", "https://mcp.example.com/mcp")));
+        let discovery = Discovery {
+            metadata: ServerMetadata {
+                authorization_endpoint: format!("{}/authorize", server.uri()),
+                token_endpoint: format!("{}/token", server.uri()),
+                registration_endpoint: None,
+                code_challenge_methods_supported: Vec::new(),
+            },
+            resource: "https://mcp.example.com/mcp".to_string(),
+            scope: None,
+        };
+
+        // An outage keeps the token for the next attempt.
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        assert!(oauth.refresh(&discovery).await.is_none());
+        assert_eq!(secrets.get(&key).unwrap().as_deref(), Some("rt-1"));
+
+        server.reset().await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({ "error": "invalid_grant" })))
+            .mount(&server)
+            .await;
+        assert!(oauth.refresh(&discovery).await.is_none());
+        assert_eq!(secrets.get(&key).unwrap(), None);
+    }
+}
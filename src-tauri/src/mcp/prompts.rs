@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use super::tool_loop::content_block;
+use super::types::{GetPromptResult, MCPError, MCPPrompt};
+use crate::anthropic::types::{Message, MessageContent};
+
+/// A prompt offered by one of the active servers, together with the slash
+/// command that invokes it.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServerPrompt {
+    pub server_id: String,
+    pub command: String,
+    #[serde(flatten)]
+    pub prompt: MCPPrompt,
+}
+
+/// Collects prompts from all servers. A name that is already taken by an
+/// earlier server is exposed as `server_id:name` instead.
+pub fn aggregate(prompts: Vec<(String, Vec<MCPPrompt>)>) -> Vec<ServerPrompt> {
+    let mut taken = HashSet::new();
+    let mut result = Vec::new();
+
+    for (server_id, listed) in prompts {
+        for prompt in listed {
+            let command = if taken.insert(prompt.name.clone()) {
+                prompt.name.clone()
+            } else {
+                format!("{}:{}", server_id, prompt.name)
+            };
+            result.push(ServerPrompt {
+                server_id: server_id.clone(),
+                command,
+                prompt,
+            });
+        }
+    }
+
+    result
+}
+
+/// Splits `/review-pr 123` into the command and the raw argument text.
+pub fn parse_slash_command(input: &str) -> Option<(&str, &str)> {
+    let rest = input.trim_start().strip_prefix('/')?;
+    let (command, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if command.is_empty() {
+        return None;
+    }
+    Some((command, args.trim()))
+}
+
+/// Maps the text after a slash command onto the prompt's arguments.
+/// `key=value` tokens set arguments by name; the remaining tokens fill the
+/// other arguments in order, with the last one taking the rest of the line.
+pub fn bind_arguments(prompt: &MCPPrompt, input: &str) -> Result<HashMap<String, String>, MCPError> {
+    let mut bound = HashMap::new();
+    let mut positional = Vec::new();
+
+    for token in input.split_whitespace() {
+        match token.split_once('=') {
+            Some((key, value)) if prompt.arguments.iter().any(|a| a.name == key) => {
+                bound.insert(key.to_string(), value.to_string());
+            }
+            _ => positional.push(token),
+        }
+    }
+
+    let open: Vec<&str> = prompt
+        .arguments
+        .iter()
+        .filter(|a| !bound.contains_key(&a.name))
+        .map(|a| a.name.as_str())
+        .collect();
+
+    for (i, name) in open.iter().enumerate() {
+        if positional.is_empty() {
+            break;
+        }
+        let value = if i == open.len() - 1 {
+            std::mem::take(&mut positional).join(" ")
+        } else {
+            positional.remove(0).to_string()
+        };
+        bound.insert(name.to_string(), value);
+    }
+
+    if !positional.is_empty() {
+        return Err(MCPError::new(
+            "INVALID_ARGUMENTS",
+            format!("/{} takes no further arguments", prompt.name),
+        ));
+    }
+
+    let missing: Vec<&str> = prompt
+        .arguments
+        .iter()
+        .filter(|a| a.required && !bound.contains_key(&a.name))
+        .map(|a| a.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(MCPError::new(
+            "INVALID_ARGUMENTS",
+            format!("Missing required arguments: {}", missing.join(", ")),
+        ));
+    }
+
+    Ok(bound)
+}
+
+/// Converts a rendered prompt into chat messages.
+pub fn render_messages(result: GetPromptResult) -> Vec<Message> {
+    result
+        .messages
+        .into_iter()
+        .filter_map(|message| {
+            let block = content_block(message.content)?;
+            Some(Message {
+                role: message.role,
+                content: MessageContent::Blocks(vec![block]),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anthropic::types::{ContentBlock, Role};
+    use crate::mcp::types::{PromptArgument, PromptMessage, ToolContent};
+
+    fn prompt(name: &str, arguments: &[(&str, bool)]) -> MCPPrompt {
+        MCPPrompt {
+            name: name.to_string(),
+            title: None,
+            description: None,
+            arguments: arguments
+                .iter()
+                .map(|(name, required)| PromptArgument {
+                    name: name.to_string(),
+                    title: None,
+                    description: None,
+                    required: *required,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_qualifies_duplicates() {
+        let prompts = aggregate(vec![
+            ("github".to_string(), vec![prompt("review-pr", &[])]),
+            ("gitlab".to_string(), vec![prompt("review-pr", &[]), prompt("summarize", &[])]),
+        ]);
+
+        let commands: Vec<&str> = prompts.iter().map(|p| p.command.as_str()).collect();
+        assert_eq!(commands, vec!["review-pr", "gitlab:review-pr", "summarize"]);
+    }
+
+    #[test]
+    fn test_parse_slash_command() {
+        assert_eq!(parse_slash_command("/review-pr 123"), Some(("review-pr", "123")));
+        assert_eq!(parse_slash_command("/summarize"), Some(("summarize", "")));
+        assert_eq!(parse_slash_command("review-pr 123"), None);
+        assert_eq!(parse_slash_command("/ 123"), None);
+    }
+
+    #[test]
+    fn test_bind_arguments() {
+        let review = prompt("review-pr", &[("number", true), ("focus", false)]);
+
+        let bound = bind_arguments(&review, "123 error handling").unwrap();
+        assert_eq!(bound["number"], "123");
+        assert_eq!(bound["focus"], "error handling");
+
+        let bound = bind_arguments(&review, "focus=tests 42").unwrap();
+        assert_eq!(bound["number"], "42");
+        assert_eq!(bound["focus"], "tests");
+
+        let error = bind_arguments(&review, "").unwrap_err();
+        assert_eq!(error.message, "Missing required arguments: number");
+
+        let error = bind_arguments(&prompt("summarize", &[]), "extra").unwrap_err();
+        assert_eq!(error.code, "INVALID_ARGUMENTS");
+    }
+
+    #[test]
+    fn test_render_messages() {
+        let messages = render_messages(GetPromptResult {
+            description: None,
+            messages: vec![PromptMessage {
+                role: Role::User,
+                content: ToolContent::Text { text: "Review PR #123".to_string() },
+            }],
+        });
+
+        assert_eq!(
+            messages,
+            vec![Message {
+                role: Role::User,
+                content: MessageContent::Blocks(vec![ContentBlock::Text {
+                    text: "Review PR #123".to_string()
+                }]),
+            }]
+        );
+    }
+}
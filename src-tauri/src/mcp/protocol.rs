@@ -0,0 +1,1059 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::{oneshot, watch, Notify};
+
+use super::tool_loop::ToolProvider;
+use super::transport::{Incoming, Transport};
+use super::types::{
+    CallToolResult, GetPromptResult, Implementation, InitializeResult, LoggingLevel, MCPError, MCPPrompt,
+    MCPResource, MCPResourceTemplate, MCPTool, ReadResourceResult,
+};
+
+pub const JSONRPC_VERSION: &str = "2.0";
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// How long a request may wait for its response. Progress the server
+/// reports for the request restarts the clock, and the clock stands still
+/// while the server waits on us, e.g. for the user to answer an elicitation.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Guards against servers that keep handing out cursors forever.
+const MAX_PAGES: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    String(String),
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestId::Number(n) => write!(f, "{}", n),
+            RequestId::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonRpcRequest {
+    pub jsonrpc: String,
+    pub id: RequestId,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    pub id: RequestId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(INTERNAL_ERROR, message)
+    }
+}
+
+impl From<JsonRpcError> for MCPError {
+    fn from(error: JsonRpcError) -> Self {
+        let code = match error.code {
+            PARSE_ERROR => "PARSE_ERROR",
+            INVALID_REQUEST => "INVALID_REQUEST",
+            METHOD_NOT_FOUND => "METHOD_NOT_FOUND",
+            INVALID_PARAMS => "INVALID_PARAMS",
+            INTERNAL_ERROR => "INTERNAL_ERROR",
+            _ => "SERVER_ERROR",
+        };
+
+        let mut details = HashMap::new();
+        details.insert("jsonrpc_code".to_string(), json!(error.code));
+        if let Some(data) = error.data {
+            details.insert("data".to_string(), data);
+        }
+
+        MCPError {
+            code: code.to_string(),
+            message: error.message,
+            details: Some(details),
+        }
+    }
+}
+
+/// Any JSON-RPC message. Variants are told apart by the presence of `id`
+/// and `method`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum JsonRpcMessage {
+    Request(JsonRpcRequest),
+    Notification(JsonRpcNotification),
+    Response(JsonRpcResponse),
+}
+
+impl JsonRpcMessage {
+    pub fn request(id: RequestId, method: &str, params: Option<Value>) -> Self {
+        Self::Request(JsonRpcRequest {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    pub fn notification(method: &str, params: Option<Value>) -> Self {
+        Self::Notification(JsonRpcNotification {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    pub fn response(id: RequestId, result: Result<Value, JsonRpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self::Response(JsonRpcResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result,
+            error,
+        })
+    }
+}
+
+/// Handles requests and notifications the server sends to the client.
+#[async_trait]
+pub trait ClientHandler: Send + Sync {
+    /// Capabilities advertised to the server during `initialize`.
+    fn capabilities(&self) -> Value {
+        json!({})
+    }
+
+    async fn handle_request(&self, method: &str, _params: Option<Value>) -> Result<Value, JsonRpcError> {
+        match method {
+            "ping" => Ok(json!({})),
+            _ => Err(JsonRpcError::method_not_found(method)),
+        }
+    }
+
+    async fn handle_notification(&self, _method: &str, _params: Option<Value>) {}
+}
+
+pub struct DefaultClientHandler;
+
+impl ClientHandler for DefaultClientHandler {}
+
+type PendingMap = HashMap<RequestId, oneshot::Sender<Result<Value, MCPError>>>;
+
+/// Signalled whenever `notifications/progress` arrives for a token.
+type ProgressMap = HashMap<String, Arc<Notify>>;
+
+/// A client-side MCP session over an arbitrary transport.
+pub struct McpSession {
+    transport: Arc<dyn Transport>,
+    handler: Arc<dyn ClientHandler>,
+    pending: Mutex<PendingMap>,
+    progress: Mutex<ProgressMap>,
+    /// Number of server requests currently being answered.
+    answering: watch::Sender<usize>,
+    next_id: AtomicI64,
+    initialized: RwLock<Option<InitializeResult>>,
+    closed: AtomicBool,
+}
+
+impl McpSession {
+    /// Starts the reader loop and performs the `initialize` handshake.
+    pub async fn connect(
+        transport: Arc<dyn Transport>,
+        handler: Arc<dyn ClientHandler>,
+    ) -> Result<Arc<Self>, MCPError> {
+        let session = Arc::new(Self {
+            transport: transport.clone(),
+            handler,
+            pending: Mutex::new(HashMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            answering: watch::channel(0).0,
+            next_id: AtomicI64::new(1),
+            initialized: RwLock::new(None),
+            closed: AtomicBool::new(false),
+        });
+
+        tokio::spawn(read_loop(Arc::downgrade(&session), transport));
+
+        if let Err(e) = session.initialize().await {
+            session.close().await;
+            return Err(e);
+        }
+        Ok(session)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn server_info(&self) -> Option<InitializeResult> {
+        self.initialized.read().ok()?.clone()
+    }
+
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.transport.close().await;
+        self.fail_pending("Session closed");
+    }
+
+    async fn initialize(&self) -> Result<InitializeResult, MCPError> {
+        let params = json!({
+            "protocolVersion": LATEST_PROTOCOL_VERSION,
+            "capabilities": self.handler.capabilities(),
+            "clientInfo": Implementation {
+                name: "luke-desktop".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        });
+
+        let result: InitializeResult = decode(self.request("initialize", Some(params)).await?)?;
+
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&result.protocol_version.as_str()) {
+            let mut error = MCPError::new(
+                "UNSUPPORTED_PROTOCOL_VERSION",
+                format!("Server requested unsupported protocol version {}", result.protocol_version),
+            );
+            error.details = Some(HashMap::from([
+                ("server_version".to_string(), json!(result.protocol_version)),
+                ("supported_versions".to_string(), json!(SUPPORTED_PROTOCOL_VERSIONS)),
+            ]));
+            return Err(error);
+        }
+
+        self.transport.set_protocol_version(&result.protocol_version);
+        self.notify("notifications/initialized", None).await?;
+
+        if let Ok(mut initialized) = self.initialized.write() {
+            *initialized = Some(result.clone());
+        }
+        Ok(result)
+    }
+
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value, MCPError> {
+        self.request_with_timeout(method, params, DEFAULT_REQUEST_TIMEOUT).await
+    }
+
+    pub async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Duration,
+    ) -> Result<Value, MCPError> {
+        if self.is_closed() {
+            return Err(MCPError::new("CONNECTION_CLOSED", "Session is closed"));
+        }
+
+        let id = RequestId::Number(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let (tx, mut rx) = oneshot::channel();
+        self.pending_map().insert(id.clone(), tx);
+        let mut in_flight = InFlight::new(self, id.clone());
+
+        let progress = progress_token(params.as_ref()).map(|token| {
+            let notify = Arc::new(Notify::new());
+            self.progress_map().insert(token.clone(), notify.clone());
+            in_flight.progress_token = Some(token);
+            notify
+        });
+
+        let sent = self
+            .transport
+            .send(JsonRpcMessage::request(id, method, params))
+            .await;
+        if let Err(e) = sent {
+            in_flight.finish();
+            return Err(e);
+        }
+
+        let mut answering = self.answering.subscribe();
+        let response = loop {
+            let waiting_on_us = *answering.borrow_and_update() > 0;
+            tokio::select! {
+                response = &mut rx => break Some(response),
+                _ = progressed(progress.as_deref()) => {}
+                _ = answering.changed() => {}
+                _ = tokio::time::sleep(timeout), if !waiting_on_us => break None,
+            }
+        };
+
+        match response {
+            Some(Ok(result)) => {
+                in_flight.finish();
+                result
+            }
+            Some(Err(_)) => {
+                in_flight.finish();
+                Err(MCPError::new("CONNECTION_CLOSED", "Session closed while waiting for a response"))
+            }
+            None => {
+                in_flight.reason = "Request timed out";
+                Err(MCPError::new(
+                    "TIMEOUT",
+                    format!("{} timed out after {}s", method, timeout.as_secs()),
+                ))
+            }
+        }
+    }
+
+    /// Sends a `ping` and returns the round-trip time. The timeout also
+    /// covers sending, which may hang on a slow server or a token renewal.
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, MCPError> {
+        let started = std::time::Instant::now();
+        tokio::time::timeout(timeout, self.request_with_timeout("ping", None, timeout))
+            .await
+            .map_err(|_| MCPError::new("TIMEOUT", format!("ping timed out after {}s", timeout.as_secs())))??;
+        Ok(started.elapsed())
+    }
+
+    pub async fn notify(&self, method: &str, params: Option<Value>) -> Result<(), MCPError> {
+        self.transport
+            .send(JsonRpcMessage::notification(method, params))
+            .await
+    }
+
+    /// Follows `nextCursor` until all pages of a list method have been read.
+    pub async fn paginate<T: DeserializeOwned>(&self, method: &str, key: &str) -> Result<Vec<T>, MCPError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_PAGES {
+            let params = cursor.take().map(|cursor| json!({ "cursor": cursor }));
+            let mut result = self.request(method, params).await?;
+
+            let page: Vec<T> = decode(result.get_mut(key).map(Value::take).unwrap_or(json!([])))?;
+            items.extend(page);
+
+            match result.get("nextCursor").and_then(Value::as_str) {
+                Some(next) => cursor = Some(next.to_string()),
+                None => return Ok(items),
+            }
+        }
+
+        Err(MCPError::new(
+            "PAGINATION_LIMIT",
+            format!("{} returned more than {} pages", method, MAX_PAGES),
+        ))
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError> {
+        if let Some(info) = self.server_info() {
+            if info.capabilities.tools.is_none() {
+                return Ok(Vec::new());
+            }
+        }
+        self.paginate("tools/list", "tools").await
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, MCPError> {
+        let result = self
+            .request("tools/call", Some(json!({ "name": name, "arguments": arguments })))
+            .await?;
+        decode(result)
+    }
+
+    /// Calls a tool and asks the server to send `notifications/progress`
+    /// under `progress_token`.
+    pub async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: &str,
+    ) -> Result<CallToolResult, MCPError> {
+        let params = json!({
+            "name": name,
+            "arguments": arguments,
+            "_meta": { "progressToken": progress_token },
+        });
+        decode(self.request("tools/call", Some(params)).await?)
+    }
+
+    /// Sets the minimum level of `notifications/message` the server sends.
+    pub async fn set_log_level(&self, level: LoggingLevel) -> Result<(), MCPError> {
+        if let Some(info) = self.server_info() {
+            if info.capabilities.logging.is_none() {
+                return Err(MCPError::new("NOT_SUPPORTED", "Server does not support logging"));
+            }
+        }
+        self.request("logging/setLevel", Some(json!({ "level": level }))).await?;
+        Ok(())
+    }
+
+    fn supports_resources(&self) -> bool {
+        self.server_info()
+            .is_none_or(|info| info.capabilities.resources.is_some())
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<MCPResource>, MCPError> {
+        if !self.supports_resources() {
+            return Ok(Vec::new());
+        }
+        self.paginate("resources/list", "resources").await
+    }
+
+    pub async fn list_resource_templates(&self) -> Result<Vec<MCPResourceTemplate>, MCPError> {
+        if !self.supports_resources() {
+            return Ok(Vec::new());
+        }
+        self.paginate("resources/templates/list", "resourceTemplates").await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, MCPError> {
+        decode(self.request("resources/read", Some(json!({ "uri": uri }))).await?)
+    }
+
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<(), MCPError> {
+        let supported = self
+            .server_info()
+            .and_then(|info| info.capabilities.resources)
+            .is_some_and(|resources| resources.subscribe);
+        if !supported {
+            return Err(MCPError::new(
+                "UNSUPPORTED",
+                "Server does not support resource subscriptions",
+            ));
+        }
+        self.request("resources/subscribe", Some(json!({ "uri": uri }))).await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<(), MCPError> {
+        self.request("resources/unsubscribe", Some(json!({ "uri": uri }))).await?;
+        Ok(())
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<MCPPrompt>, MCPError> {
+        if let Some(info) = self.server_info() {
+            if info.capabilities.prompts.is_none() {
+                return Ok(Vec::new());
+            }
+        }
+        self.paginate("prompts/list", "prompts").await
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult, MCPError> {
+        let result = self
+            .request("prompts/get", Some(json!({ "name": name, "arguments": arguments })))
+            .await?;
+        decode(result)
+    }
+
+    fn pending_map(&self) -> std::sync::MutexGuard<'_, PendingMap> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn progress_map(&self) -> std::sync::MutexGuard<'_, ProgressMap> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Restarts the timeout of the request the progress is reported for.
+    fn progressed(&self, params: Option<&Value>) {
+        let Some(token) = params.and_then(|p| p.get("progressToken")).map(token_string) else {
+            return;
+        };
+        if let Some(notify) = self.progress_map().get(&token) {
+            notify.notify_one();
+        }
+    }
+
+    fn complete(&self, response: JsonRpcResponse) {
+        let Some(tx) = self.pending_map().remove(&response.id) else {
+            eprintln!("Received response for unknown request id {}", response.id);
+            return;
+        };
+
+        let result = match (response.result, response.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
+        };
+        let _ = tx.send(result);
+    }
+
+    fn fail_pending(&self, reason: &str) {
+        for (_, tx) in self.pending_map().drain() {
+            let _ = tx.send(Err(MCPError::new("CONNECTION_CLOSED", reason)));
+        }
+    }
+
+    async fn answer(&self, request: JsonRpcRequest) {
+        let answering = Answering::new(&self.answering);
+        let result = self.handler.handle_request(&request.method, request.params).await;
+        drop(answering);
+        if let Err(e) = self.transport.send(JsonRpcMessage::response(request.id, result)).await {
+            eprintln!("Failed to answer {} request: {}", request.method, e);
+        }
+    }
+}
+
+/// Counts a server request as being answered until dropped.
+struct Answering<'a>(&'a watch::Sender<usize>);
+
+impl<'a> Answering<'a> {
+    fn new(count: &'a watch::Sender<usize>) -> Self {
+        count.send_modify(|count| *count += 1);
+        Self(count)
+    }
+}
+
+impl Drop for Answering<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// A request waiting for its response. Dropping it unfinished, because the
+/// request timed out or the caller was aborted, tells the server to stop.
+struct InFlight<'a> {
+    session: &'a McpSession,
+    id: Option<RequestId>,
+    progress_token: Option<String>,
+    reason: &'static str,
+}
+
+impl<'a> InFlight<'a> {
+    fn new(session: &'a McpSession, id: RequestId) -> Self {
+        Self {
+            session,
+            id: Some(id),
+            progress_token: None,
+            reason: "Cancelled by the user",
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.session.pending_map().remove(&id);
+        }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Some(token) = self.progress_token.take() {
+            self.session.progress_map().remove(&token);
+        }
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        self.session.pending_map().remove(&id);
+        if self.session.is_closed() {
+            return;
+        }
+
+        let transport = self.session.transport.clone();
+        let notification = JsonRpcMessage::notification(
+            "notifications/cancelled",
+            Some(json!({ "requestId": id, "reason": self.reason })),
+        );
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = transport.send(notification).await;
+            });
+        }
+    }
+}
+
+async fn read_loop(session: Weak<McpSession>, transport: Arc<dyn Transport>) {
+    while let Some(incoming) = transport.receive().await {
+        let Some(session) = session.upgrade() else {
+            return;
+        };
+
+        match incoming {
+            Incoming::Message(JsonRpcMessage::Response(response)) => session.complete(response),
+            Incoming::Message(JsonRpcMessage::Request(request)) => {
+                // Server requests such as sampling may wait on the user, so
+                // they must not block the reader.
+                tokio::spawn(async move { session.answer(request).await });
+            }
+            Incoming::Message(JsonRpcMessage::Notification(notification)) => {
+                if notification.method == "notifications/progress" {
+                    session.progressed(notification.params.as_ref());
+                }
+                session
+                    .handler
+                    .handle_notification(&notification.method, notification.params)
+                    .await;
+            }
+            Incoming::Reconnected => {
+                session.fail_pending("Server restarted");
+                tokio::spawn(async move {
+                    if let Err(e) = session.initialize().await {
+                        eprintln!("Failed to re-initialize MCP session: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    if let Some(session) = session.upgrade() {
+        session.closed.store(true, Ordering::SeqCst);
+        session.fail_pending("Transport closed");
+    }
+}
+
+fn progress_token(params: Option<&Value>) -> Option<String> {
+    params?.get("_meta")?.get("progressToken").map(token_string)
+}
+
+/// Progress tokens may be strings or numbers; both are keyed as text.
+fn token_string(token: &Value) -> String {
+    match token {
+        Value::String(token) => token.clone(),
+        token => token.to_string(),
+    }
+}
+
+async fn progressed(notify: Option<&Notify>) {
+    match notify {
+        Some(notify) => notify.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn decode<T: DeserializeOwned>(value: Value) -> Result<T, MCPError> {
+    serde_json::from_value(value).map_err(|e| MCPError::new("INVALID_RESPONSE", e.to_string()))
+}
+
+#[async_trait]
+impl ToolProvider for McpSession {
+    async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError> {
+        McpSession::list_tools(self).await
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, MCPError> {
+        McpSession::call_tool(self, name, arguments).await
+    }
+
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: &str,
+    ) -> Result<CallToolResult, MCPError> {
+        McpSession::call_tool_with_progress(self, name, arguments, progress_token).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::mcp::transport::ChannelTransport;
+    use crate::mcp::types::ToolContent;
+
+    /// Minimal in-process MCP server. `respond` maps a method and its params
+    /// to a result or error; the handshake is handled here.
+    pub(crate) fn spawn_fake_server<F>(transport: ChannelTransport, version: &'static str, respond: F)
+    where
+        F: Fn(&str, Option<Value>) -> Result<Value, JsonRpcError> + Send + Sync + 'static,
+    {
+        tokio::spawn(async move {
+            while let Some(Incoming::Message(message)) = transport.receive().await {
+                let JsonRpcMessage::Request(request) = message else {
+                    continue;
+                };
+                let result = match request.method.as_str() {
+                    "initialize" => Ok(json!({
+                        "protocolVersion": version,
+                        "capabilities": {
+                            "tools": { "listChanged": true },
+                            "resources": { "subscribe": true }
+                        },
+                        "serverInfo": { "name": "fake", "version": "0.0.1" }
+                    })),
+                    method => respond(method, request.params),
+                };
+                let _ = transport.send(JsonRpcMessage::response(request.id, result)).await;
+            }
+        });
+    }
+
+    /// Like [`spawn_fake_server`], but hands every request after the
+    /// handshake to `respond`, which runs as its own task and may talk back.
+    fn spawn_fake_server_with<F, Fut>(transport: Arc<ChannelTransport>, respond: F)
+    where
+        F: Fn(Arc<ChannelTransport>, JsonRpcRequest) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(async move {
+            while let Some(Incoming::Message(message)) = transport.receive().await {
+                let JsonRpcMessage::Request(request) = message else {
+                    continue;
+                };
+                if request.method == "initialize" {
+                    let result = json!({
+                        "protocolVersion": LATEST_PROTOCOL_VERSION,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "fake", "version": "0.0.1" }
+                    });
+                    let _ = transport.send(JsonRpcMessage::response(request.id, Ok(result))).await;
+                    continue;
+                }
+                tokio::spawn(respond(transport.clone(), request));
+            }
+        });
+    }
+
+    fn tool(name: &str) -> Value {
+        json!({ "name": name, "inputSchema": { "type": "object" } })
+    }
+
+    #[test]
+    fn test_message_framing() {
+        let request: JsonRpcMessage =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).unwrap();
+        assert!(matches!(request, JsonRpcMessage::Request(_)));
+
+        let notification: JsonRpcMessage =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).unwrap();
+        assert!(matches!(notification, JsonRpcMessage::Notification(_)));
+
+        let response: JsonRpcMessage =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"nope"}}"#)
+                .unwrap();
+        assert!(matches!(
+            response,
+            JsonRpcMessage::Response(JsonRpcResponse { id: RequestId::String(_), error: Some(_), .. })
+        ));
+
+        let encoded = serde_json::to_value(JsonRpcMessage::response(RequestId::Number(3), Ok(json!({})))).unwrap();
+        assert_eq!(encoded, json!({ "jsonrpc": "2.0", "id": 3, "result": {} }));
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let error: MCPError = JsonRpcError {
+            code: INVALID_PARAMS,
+            message: "bad args".to_string(),
+            data: Some(json!({ "field": "city" })),
+        }
+        .into();
+
+        assert_eq!(error.code, "INVALID_PARAMS");
+        assert_eq!(error.message, "bad args");
+        let details = error.details.unwrap();
+        assert_eq!(details["jsonrpc_code"], json!(-32602));
+        assert_eq!(details["data"], json!({ "field": "city" }));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_pagination_and_tool_call() {
+        let (client, server) = ChannelTransport::pair();
+        spawn_fake_server(server, LATEST_PROTOCOL_VERSION, |method, params| match method {
+            "tools/list" => match params.and_then(|p| p.get("cursor").cloned()) {
+                None => Ok(json!({ "tools": [tool("a"), tool("b")], "nextCursor": "page-2" })),
+                Some(cursor) if cursor == "page-2" => Ok(json!({ "tools": [tool("c")] })),
+                Some(_) => Err(JsonRpcError::invalid_params("bad cursor")),
+            },
+            "tools/call" => {
+                let params = params.unwrap();
+                if params["name"] == "a" {
+                    Ok(json!({ "content": [{ "type": "text", "text": params["arguments"]["q"] }] }))
+                } else {
+                    Err(JsonRpcError::invalid_params("Unknown tool"))
+                }
+            }
+            method => Err(JsonRpcError::method_not_found(method)),
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        let info = session.server_info().unwrap();
+        assert_eq!(info.server_info.name, "fake");
+        assert!(info.capabilities.tools.unwrap().list_changed);
+
+        let names: Vec<String> = session.list_tools().await.unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        let result = session.call_tool("a", json!({ "q": "echo" })).await.unwrap();
+        assert_eq!(result.content, vec![ToolContent::Text { text: "echo".to_string() }]);
+
+        let error = session.call_tool("zzz", json!({})).await.unwrap_err();
+        assert_eq!(error.code, "INVALID_PARAMS");
+    }
+
+    #[tokio::test]
+    async fn test_resources() {
+        let (client, server) = ChannelTransport::pair();
+        spawn_fake_server(server, LATEST_PROTOCOL_VERSION, |method, params| match method {
+            "resources/list" => Ok(json!({ "resources": [
+                { "uri": "file:///notes.md", "name": "notes.md", "mimeType": "text/markdown" }
+            ] })),
+            "resources/templates/list" => Ok(json!({ "resourceTemplates": [
+                { "uriTemplate": "db://rows/{id}", "name": "row" }
+            ] })),
+            "resources/read" => Ok(json!({ "contents": [
+                { "uri": params.unwrap()["uri"], "mimeType": "text/markdown", "text": "# Notes" }
+            ] })),
+            "resources/subscribe" => Ok(json!({})),
+            method => Err(JsonRpcError::method_not_found(method)),
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+
+        let resources = session.list_resources().await.unwrap();
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/markdown"));
+
+        let templates = session.list_resource_templates().await.unwrap();
+        assert_eq!(templates[0].uri_template, "db://rows/{id}");
+
+        let read = session.read_resource("file:///notes.md").await.unwrap();
+        assert_eq!(read.contents[0].text.as_deref(), Some("# Notes"));
+
+        session.subscribe_resource("file:///notes.md").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsupported_protocol_version() {
+        let (client, server) = ChannelTransport::pair();
+        spawn_fake_server(server, "1999-01-01", |method, _| Err(JsonRpcError::method_not_found(method)));
+
+        let error = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code, "UNSUPPORTED_PROTOCOL_VERSION");
+    }
+
+    #[tokio::test]
+    async fn test_answers_server_ping() {
+        let (client, server) = ChannelTransport::pair();
+        let server = Arc::new(server);
+
+        let responder = server.clone();
+        tokio::spawn(async move {
+            let Some(Incoming::Message(JsonRpcMessage::Request(init))) = responder.receive().await else {
+                panic!("expected initialize");
+            };
+            responder
+                .send(JsonRpcMessage::response(init.id, Ok(json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": { "name": "fake", "version": "1" }
+                }))))
+                .await
+                .unwrap();
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+        assert!(session.list_tools().await.unwrap().is_empty());
+
+        let Some(Incoming::Message(JsonRpcMessage::Notification(initialized))) = server.receive().await else {
+            panic!("expected initialized notification");
+        };
+        assert_eq!(initialized.method, "notifications/initialized");
+
+        server
+            .send(JsonRpcMessage::request(RequestId::String("srv-1".to_string()), "ping", None))
+            .await
+            .unwrap();
+        let Some(Incoming::Message(JsonRpcMessage::Response(pong))) = server.receive().await else {
+            panic!("expected ping response");
+        };
+        assert_eq!(pong.id, RequestId::String("srv-1".to_string()));
+        assert_eq!(pong.result, Some(json!({})));
+    }
+
+    #[tokio::test]
+    async fn test_progress_keeps_a_call_alive() {
+        let (client, server) = ChannelTransport::pair();
+        let server = Arc::new(server);
+        spawn_fake_server_with(server.clone(), |server, request| async move {
+            // Slower than the timeout, but reporting progress all along.
+            for progress in 1..=4 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let params = json!({ "progressToken": "token-1", "progress": progress });
+                server
+                    .send(JsonRpcMessage::notification("notifications/progress", Some(params)))
+                    .await
+                    .unwrap();
+            }
+            server
+                .send(JsonRpcMessage::response(request.id, Ok(json!({ "content": [] }))))
+                .await
+                .unwrap();
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+        let params = |token: &str| json!({ "name": "build", "arguments": {}, "_meta": { "progressToken": token } });
+        let timeout = Duration::from_millis(250);
+
+        session.request_with_timeout("tools/call", Some(params("token-1")), timeout).await.unwrap();
+        assert!(session.progress_map().is_empty());
+
+        // Progress for another call does not help.
+        let error = session
+            .request_with_timeout("tools/call", Some(params("token-2")), timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, "TIMEOUT");
+    }
+
+    /// Takes its time to answer, like a user filling in an elicitation form.
+    struct SlowUser;
+
+    #[async_trait]
+    impl ClientHandler for SlowUser {
+        async fn handle_request(&self, method: &str, _params: Option<Value>) -> Result<Value, JsonRpcError> {
+            assert_eq!(method, "elicitation/create");
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            Ok(json!({ "action": "accept", "content": { "confirm": true } }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_waits_for_the_user_past_its_timeout() {
+        let (client, server) = ChannelTransport::pair();
+        tokio::spawn(async move {
+            let mut call_id = None;
+            while let Some(Incoming::Message(message)) = server.receive().await {
+                let reply = match message {
+                    JsonRpcMessage::Request(request) if request.method == "initialize" => {
+                        JsonRpcMessage::response(request.id, Ok(json!({
+                            "protocolVersion": LATEST_PROTOCOL_VERSION,
+                            "capabilities": { "tools": {} },
+                            "serverInfo": { "name": "fake", "version": "1" }
+                        })))
+                    }
+                    // Ask the user before deploying.
+                    JsonRpcMessage::Request(request) => {
+                        call_id = Some(request.id);
+                        let params = json!({ "message": "Deploy to production?", "requestedSchema": {} });
+                        JsonRpcMessage::request(RequestId::String("srv-1".to_string()), "elicitation/create", Some(params))
+                    }
+                    JsonRpcMessage::Response(answer) => {
+                        let confirmed = answer.result.unwrap()["content"]["confirm"].clone();
+                        let result = json!({ "content": [{ "type": "text", "text": format!("deployed: {}", confirmed) }] });
+                        JsonRpcMessage::response(call_id.take().unwrap(), Ok(result))
+                    }
+                    JsonRpcMessage::Notification(_) => continue,
+                };
+                server.send(reply).await.unwrap();
+            }
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(SlowUser)).await.unwrap();
+        let result = session
+            .request_with_timeout(
+                "tools/call",
+                Some(json!({ "name": "deploy", "arguments": {} })),
+                Duration::from_millis(150),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "deployed: true");
+    }
+
+    #[tokio::test]
+    async fn test_aborted_call_is_cancelled_on_the_server() {
+        let (client, server) = ChannelTransport::pair();
+        let server = Arc::new(server);
+
+        let responder = server.clone();
+        tokio::spawn(async move {
+            let Some(Incoming::Message(JsonRpcMessage::Request(init))) = responder.receive().await else {
+                panic!("expected initialize");
+            };
+            responder
+                .send(JsonRpcMessage::response(init.id, Ok(json!({
+                    "protocolVersion": LATEST_PROTOCOL_VERSION,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "fake", "version": "1" }
+                }))))
+                .await
+                .unwrap();
+        });
+
+        let session = McpSession::connect(Arc::new(client), Arc::new(DefaultClientHandler))
+            .await
+            .unwrap();
+        let Some(Incoming::Message(JsonRpcMessage::Notification(_))) = server.receive().await else {
+            panic!("expected initialized notification");
+        };
+
+        let caller = session.clone();
+        let call = tokio::spawn(async move {
+            caller.call_tool_with_progress("index", json!({}), "token-1").await
+        });
+        let Some(Incoming::Message(JsonRpcMessage::Request(request))) = server.receive().await else {
+            panic!("expected tools/call");
+        };
+        assert_eq!(request.params.unwrap()["_meta"]["progressToken"], "token-1");
+
+        call.abort();
+        let Some(Incoming::Message(JsonRpcMessage::Notification(cancelled))) = server.receive().await else {
+            panic!("expected cancellation");
+        };
+        assert_eq!(cancelled.method, "notifications/cancelled");
+        assert_eq!(cancelled.params.unwrap()["requestId"], json!(request.id));
+        assert!(session.pending_map().is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::tool_loop::ToolProvider;
+use super::types::{CallToolResult, MCPError, MCPServer, MCPTool, ToolOverrides};
+
+pub const TOOLS_CHANGED_EVENT: &str = "mcp-tools-changed";
+
+/// The API limits tool names to this many characters.
+const MAX_NAME_LEN: usize = 64;
+
+/// Separates the server prefix from the tool name.
+const SEPARATOR: &str = "__";
+
+/// Whether the Messages API accepts `name` as a tool name.
+pub fn is_valid_tool_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn sanitize(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    sanitized.trim_matches('_').to_string()
+}
+
+fn truncate(name: &str, len: usize) -> &str {
+    &name[..name.len().min(len)]
+}
+
+/// Makes `name` unique among `taken` by appending a counter.
+fn claim(taken: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        let suffix = format!("_{}", n);
+        candidate = format!("{}{}", truncate(&name, MAX_NAME_LEN - suffix.len()), suffix);
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// How server tools are named for the model: each server gets a prefix
+/// derived from its name, and users can alias or hide single tools.
+#[derive(Debug, Clone, Default)]
+pub struct ToolNaming {
+    labels: HashMap<String, String>,
+    overrides: ToolOverrides,
+}
+
+impl ToolNaming {
+    pub fn new(servers: &[MCPServer], overrides: ToolOverrides) -> Self {
+        Self {
+            labels: servers.iter().map(|s| (s.id.clone(), s.name.clone())).collect(),
+            overrides,
+        }
+    }
+}
+
+/// A server tool as offered to the model.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RegisteredTool {
+    /// The namespaced name or the user's alias.
+    pub name: String,
+    pub server_id: String,
+    pub tool_name: String,
+    pub hidden: bool,
+    pub tool: MCPTool,
+}
+
+/// Names the tools of every server. Prefixes and names are made unique in
+/// server order, so an earlier server keeps its names when others join.
+pub fn register(listed: Vec<(String, Vec<MCPTool>)>, naming: &ToolNaming) -> Vec<RegisteredTool> {
+    let mut prefixes = HashSet::new();
+    let mut names = HashSet::new();
+    let mut aliases = HashSet::new();
+    let mut registered = Vec::new();
+
+    // Aliases are reserved first so generated names never take them.
+    for tools in naming.overrides.values() {
+        names.extend(tools.values().filter_map(|o| o.alias.clone()));
+    }
+
+    for (server_id, tools) in listed {
+        let label = naming.labels.get(&server_id).map(|name| sanitize(name).to_lowercase());
+        let label = label.filter(|l| !l.is_empty()).unwrap_or_else(|| sanitize(&server_id));
+        let prefix = claim(&mut prefixes, truncate(&label, MAX_NAME_LEN / 2).to_string());
+        let overrides = naming.overrides.get(&server_id);
+
+        for tool in tools {
+            let tool_override = overrides.and_then(|o| o.get(&tool.name)).cloned().unwrap_or_default();
+            let name = match tool_override.alias {
+                Some(alias) if aliases.insert(alias.clone()) => alias,
+                Some(alias) => claim(&mut names, alias),
+                None => {
+                    let name = format!("{}{}{}", prefix, SEPARATOR, sanitize(&tool.name));
+                    claim(&mut names, truncate(&name, MAX_NAME_LEN).to_string())
+                }
+            };
+            registered.push(RegisteredTool {
+                name,
+                server_id: server_id.clone(),
+                tool_name: tool.name.clone(),
+                hidden: tool_override.hidden,
+                tool,
+            });
+        }
+    }
+    registered
+}
+
+struct CachedList {
+    provider: Weak<dyn ToolProvider>,
+    tools: Vec<MCPTool>,
+}
+
+/// Tool lists per server, kept until the server reports a change or the
+/// session is replaced.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    cache: Arc<Mutex<HashMap<String, CachedList>>>,
+}
+
+impl ToolRegistry {
+    fn cached(&self, server_id: &str, provider: &Arc<dyn ToolProvider>) -> Option<Vec<MCPTool>> {
+        let cache = self.cache.lock().ok()?;
+        let entry = cache.get(server_id)?;
+        Weak::ptr_eq(&entry.provider, &Arc::downgrade(provider)).then(|| entry.tools.clone())
+    }
+
+    pub async fn list(&self, server_id: &str, provider: &Arc<dyn ToolProvider>) -> Result<Vec<MCPTool>, MCPError> {
+        if let Some(tools) = self.cached(server_id, provider) {
+            return Ok(tools);
+        }
+        self.refresh(server_id, provider).await
+    }
+
+    /// Fetches the tool list again, e.g. after `notifications/tools/list_changed`.
+    pub async fn refresh(&self, server_id: &str, provider: &Arc<dyn ToolProvider>) -> Result<Vec<MCPTool>, MCPError> {
+        let tools = provider.list_tools().await?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(
+                server_id.to_string(),
+                CachedList {
+                    provider: Arc::downgrade(provider),
+                    tools: tools.clone(),
+                },
+            );
+        }
+        Ok(tools)
+    }
+
+    pub fn invalidate(&self, server_id: &str) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.remove(server_id);
+        }
+    }
+
+    /// Wraps a server's provider so its tool list is served from the cache.
+    pub fn cached_provider(&self, server_id: &str, provider: Arc<dyn ToolProvider>) -> Arc<dyn ToolProvider> {
+        Arc::new(CachedTools {
+            server_id: server_id.to_string(),
+            provider,
+            registry: self.clone(),
+        })
+    }
+}
+
+struct CachedTools {
+    server_id: String,
+    provider: Arc<dyn ToolProvider>,
+    registry: ToolRegistry,
+}
+
+#[async_trait]
+impl ToolProvider for CachedTools {
+    async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError> {
+        self.registry.list(&self.server_id, &self.provider).await
+    }
+
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, MCPError> {
+        self.provider.call_tool(name, arguments).await
+    }
+
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: &str,
+    ) -> Result<CallToolResult, MCPError> {
+        self.provider.call_tool_with_progress(name, arguments, progress_token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::{MCPTransport, ToolOverride};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tool(name: &str) -> MCPTool {
+        MCPTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+            output_schema: None,
+        }
+    }
+
+    fn server(id: &str, name: &str) -> MCPServer {
+        MCPServer {
+            id: id.to_string(),
+            name: name.to_string(),
+            transport: MCPTransport::Http { url: "http://localhost".to_string() },
+            token: None,
+            is_active: true,
+            last_connected: None,
+        }
+    }
+
+    #[test]
+    fn test_namespacing_aliases_and_hiding() {
+        let overrides = BTreeMap::from([(
+            "2".to_string(),
+            BTreeMap::from([
+                ("search".to_string(), ToolOverride { alias: Some("search_code".to_string()), hidden: false }),
+                ("delete_repo".to_string(), ToolOverride { alias: None, hidden: true }),
+            ]),
+        )]);
+        let naming = ToolNaming::new(
+            &[server("1", "Docs Search"), server("2", "GitHub"), server("3", "github")],
+            overrides,
+        );
+
+        let registered = register(
+            vec![
+                ("1".to_string(), vec![tool("search"), tool("fetch.page")]),
+                ("2".to_string(), vec![tool("search"), tool("delete_repo")]),
+                ("3".to_string(), vec![tool("search")]),
+            ],
+            &naming,
+        );
+        let names: Vec<_> = registered.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["docs_search__search", "docs_search__fetch_page", "search_code", "github__delete_repo", "github_2__search"]
+        );
+        assert!(registered.iter().all(|t| is_valid_tool_name(&t.name)));
+        assert_eq!((registered[2].server_id.as_str(), registered[2].tool_name.as_str()), ("2", "search"));
+        assert!(registered[3].hidden);
+
+        // Unknown servers fall back to their id; long names are cut to the limit.
+        let long = "x".repeat(80);
+        let registered = register(vec![("abc".to_string(), vec![tool(&long), tool(&long)])], &ToolNaming::default());
+        assert_eq!(registered[0].name.len(), MAX_NAME_LEN);
+        assert!(registered[1].name.ends_with("_2") && registered[1].name.len() == MAX_NAME_LEN);
+    }
+
+    struct CountingServer(AtomicUsize);
+
+    #[async_trait]
+    impl ToolProvider for CountingServer {
+        async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![tool("search")])
+        }
+
+        async fn call_tool(&self, _: &str, _: Value) -> Result<CallToolResult, MCPError> {
+            Ok(CallToolResult {
+                content: Vec::new(),
+                is_error: false,
+                structured_content: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_lists_are_cached_until_invalidated() {
+        let registry = ToolRegistry::default();
+        let server = Arc::new(CountingServer(AtomicUsize::new(0)));
+        let cached = registry.cached_provider("s", server.clone());
+
+        cached.list_tools().await.unwrap();
+        cached.list_tools().await.unwrap();
+        assert_eq!(server.0.load(Ordering::SeqCst), 1);
+
+        registry.invalidate("s");
+        cached.list_tools().await.unwrap();
+        assert_eq!(server.0.load(Ordering::SeqCst), 2);
+
+        // A new session for the same server is asked again.
+        let restarted = Arc::new(CountingServer(AtomicUsize::new(0)));
+        registry.cached_provider("s", restarted.clone()).list_tools().await.unwrap();
+        assert_eq!(restarted.0.load(Ordering::SeqCst), 1);
+    }
+}
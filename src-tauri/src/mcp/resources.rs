@@ -0,0 +1,71 @@
+use super::types::ResourceContents;
+use crate::anthropic::types::{ContentBlock, ImageSource};
+
+/// Turns the contents of a `resources/read` result into content blocks that
+/// can be attached to a user message as context.
+pub fn context_blocks(contents: Vec<ResourceContents>) -> Vec<ContentBlock> {
+    contents.into_iter().map(context_block).collect()
+}
+
+fn context_block(contents: ResourceContents) -> ContentBlock {
+    let mime_type = contents.mime_type.unwrap_or_else(|| "text/plain".to_string());
+
+    if let Some(text) = contents.text {
+        return ContentBlock::Text {
+            text: format!(
+                "<resource uri=\"{}\" mime_type=\"{}\">\n{}\n</resource>",
+                contents.uri, mime_type, text
+            ),
+        };
+    }
+
+    match contents.blob {
+        Some(data) if mime_type.starts_with("image/") => ContentBlock::Image {
+            source: ImageSource {
+                source_type: "base64".to_string(),
+                media_type: mime_type,
+                data,
+            },
+        },
+        // The model can't do anything useful with other binary payloads.
+        _ => ContentBlock::Text {
+            text: format!(
+                "<resource uri=\"{}\" mime_type=\"{}\">[binary content omitted]</resource>",
+                contents.uri, mime_type
+            ),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(mime_type: &str, text: Option<&str>, blob: Option<&str>) -> ResourceContents {
+        ResourceContents {
+            uri: "file:///notes.md".to_string(),
+            mime_type: Some(mime_type.to_string()),
+            text: text.map(str::to_string),
+            blob: blob.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_context_blocks() {
+        let blocks = context_blocks(vec![
+            contents("text/markdown", Some("# Notes"), None),
+            contents("image/png", None, Some("iVBORw0KGgo=")),
+            contents("application/pdf", None, Some("JVBERi0=")),
+        ]);
+
+        assert_eq!(
+            blocks[0],
+            ContentBlock::Text {
+                text: "<resource uri=\"file:///notes.md\" mime_type=\"text/markdown\">\n# Notes\n</resource>"
+                    .to_string()
+            }
+        );
+        assert!(matches!(&blocks[1], ContentBlock::Image { source } if source.media_type == "image/png"));
+        assert!(matches!(&blocks[2], ContentBlock::Text { text } if text.contains("binary content omitted")));
+    }
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+use super::approval::{ask_user, ApprovalBroker, ApprovalDecision};
+use super::protocol::JsonRpcError;
+use super::types::ToolContent;
+use crate::anthropic::client::AnthropicClient;
+use crate::anthropic::commands::AnthropicState;
+use crate::anthropic::types::{
+    ContentBlock, ImageSource, Message, MessageContent, MessagesRequest, MessagesResponse, Role, StopReason,
+};
+
+pub const SAMPLING_APPROVAL_EVENT: &str = "mcp-sampling-approval";
+
+/// Error code the spec uses for a sampling request the user turned down.
+const USER_REJECTED: i64 = -1;
+
+/// Models a server can get, from most to least capable.
+const OPUS: &str = "claude-opus-4-1";
+const SONNET: &str = "claude-sonnet-4-5";
+const HAIKU: &str = "claude-haiku-4-5";
+const MODELS: &[&str] = &[OPUS, SONNET, HAIKU];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ModelHint {
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPreferences {
+    #[serde(default)]
+    pub hints: Vec<ModelHint>,
+    #[serde(default)]
+    pub cost_priority: Option<f64>,
+    #[serde(default)]
+    pub speed_priority: Option<f64>,
+    #[serde(default)]
+    pub intelligence_priority: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: ToolContent,
+}
+
+/// Params of a `sampling/createMessage` request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(default)]
+    pub model_preferences: Option<ModelPreferences>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    pub max_tokens: u32,
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: ToolContent,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+/// Payload of [`SAMPLING_APPROVAL_EVENT`]; answered via `respond_tool_approval`.
+#[derive(Debug, Serialize, Clone)]
+pub struct SamplingApprovalRequest {
+    pub approval_id: String,
+    pub server_id: String,
+    pub request: MessagesRequest,
+}
+
+/// Picks a model for the server's preferences. Hints are matched in order,
+/// as a substring of a model name or by family ("sonnet", "claude-3-haiku");
+/// hints for other vendors fall through to the priorities.
+pub fn select_model(preferences: Option<&ModelPreferences>) -> &'static str {
+    let Some(preferences) = preferences else {
+        return SONNET;
+    };
+
+    for hint in preferences.hints.iter().filter_map(|h| h.name.as_deref()) {
+        let hint = hint.to_lowercase();
+        if let Some(model) = MODELS.iter().find(|model| model.contains(&hint)) {
+            return model;
+        }
+        for (family, model) in [("opus", OPUS), ("sonnet", SONNET), ("haiku", HAIKU)] {
+            if hint.contains(family) {
+                return model;
+            }
+        }
+    }
+
+    let intelligence = preferences.intelligence_priority.unwrap_or(0.0);
+    let economy = preferences
+        .cost_priority
+        .unwrap_or(0.0)
+        .max(preferences.speed_priority.unwrap_or(0.0));
+    if intelligence > economy {
+        OPUS
+    } else if economy > intelligence {
+        HAIKU
+    } else {
+        SONNET
+    }
+}
+
+fn content_block(content: &ToolContent) -> Result<ContentBlock, JsonRpcError> {
+    match content {
+        ToolContent::Text { text } => Ok(ContentBlock::Text { text: text.clone() }),
+        ToolContent::Image { data, mime_type } => Ok(ContentBlock::Image {
+            source: ImageSource {
+                source_type: "base64".to_string(),
+                media_type: mime_type.clone(),
+                data: data.clone(),
+            },
+        }),
+        _ => Err(JsonRpcError::invalid_params("Only text and image content can be sampled")),
+    }
+}
+
+/// Translates the server's request into a Messages API request.
+pub fn messages_request(params: &CreateMessageParams) -> Result<MessagesRequest, JsonRpcError> {
+    if params.messages.is_empty() {
+        return Err(JsonRpcError::invalid_params("messages must not be empty"));
+    }
+
+    let messages = params
+        .messages
+        .iter()
+        .map(|message| {
+            Ok(Message {
+                role: message.role,
+                content: MessageContent::Blocks(vec![content_block(&message.content)?]),
+            })
+        })
+        .collect::<Result<Vec<_>, JsonRpcError>>()?;
+
+    Ok(MessagesRequest {
+        model: select_model(params.model_preferences.as_ref()).to_string(),
+        messages,
+        max_tokens: params.max_tokens,
+        system: params.system_prompt.clone(),
+        temperature: params.temperature,
+        stop_sequences: params.stop_sequences.clone(),
+        ..Default::default()
+    })
+}
+
+fn stop_reason(reason: StopReason) -> String {
+    match reason {
+        StopReason::EndTurn => "endTurn",
+        StopReason::MaxTokens => "maxTokens",
+        StopReason::StopSequence => "stopSequence",
+        StopReason::ToolUse => "toolUse",
+        StopReason::PauseTurn => "pauseTurn",
+        StopReason::Refusal => "refusal",
+    }
+    .to_string()
+}
+
+fn create_message_result(response: &MessagesResponse) -> CreateMessageResult {
+    CreateMessageResult {
+        role: Role::Assistant,
+        content: ToolContent::Text { text: response.text() },
+        model: response.model.clone(),
+        stop_reason: response.stop_reason.map(stop_reason),
+    }
+}
+
+/// Sends an approved request with the user's key.
+pub async fn sample(client: &AnthropicClient, request: &MessagesRequest) -> Result<CreateMessageResult, JsonRpcError> {
+    let response = client
+        .send_message(request)
+        .await
+        .map_err(|e| JsonRpcError::internal(e.to_string()))?;
+    Ok(create_message_result(&response))
+}
+
+/// Answers `sampling/createMessage` from `server_id`. Nothing is sent to
+/// the API until the user has seen and approved the prompt.
+pub async fn create_message(app: &AppHandle, server_id: &str, params: Option<Value>) -> Result<Value, JsonRpcError> {
+    let params: CreateMessageParams = serde_json::from_value(params.unwrap_or(Value::Null))
+        .map_err(|e| JsonRpcError::invalid_params(e.to_string()))?;
+    let request = messages_request(&params)?;
+
+    let approval_id = Uuid::new_v4().to_string();
+    let prompt = SamplingApprovalRequest {
+        approval_id: approval_id.clone(),
+        server_id: server_id.to_string(),
+        request: request.clone(),
+    };
+    let approvals = app.state::<ApprovalBroker>();
+    match ask_user(app, &approvals, SAMPLING_APPROVAL_EVENT, &approval_id, prompt).await {
+        Ok(Some(ApprovalDecision::Approve)) => {}
+        Ok(_) => return Err(JsonRpcError::new(USER_REJECTED, "User rejected sampling request")),
+        Err(e) => return Err(JsonRpcError::internal(e)),
+    }
+
+    let client = app
+        .state::<AnthropicState>()
+        .client()
+        .map_err(JsonRpcError::internal)?;
+    let result = sample(&client, &request).await?;
+    Ok(json!(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::protocol::INVALID_PARAMS;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn preferences(hints: &[&str], cost: f64, speed: f64, intelligence: f64) -> ModelPreferences {
+        ModelPreferences {
+            hints: hints
+                .iter()
+                .map(|name| ModelHint { name: Some(name.to_string()) })
+                .collect(),
+            cost_priority: Some(cost),
+            speed_priority: Some(speed),
+            intelligence_priority: Some(intelligence),
+        }
+    }
+
+    #[test]
+    fn test_model_selection() {
+        assert_eq!(select_model(None), SONNET);
+        assert_eq!(select_model(Some(&preferences(&["claude-3-haiku"], 0.0, 0.0, 1.0))), HAIKU);
+        assert_eq!(select_model(Some(&preferences(&["gpt-4o", "opus"], 1.0, 0.0, 0.0))), OPUS);
+        assert_eq!(select_model(Some(&preferences(&["gemini-1.5-pro"], 0.2, 0.3, 0.9))), OPUS);
+        assert_eq!(select_model(Some(&preferences(&[], 0.8, 0.5, 0.5))), HAIKU);
+        assert_eq!(select_model(Some(&preferences(&[], 0.5, 0.5, 0.5))), SONNET);
+    }
+
+    #[tokio::test]
+    async fn test_sampling_round_trip() {
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({
+                "model": HAIKU,
+                "system": "You are a summarizer.",
+                "max_tokens": 100,
+                "messages": [{ "role": "user", "content": [{ "type": "text", "text": "Summarize this." }] }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg", "type": "message", "role": "assistant", "model": HAIKU,
+                "content": [{ "type": "text", "text": "A summary." }],
+                "stop_reason": "end_turn", "stop_sequence": null,
+                "usage": { "input_tokens": 5, "output_tokens": 3 }
+            })))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        let params: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": "Summarize this." } }],
+            "modelPreferences": { "hints": [{ "name": "haiku" }], "speedPriority": 0.8 },
+            "systemPrompt": "You are a summarizer.",
+            "includeContext": "none",
+            "maxTokens": 100
+        }))
+        .unwrap();
+        let request = messages_request(&params).unwrap();
+        let client = AnthropicClient::new("test-key").with_base_url(api.uri());
+
+        let result = json!(sample(&client, &request).await.unwrap());
+        assert_eq!(
+            result,
+            json!({
+                "role": "assistant",
+                "content": { "type": "text", "text": "A summary." },
+                "model": HAIKU,
+                "stopReason": "endTurn"
+            })
+        );
+
+        let audio: CreateMessageParams = serde_json::from_value(json!({
+            "messages": [{ "role": "user", "content": { "type": "audio", "data": "", "mimeType": "audio/wav" } }],
+            "maxTokens": 10
+        }))
+        .unwrap();
+        assert_eq!(messages_request(&audio).unwrap_err().code, INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use fd_lock::RwLock;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// How many last-known-good copies of the config are kept.
+pub const LAST_KNOWN_GOOD: usize = 3;
+
+/// What the config file looked like when we last read or wrote it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: String,
+}
+
+impl FileStamp {
+    /// Returns `None` if the file does not exist.
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let content = fs::read(path)?;
+
+        Ok(Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hash(&content),
+        }))
+    }
+}
+
+/// Whether the file differs from `known`. The mtime and size are checked
+/// first; the content hash decides when they disagree (e.g. after a touch).
+pub fn changed_since(path: &Path, known: Option<&FileStamp>) -> io::Result<bool> {
+    let current = FileStamp::read(path)?;
+    Ok(match (known, current) {
+        (None, None) => false,
+        (Some(known), Some(current)) => {
+            let untouched = known.modified.is_some()
+                && known.modified == current.modified
+                && known.len == current.len;
+            !untouched && known.hash != current.hash
+        }
+        _ => true,
+    })
+}
+
+pub fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Runs `f` while holding an exclusive advisory lock on a lock file next to
+/// `path`, so several app instances don't interleave reads and writes.
+pub fn with_lock<T, E: From<io::Error>>(path: &Path, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling(path, "lock"))?;
+    let mut lock = RwLock::new(file);
+    let _guard = lock.write()?;
+    f()
+}
+
+/// Writes to a temporary file in the same directory and renames it over the
+/// target, so readers see either the old or the new content, never a mix.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    write_atomic_with(path, content, OpenOptions::new())
+}
+
+/// Like [`write_atomic`], but the file is readable only by the current user
+/// from the moment it is created.
+pub fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    write_atomic_with(path, content, options)
+}
+
+fn write_atomic_with(path: &Path, content: &[u8], mut options: OpenOptions) -> io::Result<()> {
+    let tmp = sibling(path, &format!("{}.tmp", Uuid::new_v4()));
+
+    let result = (|| {
+        let mut file = options.write(true).create_new(true).open(&tmp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result?;
+
+    // Persist the rename itself.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("mcp.json");
+    path.with_file_name(format!("{}.{}", file_name, suffix))
+}
+
+fn good_copy(path: &Path, index: usize) -> PathBuf {
+    sibling(path, &format!("good-{}", index))
+}
+
+/// Last-known-good copies, newest first.
+pub fn good_copies(path: &Path) -> Vec<PathBuf> {
+    (1..=LAST_KNOWN_GOOD)
+        .map(|i| good_copy(path, i))
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// Records the current (valid) config as the newest last-known-good copy,
+/// rotating out the oldest one.
+pub fn remember_good(path: &Path) -> io::Result<()> {
+    let content = fs::read(path)?;
+    let newest = good_copy(path, 1);
+    if fs::read(&newest).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+
+    for i in (1..LAST_KNOWN_GOOD).rev() {
+        let from = good_copy(path, i);
+        if from.exists() {
+            fs::rename(&from, good_copy(path, i + 1))?;
+        }
+    }
+    write_atomic(&newest, &content)
+}
+
+/// Everything next to the config that may hold an older version of it:
+/// last-known-good copies, migration backups and quarantined files.
+pub fn old_copies(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut copies = good_copies(path);
+    let Some(dir) = path.parent() else {
+        return Ok(copies);
+    };
+    let prefix = format!("{}.", path.file_name().and_then(|n| n.to_str()).unwrap_or("mcp.json"));
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(&prefix) && (name.ends_with(".bak") || name.ends_with(".corrupt")) {
+            copies.push(dir.join(name.as_ref()));
+        }
+    }
+    Ok(copies)
+}
+
+/// Replaces every occurrence of each `from` with its `to` in the file, in
+/// place. Works on any content, including files that no longer parse.
+pub fn replace_in(path: &Path, replacements: &[(Vec<u8>, Vec<u8>)]) -> io::Result<()> {
+    let original = fs::read(path)?;
+    let mut content = original.clone();
+    for (from, to) in replacements.iter().filter(|(from, _)| !from.is_empty()) {
+        let mut replaced = Vec::with_capacity(content.len());
+        let mut rest = content.as_slice();
+        while let Some(pos) = rest.windows(from.len()).position(|window| window == from.as_slice()) {
+            replaced.extend_from_slice(&rest[..pos]);
+            replaced.extend_from_slice(to);
+            rest = &rest[pos + from.len()..];
+        }
+        replaced.extend_from_slice(rest);
+        content = replaced;
+    }
+
+    if content != original {
+        write_atomic(path, &content)?;
+    }
+    Ok(())
+}
+
+/// Moves an unreadable config out of the way, keeping it for inspection.
+pub fn quarantine(path: &Path, stamp: &str) -> io::Result<PathBuf> {
+    let target = sibling(path, &format!("{}.corrupt", stamp));
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+pub(crate) fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("luke-config-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_and_change_detection() {
+        let dir = temp_dir();
+        let path = dir.join("mcp.json");
+
+        write_atomic(&path, b"{}").unwrap();
+        let stamp = FileStamp::read(&path).unwrap();
+        assert!(!changed_since(&path, stamp.as_ref()).unwrap());
+
+        write_atomic(&path, br#"{"servers":[]}"#).unwrap();
+        assert!(changed_since(&path, stamp.as_ref()).unwrap());
+        assert!(changed_since(&path, None).unwrap());
+
+        // Only the target file is left behind.
+        let names: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, vec!["mcp.json"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remember_good_rotates() {
+        let dir = temp_dir();
+        let path = dir.join("mcp.json");
+
+        for i in 0..5 {
+            fs::write(&path, format!("{{\"n\":{}}}", i)).unwrap();
+            remember_good(&path).unwrap();
+            // Remembering the same content twice does not rotate.
+            remember_good(&path).unwrap();
+        }
+
+        let copies = good_copies(&path);
+        assert_eq!(copies.len(), LAST_KNOWN_GOOD);
+        assert_eq!(fs::read_to_string(&copies[0]).unwrap(), "{\"n\":4}");
+        assert_eq!(fs::read_to_string(&copies[2]).unwrap(), "{\"n\":2}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_with_lock() {
+        let dir = temp_dir();
+        let path = dir.join("mcp.json");
+
+        let value: Result<u8, io::Error> = with_lock(&path, || Ok(7));
+        assert_eq!(value.unwrap(), 7);
+        assert!(dir.join("mcp.json.lock").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
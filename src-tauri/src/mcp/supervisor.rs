@@ -0,0 +1,517 @@
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use super::types::{MCPError, MCPTransport};
+
+const LOG_CAPACITY: usize = 500;
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// A process that stayed up this long is considered healthy again and
+    /// its restart counter is reset.
+    pub stable_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            stable_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ProcessState {
+    Starting,
+    Running { pid: Option<u32> },
+    Restarting { attempt: u32, delay_ms: u64 },
+    Failed { message: String },
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    Stderr,
+    Supervisor,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub source: LogSource,
+    pub line: String,
+}
+
+/// Fixed-size buffer that keeps the most recent log lines of a process.
+#[derive(Debug)]
+pub struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, source: LogSource, line: impl Into<String>) {
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(LogLine {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            source,
+            line: line.into(),
+        });
+    }
+
+    pub fn snapshot(&self) -> Vec<LogLine> {
+        self.lines.iter().cloned().collect()
+    }
+}
+
+type SharedLogs = Arc<Mutex<LogBuffer>>;
+
+fn log(logs: &SharedLogs, source: LogSource, line: impl Into<String>) {
+    if let Ok(mut logs) = logs.lock() {
+        logs.push(source, line);
+    }
+}
+
+/// Launch parameters of a stdio server.
+#[derive(Debug, Clone)]
+pub struct StdioCommand {
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+}
+
+impl StdioCommand {
+    pub fn from_transport(transport: &MCPTransport) -> Option<Self> {
+        match transport {
+            MCPTransport::Stdio { command, args, env, cwd } => Some(Self {
+                command: command.clone(),
+                args: args.clone(),
+                env: env.clone(),
+                cwd: cwd.clone(),
+            }),
+            MCPTransport::Http { .. } => None,
+        }
+    }
+
+    fn build(&self) -> Command {
+        // `npx`, `uvx` and friends are batch scripts on Windows and cannot be
+        // spawned directly.
+        #[cfg(target_os = "windows")]
+        let mut command = {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(&self.command);
+            command
+        };
+        #[cfg(not(target_os = "windows"))]
+        let mut command = Command::new(&self.command);
+
+        command
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        command
+    }
+}
+
+/// Line-oriented pipe to a supervised process. The channels survive restarts,
+/// so a consumer keeps the same handle while the child is replaced; watch
+/// `state` to notice when a fresh process needs to be re-initialized.
+pub struct StdioChannel {
+    pub outgoing: mpsc::UnboundedSender<String>,
+    pub incoming: mpsc::UnboundedReceiver<String>,
+    pub state: watch::Receiver<ProcessState>,
+}
+
+struct ManagedProcess {
+    shutdown: watch::Sender<bool>,
+    state: watch::Receiver<ProcessState>,
+    logs: SharedLogs,
+    task: JoinHandle<()>,
+}
+
+/// Spawns stdio MCP servers, restarts them with exponential backoff when they
+/// exit, and keeps their stderr output for diagnostics.
+#[derive(Default)]
+pub struct ProcessSupervisor {
+    processes: Mutex<HashMap<String, ManagedProcess>>,
+}
+
+impl ProcessSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(
+        &self,
+        server_id: &str,
+        command: StdioCommand,
+        policy: RestartPolicy,
+    ) -> Result<StdioChannel, MCPError> {
+        let mut processes = self
+            .processes
+            .lock()
+            .map_err(|e| MCPError::new("INTERNAL_ERROR", e.to_string()))?;
+
+        if let Some(existing) = processes.get(server_id) {
+            if !existing.task.is_finished() {
+                return Err(MCPError::new(
+                    "ALREADY_RUNNING",
+                    format!("Server {} is already running", server_id),
+                ));
+            }
+        }
+
+        let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let (state_tx, state_rx) = watch::channel(ProcessState::Starting);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let logs = Arc::new(Mutex::new(LogBuffer::new(LOG_CAPACITY)));
+
+        let task = tokio::spawn(supervise(
+            command,
+            policy,
+            outgoing_rx,
+            incoming_tx,
+            state_tx,
+            shutdown_rx,
+            logs.clone(),
+        ));
+
+        processes.insert(
+            server_id.to_string(),
+            ManagedProcess {
+                shutdown: shutdown_tx,
+                state: state_rx.clone(),
+                logs,
+                task,
+            },
+        );
+
+        Ok(StdioChannel {
+            outgoing: outgoing_tx,
+            incoming: incoming_rx,
+            state: state_rx,
+        })
+    }
+
+    /// Stops a server and waits for its process to exit.
+    pub async fn stop(&self, server_id: &str) -> bool {
+        let process = match self.processes.lock() {
+            Ok(mut processes) => processes.remove(server_id),
+            Err(_) => None,
+        };
+
+        match process {
+            Some(process) => {
+                shutdown(process).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Terminates every child process. Called when the app exits.
+    pub async fn shutdown_all(&self) {
+        let processes: Vec<ManagedProcess> = match self.processes.lock() {
+            Ok(mut processes) => processes.drain().map(|(_, p)| p).collect(),
+            Err(_) => return,
+        };
+
+        // Signal everything first so the grace periods run concurrently.
+        for process in &processes {
+            let _ = process.shutdown.send(true);
+        }
+        for process in processes {
+            shutdown(process).await;
+        }
+    }
+
+    pub fn state(&self, server_id: &str) -> Option<ProcessState> {
+        let processes = self.processes.lock().ok()?;
+        processes.get(server_id).map(|p| p.state.borrow().clone())
+    }
+
+    pub fn logs(&self, server_id: &str) -> Option<Vec<LogLine>> {
+        let processes = self.processes.lock().ok()?;
+        let process = processes.get(server_id)?;
+        let logs = process.logs.lock().ok()?;
+        Some(logs.snapshot())
+    }
+}
+
+async fn shutdown(process: ManagedProcess) {
+    let _ = process.shutdown.send(true);
+    let mut task = process.task;
+    if tokio::time::timeout(SHUTDOWN_GRACE * 2, &mut task).await.is_err() {
+        task.abort();
+    }
+}
+
+async fn supervise(
+    command: StdioCommand,
+    policy: RestartPolicy,
+    mut outgoing: mpsc::UnboundedReceiver<String>,
+    incoming: mpsc::UnboundedSender<String>,
+    state: watch::Sender<ProcessState>,
+    mut shutdown_rx: watch::Receiver<bool>,
+    logs: SharedLogs,
+) {
+    let mut attempt = 0u32;
+
+    loop {
+        state.send_replace(ProcessState::Starting);
+        let started = Instant::now();
+
+        match command.build().spawn() {
+            Ok(mut child) => {
+                log(&logs, LogSource::Supervisor, format!("Started `{}` (pid {:?})", command.command, child.id()));
+                state.send_replace(ProcessState::Running { pid: child.id() });
+
+                match run_child(&mut child, &mut outgoing, &incoming, &mut shutdown_rx, &logs).await {
+                    ChildExit::Shutdown => {
+                        terminate(child).await;
+                        log(&logs, LogSource::Supervisor, "Stopped");
+                        state.send_replace(ProcessState::Stopped);
+                        return;
+                    }
+                    ChildExit::Exited(status) => {
+                        log(&logs, LogSource::Supervisor, format!("Process exited: {}", status));
+                    }
+                }
+            }
+            Err(e) => {
+                log(&logs, LogSource::Supervisor, format!("Failed to spawn `{}`: {}", command.command, e));
+            }
+        }
+
+        if started.elapsed() >= policy.stable_after {
+            attempt = 0;
+        }
+        attempt += 1;
+
+        if attempt > policy.max_restarts {
+            let message = format!("Gave up after {} restarts", policy.max_restarts);
+            log(&logs, LogSource::Supervisor, message.clone());
+            state.send_replace(ProcessState::Failed { message });
+            return;
+        }
+
+        let delay = policy.backoff(attempt);
+        state.send_replace(ProcessState::Restarting {
+            attempt,
+            delay_ms: delay.as_millis() as u64,
+        });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown_rx.changed() => {
+                state.send_replace(ProcessState::Stopped);
+                return;
+            }
+        }
+    }
+}
+
+enum ChildExit {
+    Exited(String),
+    Shutdown,
+}
+
+async fn run_child(
+    child: &mut Child,
+    outgoing: &mut mpsc::UnboundedReceiver<String>,
+    incoming: &mpsc::UnboundedSender<String>,
+    shutdown_rx: &mut watch::Receiver<bool>,
+    logs: &SharedLogs,
+) -> ChildExit {
+    let mut stdin = child.stdin.take();
+
+    if let Some(stdout) = child.stdout.take() {
+        let incoming = incoming.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if !line.trim().is_empty() && incoming.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        let logs = logs.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log(&logs, LogSource::Stderr, line);
+            }
+        });
+    }
+
+    loop {
+        tokio::select! {
+            status = child.wait() => {
+                return ChildExit::Exited(match status {
+                    Ok(status) => status.to_string(),
+                    Err(e) => e.to_string(),
+                });
+            }
+            message = outgoing.recv() => {
+                let Some(message) = message else {
+                    return ChildExit::Shutdown;
+                };
+                if let Some(writer) = stdin.as_mut() {
+                    let written = async {
+                        writer.write_all(message.as_bytes()).await?;
+                        writer.write_all(b"\n").await?;
+                        writer.flush().await
+                    };
+                    if let Err(e) = written.await {
+                        log(logs, LogSource::Supervisor, format!("Failed to write to stdin: {}", e));
+                    }
+                }
+            }
+            _ = shutdown_rx.changed() => return ChildExit::Shutdown,
+        }
+    }
+}
+
+/// Closes stdin to let the server exit on its own, then kills it.
+async fn terminate(mut child: Child) {
+    drop(child.stdin.take());
+    if tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await.is_err() {
+        let _ = child.kill().await;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> StdioCommand {
+        StdioCommand {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::from([("GREETING".to_string(), "hello".to_string())]),
+            cwd: None,
+        }
+    }
+
+    fn fast_policy(max_restarts: u32) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            stable_after: Duration::from_secs(60),
+        }
+    }
+
+    async fn wait_for(state: &mut watch::Receiver<ProcessState>, expected: fn(&ProcessState) -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), state.wait_for(expected))
+            .await
+            .expect("timed out waiting for process state")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_echo_and_stderr_capture() {
+        let supervisor = ProcessSupervisor::new();
+        let mut channel = supervisor
+            .spawn(
+                "echo",
+                shell("echo \"$GREETING from stderr\" >&2; while read line; do echo \"$line\"; done"),
+                fast_policy(0),
+            )
+            .unwrap();
+
+        channel.outgoing.send("{\"jsonrpc\":\"2.0\"}".to_string()).unwrap();
+        let line = tokio::time::timeout(Duration::from_secs(5), channel.incoming.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(line, "{\"jsonrpc\":\"2.0\"}");
+
+        let logs = supervisor.logs("echo").unwrap();
+        assert!(logs
+            .iter()
+            .any(|l| l.source == LogSource::Stderr && l.line == "hello from stderr"));
+
+        assert!(supervisor.stop("echo").await);
+        assert_eq!(*channel.state.borrow(), ProcessState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_restart_with_backoff_then_give_up() {
+        let supervisor = ProcessSupervisor::new();
+        let mut channel = supervisor.spawn("crashy", shell("exit 3"), fast_policy(2)).unwrap();
+
+        wait_for(&mut channel.state, |s| matches!(s, ProcessState::Failed { .. })).await;
+
+        let logs = supervisor.logs("crashy").unwrap();
+        let starts = logs.iter().filter(|l| l.line.starts_with("Started")).count();
+        assert_eq!(starts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_all_kills_children() {
+        let supervisor = ProcessSupervisor::new();
+        let mut channel = supervisor.spawn("sleepy", shell("trap '' TERM; sleep 30"), fast_policy(0)).unwrap();
+        wait_for(&mut channel.state, |s| matches!(s, ProcessState::Running { .. })).await;
+
+        tokio::time::timeout(Duration::from_secs(10), supervisor.shutdown_all())
+            .await
+            .unwrap();
+
+        assert_eq!(*channel.state.borrow(), ProcessState::Stopped);
+        assert!(supervisor.state("sleepy").is_none());
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(20), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::registry::{register, ToolNaming};
+use super::types::{CallToolResult, MCPError, MCPTool, ToolContent};
+use super::validation::{validate_arguments, validate_result};
+use crate::anthropic::client::{AnthropicClient, AnthropicError};
+use crate::anthropic::types::{
+    ContentBlock, ImageSource, Message, MessagesRequest, MessagesResponse, StopReason, Tool,
+};
+
+pub const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+/// Anything that can list and execute tools on behalf of the model.
+#[async_trait]
+pub trait ToolProvider: Send + Sync {
+    async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError>;
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, MCPError>;
+
+    /// Like `call_tool`, with progress reported under `progress_token` by
+    /// providers that support it.
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        _progress_token: &str,
+    ) -> Result<CallToolResult, MCPError> {
+        self.call_tool(name, arguments).await
+    }
+}
+
+/// How a tool call came to be allowed, or that it wasn't.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Approval {
+    /// No approver was involved, e.g. calls made by the user directly.
+    NotRequired,
+    /// Sent by hand from the inspector, which bypasses approval.
+    Inspector,
+    AllowedByPolicy,
+    ApprovedByUser,
+    Denied,
+}
+
+/// Decides whether the model may run a tool call. An error is returned to
+/// the model as the tool's result.
+#[async_trait]
+pub trait ToolApprover: Send + Sync {
+    async fn approve(&self, server_id: &str, tool_name: &str, arguments: &Value) -> Result<Approval, MCPError>;
+}
+
+/// A finished tool call, as seen by a [`ToolCallObserver`].
+pub struct ToolCallRecord<'a> {
+    pub server_id: &'a str,
+    pub tool_name: &'a str,
+    pub arguments: &'a Value,
+    pub approval: Approval,
+    pub result: &'a Result<CallToolResult, MCPError>,
+    pub duration: Duration,
+}
+
+/// Gets to see every tool call the loop makes, including denied ones.
+pub trait ToolCallObserver: Send + Sync {
+    fn tool_called(&self, record: &ToolCallRecord);
+}
+
+/// Hands out progress tokens, so notifications about a running call can be
+/// tied back to it.
+pub trait CallTracker: Send + Sync {
+    fn started(&self, server_id: &str, tool_name: &str) -> String;
+    fn finished(&self, progress_token: &str);
+}
+
+#[derive(Debug, Error)]
+pub enum ToolLoopError {
+    #[error(transparent)]
+    Anthropic(#[from] AnthropicError),
+
+    #[error("Tool loop stopped after {0} iterations without the model finishing its turn")]
+    MaxIterations(usize),
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolLoopResult {
+    /// Messages produced by the loop, to be appended to the conversation.
+    pub messages: Vec<Message>,
+    /// The final model response.
+    pub response: MessagesResponse,
+    pub iterations: usize,
+}
+
+/// Drives a conversation turn until the model stops requesting tools.
+pub struct ToolLoop {
+    client: AnthropicClient,
+    providers: Vec<(String, Arc<dyn ToolProvider>)>,
+    approver: Option<Arc<dyn ToolApprover>>,
+    observer: Option<Arc<dyn ToolCallObserver>>,
+    tracker: Option<Arc<dyn CallTracker>>,
+    naming: ToolNaming,
+    max_iterations: usize,
+}
+
+/// Namespaced tool name to server id, the tool as listed and provider.
+type ToolOwners = HashMap<String, (String, MCPTool, Arc<dyn ToolProvider>)>;
+
+impl ToolLoop {
+    pub fn new(client: AnthropicClient, providers: Vec<(String, Arc<dyn ToolProvider>)>) -> Self {
+        Self {
+            client,
+            providers,
+            approver: None,
+            observer: None,
+            tracker: None,
+            naming: ToolNaming::default(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+        }
+    }
+
+    /// Asks `approver` before every tool call.
+    pub fn with_approver(mut self, approver: Arc<dyn ToolApprover>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn ToolCallObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub fn with_tracker(mut self, tracker: Arc<dyn CallTracker>) -> Self {
+        self.tracker = Some(tracker);
+        self
+    }
+
+    pub fn with_naming(mut self, naming: ToolNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Collects the visible tools of all providers under their namespaced
+    /// names.
+    async fn collect_tools(&self) -> (Vec<Tool>, ToolOwners) {
+        let mut listed = Vec::new();
+        for (server_id, provider) in &self.providers {
+            match provider.list_tools().await {
+                Ok(tools) => listed.push((server_id.clone(), tools)),
+                Err(e) => eprintln!("Failed to list tools of MCP server {}: {}", server_id, e),
+            }
+        }
+
+        let mut tools = Vec::new();
+        let mut owners = ToolOwners::new();
+        for registered in register(listed, &self.naming) {
+            if registered.hidden {
+                continue;
+            }
+            let Some((_, provider)) = self.providers.iter().find(|(id, _)| *id == registered.server_id) else {
+                continue;
+            };
+            tools.push(Tool {
+                name: registered.name.clone(),
+                description: registered.tool.description.clone(),
+                input_schema: registered.tool.input_schema.clone(),
+            });
+            owners.insert(registered.name, (registered.server_id, registered.tool, provider.clone()));
+        }
+
+        (tools, owners)
+    }
+
+    pub async fn run(&self, mut request: MessagesRequest) -> Result<ToolLoopResult, ToolLoopError> {
+        let (tools, owners) = self.collect_tools().await;
+        if !tools.is_empty() {
+            request.tools = Some(tools);
+        }
+
+        let mut produced = Vec::new();
+
+        for iteration in 1..=self.max_iterations {
+            let response = self.client.send_message(&request).await?;
+            let assistant = Message::assistant(response.content.clone());
+            request.messages.push(assistant.clone());
+            produced.push(assistant);
+
+            if response.stop_reason != Some(StopReason::ToolUse) {
+                return Ok(ToolLoopResult {
+                    messages: produced,
+                    response,
+                    iterations: iteration,
+                });
+            }
+
+            let mut results = Vec::new();
+            for (id, name, input) in response.tool_uses() {
+                results.push(tool_result_block(id, self.call(&owners, name, input).await));
+            }
+
+            let results = Message::tool_results(results);
+            request.messages.push(results.clone());
+            produced.push(results);
+        }
+
+        Err(ToolLoopError::MaxIterations(self.max_iterations))
+    }
+
+    async fn call(&self, owners: &ToolOwners, name: &str, input: &Value) -> Result<CallToolResult, MCPError> {
+        let (server_id, tool, provider) = owners
+            .get(name)
+            .ok_or_else(|| MCPError::new("TOOL_NOT_FOUND", format!("Unknown tool: {}", name)))?;
+        let tool_name = &tool.name;
+
+        // Invalid arguments go back to the model without bothering the user
+        // or the server.
+        let started = Instant::now();
+        let approval = match (validate_arguments(tool, input), &self.approver) {
+            (Err(e), _) => Err((Approval::NotRequired, e)),
+            (Ok(()), Some(approver)) => approver
+                .approve(server_id, tool_name, input)
+                .await
+                .map_err(|e| (Approval::Denied, e)),
+            (Ok(()), None) => Ok(Approval::NotRequired),
+        };
+        let (approval, result) = match approval {
+            Ok(approval) => {
+                let result = self.execute(server_id, provider, tool_name, input).await;
+                let result = result.and_then(|result| validate_result(tool, &result).map(|()| result));
+                (approval, result)
+            }
+            Err((approval, e)) => (approval, Err(e)),
+        };
+
+        if let Some(observer) = &self.observer {
+            observer.tool_called(&ToolCallRecord {
+                server_id,
+                tool_name,
+                arguments: input,
+                approval,
+                result: &result,
+                duration: started.elapsed(),
+            });
+        }
+        result
+    }
+
+    async fn execute(
+        &self,
+        server_id: &str,
+        provider: &Arc<dyn ToolProvider>,
+        name: &str,
+        input: &Value,
+    ) -> Result<CallToolResult, MCPError> {
+        let Some(tracker) = &self.tracker else {
+            return provider.call_tool(name, input.clone()).await;
+        };
+        let token = tracker.started(server_id, name);
+        let result = provider.call_tool_with_progress(name, input.clone(), &token).await;
+        tracker.finished(&token);
+        result
+    }
+}
+
+/// Converts an MCP tool result into a `tool_result` block for the model.
+pub fn tool_result_block(tool_use_id: &str, result: Result<CallToolResult, MCPError>) -> ContentBlock {
+    match result {
+        Ok(result) => ContentBlock::ToolResult {
+            tool_use_id: tool_use_id.to_string(),
+            content: result.content.into_iter().filter_map(content_block).collect(),
+            is_error: result.is_error,
+        },
+        Err(e) => {
+            let mut text = e.to_string();
+            if let Some(details) = &e.details {
+                text = format!("{}\n{}", text, serde_json::json!(details));
+            }
+            ContentBlock::ToolResult {
+                tool_use_id: tool_use_id.to_string(),
+                content: vec![ContentBlock::Text { text }],
+                is_error: true,
+            }
+        }
+    }
+}
+
+/// Converts MCP content into a content block for the model. Kinds the API
+/// does not accept are dropped.
+pub fn content_block(content: ToolContent) -> Option<ContentBlock> {
+    match content {
+        ToolContent::Text { text } => Some(ContentBlock::Text { text }),
+        ToolContent::Image { data, mime_type } => Some(ContentBlock::Image {
+            source: ImageSource {
+                source_type: "base64".to_string(),
+                media_type: mime_type,
+                data,
+            },
+        }),
+        ToolContent::Resource { resource } => {
+            let text = resource
+                .get("text")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| resource.to_string());
+            Some(ContentBlock::Text { text })
+        }
+        ToolContent::Unsupported => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct FakeServer {
+        calls: Mutex<Vec<(String, Value)>>,
+    }
+
+    #[async_trait]
+    impl ToolProvider for FakeServer {
+        async fn list_tools(&self) -> Result<Vec<MCPTool>, MCPError> {
+            Ok(vec![MCPTool {
+                name: "get_weather".to_string(),
+                description: Some("Current weather for a city".to_string()),
+                input_schema: json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+                output_schema: None,
+            }])
+        }
+
+        async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, MCPError> {
+            self.calls.lock().unwrap().push((name.to_string(), arguments));
+            Ok(CallToolResult {
+                content: vec![ToolContent::Text { text: "Sunny, 21°C".to_string() }],
+                is_error: false,
+                structured_content: None,
+            })
+        }
+    }
+
+    fn response(stop_reason: &str, content: Value) -> Value {
+        json!({
+            "id": "msg", "type": "message", "role": "assistant", "model": "claude-sonnet-4-5",
+            "content": content, "stop_reason": stop_reason, "stop_sequence": null,
+            "usage": { "input_tokens": 1, "output_tokens": 1 }
+        })
+    }
+
+    fn tool_use_response() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(response(
+            "tool_use",
+            json!([{ "type": "tool_use", "id": "toolu_1", "name": "weather__get_weather", "input": { "city": "Berlin" } }]),
+        ))
+    }
+
+    fn request() -> MessagesRequest {
+        MessagesRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![Message::user("Weather in Berlin?")],
+            max_tokens: 256,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_loop_round_trip() {
+        let api = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({ "tools": [{ "name": "weather__get_weather" }] })))
+            .respond_with(tool_use_response())
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({ "messages": [{}, {}, { "role": "user", "content": [{
+                "type": "tool_result", "tool_use_id": "toolu_1",
+                "content": [{ "type": "text", "text": "Sunny, 21°C" }]
+            }] }] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response(
+                "end_turn",
+                json!([{ "type": "text", "text": "It is sunny in Berlin." }]),
+            )))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        let server = Arc::new(FakeServer { calls: Mutex::new(Vec::new()) });
+        let tool_loop = ToolLoop::new(
+            AnthropicClient::new("test-key").with_base_url(api.uri()),
+            vec![("weather".to_string(), server.clone() as Arc<dyn ToolProvider>)],
+        );
+
+        let result = tool_loop.run(request()).await.unwrap();
+
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.messages.len(), 3);
+        assert_eq!(result.response.text(), "It is sunny in Berlin.");
+        assert_eq!(
+            *server.calls.lock().unwrap(),
+            vec![("get_weather".to_string(), json!({ "city": "Berlin" }))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_loop_max_iterations() {
+        let api = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(tool_use_response())
+            .expect(3)
+            .mount(&api)
+            .await;
+
+        let server = Arc::new(FakeServer { calls: Mutex::new(Vec::new()) });
+        let tool_loop = ToolLoop::new(
+            AnthropicClient::new("test-key").with_base_url(api.uri()),
+            vec![("weather".to_string(), server as Arc<dyn ToolProvider>)],
+        )
+        .with_max_iterations(3);
+
+        assert!(matches!(tool_loop.run(request()).await, Err(ToolLoopError::MaxIterations(3))));
+    }
+
+    struct DenyAll;
+
+    #[async_trait]
+    impl ToolApprover for DenyAll {
+        async fn approve(&self, server_id: &str, tool_name: &str, _: &Value) -> Result<Approval, MCPError> {
+            Err(MCPError::new("TOOL_DENIED", format!("{}/{} denied", server_id, tool_name)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_denied_tool_call_is_reported_to_the_model() {
+        let api = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({ "messages": [{}, {}, { "role": "user", "content": [{
+                "type": "tool_result", "tool_use_id": "toolu_1", "is_error": true,
+                "content": [{ "type": "text", "text": "TOOL_DENIED: weather/get_weather denied" }]
+            }] }] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response(
+                "end_turn",
+                json!([{ "type": "text", "text": "I was not allowed to check." }]),
+            )))
+            .expect(1)
+            .mount(&api)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(tool_use_response())
+            .up_to_n_times(1)
+            .mount(&api)
+            .await;
+
+        let server = Arc::new(FakeServer { calls: Mutex::new(Vec::new()) });
+        let tool_loop = ToolLoop::new(
+            AnthropicClient::new("test-key").with_base_url(api.uri()),
+            vec![("weather".to_string(), server.clone() as Arc<dyn ToolProvider>)],
+        )
+        .with_approver(Arc::new(DenyAll));
+
+        let result = tool_loop.run(request()).await.unwrap();
+
+        assert_eq!(result.iterations, 2);
+        assert!(server.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_are_not_sent_to_the_server() {
+        let server = Arc::new(FakeServer { calls: Mutex::new(Vec::new()) });
+        let tool_loop = ToolLoop::new(
+            AnthropicClient::new("test-key"),
+            vec![("weather".to_string(), server.clone() as Arc<dyn ToolProvider>)],
+        )
+        .with_approver(Arc::new(DenyAll));
+        let (_, owners) = tool_loop.collect_tools().await;
+
+        let error = tool_loop
+            .call(&owners, "weather__get_weather", &json!({ "city": 12 }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, "INVALID_ARGUMENTS");
+        assert!(server.calls.lock().unwrap().is_empty());
+
+        let ContentBlock::ToolResult { content, .. } = tool_result_block("toolu_1", Err(error)) else {
+            unreachable!();
+        };
+        let ContentBlock::Text { text } = &content[0] else {
+            unreachable!();
+        };
+        assert!(text.contains("\"path\":\"/city\""));
+    }
+
+    #[test]
+    fn test_tool_error_becomes_error_result() {
+        let block = tool_result_block("toolu_9", Err(MCPError::new("SERVER_ERROR", "boom")));
+        assert_eq!(
+            block,
+            ContentBlock::ToolResult {
+                tool_use_id: "toolu_9".to_string(),
+                content: vec![ContentBlock::Text { text: "SERVER_ERROR: boom".to_string() }],
+                is_error: true,
+            }
+        );
+    }
+}
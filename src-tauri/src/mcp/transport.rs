@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use tokio::sync::{mpsc, watch, Mutex};
+
+use super::protocol::JsonRpcMessage;
+use super::supervisor::{ProcessState, StdioChannel};
+use super::types::MCPError;
+
+/// What a transport hands to the session's reader loop.
+#[derive(Debug)]
+pub enum Incoming {
+    Message(JsonRpcMessage),
+    /// The peer was replaced (e.g. a crashed stdio server was restarted) and
+    /// has lost all session state; the handshake has to be repeated.
+    Reconnected,
+}
+
+/// A bidirectional JSON-RPC message pipe to an MCP server.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError>;
+
+    /// Returns `None` once the transport is closed for good.
+    async fn receive(&self) -> Option<Incoming>;
+
+    async fn close(&self) {}
+
+    /// Called after a successful handshake so transports that need it (HTTP)
+    /// can attach the negotiated version to subsequent requests.
+    fn set_protocol_version(&self, _version: &str) {}
+}
+
+/// Newline-delimited JSON over the stdin/stdout of a supervised process.
+pub struct StdioTransport {
+    outgoing: mpsc::UnboundedSender<String>,
+    incoming: Mutex<StdioIncoming>,
+}
+
+struct StdioIncoming {
+    lines: mpsc::UnboundedReceiver<String>,
+    state: watch::Receiver<ProcessState>,
+    restarting: bool,
+}
+
+impl StdioTransport {
+    pub fn new(channel: StdioChannel) -> Self {
+        let mut state = channel.state;
+        state.mark_unchanged();
+        Self {
+            outgoing: channel.outgoing,
+            incoming: Mutex::new(StdioIncoming {
+                lines: channel.incoming,
+                state,
+                restarting: false,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError> {
+        let line = serde_json::to_string(&message)
+            .map_err(|e| MCPError::new("SERIALIZATION_ERROR", e.to_string()))?;
+        self.outgoing
+            .send(line)
+            .map_err(|_| MCPError::new("CONNECTION_CLOSED", "Server process is not running"))
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        let mut guard = self.incoming.lock().await;
+        let incoming = &mut *guard;
+
+        loop {
+            tokio::select! {
+                line = incoming.lines.recv() => {
+                    let line = line?;
+                    match serde_json::from_str(&line) {
+                        Ok(message) => return Some(Incoming::Message(message)),
+                        // Servers occasionally print banners to stdout; skip them.
+                        Err(e) => eprintln!("Ignoring non JSON-RPC output from MCP server: {} ({})", line, e),
+                    }
+                }
+                changed = incoming.state.changed() => {
+                    changed.ok()?;
+                    let current = incoming.state.borrow_and_update().clone();
+                    match current {
+                        ProcessState::Failed { .. } | ProcessState::Stopped => return None,
+                        ProcessState::Restarting { .. } => incoming.restarting = true,
+                        ProcessState::Running { .. } if incoming.restarting => {
+                            incoming.restarting = false;
+                            return Some(Incoming::Reconnected);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// In-process transport connecting two endpoints, used to test the protocol
+/// layer against a fake server.
+#[cfg(test)]
+pub struct ChannelTransport {
+    outgoing: mpsc::UnboundedSender<JsonRpcMessage>,
+    incoming: Mutex<mpsc::UnboundedReceiver<JsonRpcMessage>>,
+}
+
+#[cfg(test)]
+impl ChannelTransport {
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, a_rx) = mpsc::unbounded_channel();
+        let (b_tx, b_rx) = mpsc::unbounded_channel();
+        (
+            Self { outgoing: a_tx, incoming: Mutex::new(b_rx) },
+            Self { outgoing: b_tx, incoming: Mutex::new(a_rx) },
+        )
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Transport for ChannelTransport {
+    async fn send(&self, message: JsonRpcMessage) -> Result<(), MCPError> {
+        self.outgoing
+            .send(message)
+            .map_err(|_| MCPError::new("CONNECTION_CLOSED", "Peer is gone"))
+    }
+
+    async fn receive(&self) -> Option<Incoming> {
+        self.incoming.lock().await.recv().await.map(Incoming::Message)
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use super::types::{CallToolResult, MCPError, MCPTool};
+
+/// More violations than this are summarized by their count.
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// The schema violations of `instance`, or `None` if the schema itself is
+/// broken. A server with a broken schema is not held to it.
+fn violations(tool: &MCPTool, schema: &Value, instance: &Value) -> Option<Vec<Value>> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            eprintln!("Ignoring invalid schema of tool {}: {}", tool.name, e);
+            return None;
+        }
+    };
+    Some(
+        validator
+            .iter_errors(instance)
+            .map(|e| json!({ "path": e.instance_path().as_str(), "message": e.to_string() }))
+            .collect(),
+    )
+}
+
+fn invalid(code: &str, message: String, tool: &MCPTool, errors: Vec<Value>) -> MCPError {
+    let mut details = HashMap::from([
+        ("tool".to_string(), json!(tool.name)),
+        ("errorCount".to_string(), json!(errors.len())),
+    ]);
+    details.insert(
+        "errors".to_string(),
+        Value::Array(errors.into_iter().take(MAX_REPORTED_ERRORS).collect()),
+    );
+    MCPError {
+        code: code.to_string(),
+        message,
+        details: Some(details),
+    }
+}
+
+/// Checks the model's arguments against the tool's `inputSchema` before
+/// they are sent to the server.
+pub fn validate_arguments(tool: &MCPTool, arguments: &Value) -> Result<(), MCPError> {
+    match violations(tool, &tool.input_schema, arguments) {
+        Some(errors) if !errors.is_empty() => Err(invalid(
+            "INVALID_ARGUMENTS",
+            format!("Arguments for {} do not match its input schema", tool.name),
+            tool,
+            errors,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks `structuredContent` against the tool's `outputSchema`. Tools
+/// with an output schema must return structured content unless they fail.
+pub fn validate_result(tool: &MCPTool, result: &CallToolResult) -> Result<(), MCPError> {
+    let Some(schema) = &tool.output_schema else {
+        return Ok(());
+    };
+    if result.is_error {
+        return Ok(());
+    }
+    let Some(content) = &result.structured_content else {
+        return Err(invalid(
+            "INVALID_RESULT",
+            format!("{} declares an output schema but returned no structured content", tool.name),
+            tool,
+            Vec::new(),
+        ));
+    };
+    match violations(tool, schema, content) {
+        Some(errors) if !errors.is_empty() => Err(invalid(
+            "INVALID_RESULT",
+            format!("Structured content of {} does not match its output schema", tool.name),
+            tool,
+            errors,
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(output_schema: Option<Value>) -> MCPTool {
+        MCPTool {
+            name: "get_weather".to_string(),
+            description: None,
+            input_schema: json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" }, "days": { "type": "integer", "minimum": 1 } },
+                "required": ["city"]
+            }),
+            output_schema,
+        }
+    }
+
+    fn result(structured_content: Option<Value>, is_error: bool) -> CallToolResult {
+        CallToolResult {
+            content: Vec::new(),
+            is_error,
+            structured_content,
+        }
+    }
+
+    #[test]
+    fn test_arguments_are_checked_against_the_input_schema() {
+        let tool = tool(None);
+        validate_arguments(&tool, &json!({ "city": "Berlin", "days": 3 })).unwrap();
+
+        let error = validate_arguments(&tool, &json!({ "days": 0 })).unwrap_err();
+        assert_eq!(error.code, "INVALID_ARGUMENTS");
+        let details = error.details.unwrap();
+        assert_eq!(details["tool"], "get_weather");
+        assert_eq!(details["errorCount"], 2);
+        let paths: Vec<_> = details["errors"].as_array().unwrap().iter().map(|e| e["path"].clone()).collect();
+        assert!(paths.contains(&json!("")) && paths.contains(&json!("/days")));
+
+        // A schema that does not compile is not enforced.
+        let broken = MCPTool {
+            input_schema: json!({ "type": 12 }),
+            ..tool
+        };
+        validate_arguments(&broken, &json!({})).unwrap();
+    }
+
+    #[test]
+    fn test_structured_content_is_checked_against_the_output_schema() {
+        let schema = json!({ "type": "object", "properties": { "temperature": { "type": "number" } }, "required": ["temperature"] });
+        let tool = tool(Some(schema));
+
+        validate_result(&tool, &result(Some(json!({ "temperature": 21.5 })), false)).unwrap();
+        let error = validate_result(&tool, &result(Some(json!({ "temperature": "warm" })), false)).unwrap_err();
+        assert_eq!(error.code, "INVALID_RESULT");
+        assert_eq!(error.details.unwrap()["errors"][0]["path"], "/temperature");
+
+        assert_eq!(validate_result(&tool, &result(None, false)).unwrap_err().code, "INVALID_RESULT");
+        validate_result(&tool, &result(None, true)).unwrap();
+        validate_result(&self::tool(None), &result(None, false)).unwrap();
+    }
+}
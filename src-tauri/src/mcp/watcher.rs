@@ -0,0 +1,113 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+
+use super::config::{ConfigDiff, ConfigManager};
+use super::connections::ConnectionManager;
+use super::oauth;
+
+pub const CONFIG_CHANGED_EVENT: &str = "mcp-config-changed";
+
+/// Editors tend to save in several steps (truncate, write, rename).
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConfigChange {
+    Reloaded { diff: ConfigDiff },
+    Rejected { message: String },
+}
+
+/// Keeps the file watcher alive while it is managed by the app.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches `mcp.json` for edits made outside the app and applies them.
+pub fn watch_config(app: &AppHandle, path: &Path) -> notify::Result<ConfigWatcher> {
+    let file_name = path.file_name().map(|n| n.to_os_string());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // The directory also holds the lock file, temp files and backups.
+        if event.paths.iter().any(|p| p.file_name() == file_name.as_deref()) {
+            let _ = tx.send(());
+        }
+    })?;
+
+    // Watch the directory so atomic replacements of the file are seen too.
+    let dir = path.parent().unwrap_or(path);
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            reload(&app).await;
+        }
+    });
+
+    Ok(ConfigWatcher { _watcher: watcher })
+}
+
+async fn reload(app: &AppHandle) {
+    let result = {
+        let config = app.state::<Mutex<ConfigManager>>();
+        let mut config = match config.lock() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to reload MCP config: {}", e);
+                return;
+            }
+        };
+        config.reload()
+    };
+
+    let (change, diff) = match result {
+        Ok(None) => return,
+        Ok(Some(diff)) => (ConfigChange::Reloaded { diff: diff.clone() }, Some(diff)),
+        Err(e) => (
+            ConfigChange::Rejected {
+                message: e.to_string(),
+            },
+            None,
+        ),
+    };
+
+    if let Err(e) = app.emit(CONFIG_CHANGED_EVENT, change) {
+        eprintln!("Failed to emit {}: {}", CONFIG_CHANGED_EVENT, e);
+    }
+    if let Some(diff) = diff {
+        apply(app, &diff).await;
+    }
+}
+
+/// Stops removed servers and restarts changed ones; servers that are not
+/// part of the diff keep running untouched. Servers connect in the
+/// background, each on its own, and never ask the user to sign in; that is
+/// left to an explicit connect.
+pub async fn apply(app: &AppHandle, diff: &ConfigDiff) {
+    let connections = app.state::<ConnectionManager>();
+    for server in diff.removed.iter().chain(&diff.changed) {
+        connections.disconnect(&server.id).await;
+    }
+
+    for server in diff.added.iter().chain(&diff.changed).filter(|s| s.is_active).cloned() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let connections = app.state::<ConnectionManager>();
+            if let Err(e) = oauth::unattended(connections.connect(&server)).await {
+                eprintln!("Failed to start MCP server {}: {}", server.id, e);
+            }
+        });
+    }
+}
@@ -1,7 +1,20 @@
+use directories::ProjectDirs;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use tauri::api::path::data_dir;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, State};
+
+use crate::mcp::config::ConfigManager;
+use crate::mcp::credentials;
+use crate::mcp::connections::ConnectionManager;
+use crate::mcp::types::ProjectServers;
+use crate::mcp::watcher::apply;
+use crate::secrets::SecretStore;
+
+pub const ROOTS_CHANGED_NOTIFICATION: &str = "notifications/roots/list_changed";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Project {
@@ -33,10 +46,9 @@ pub struct ProjectMetadata {
 }
 
 pub fn get_projects_dir() -> PathBuf {
-    let mut path = data_dir().expect("Failed to get data directory");
-    path.push("LukeDesktop");
-    path.push("projects");
-    path
+    let dirs = ProjectDirs::from("com", "lukedesktop", "LukeDesktop")
+        .expect("Failed to get data directory");
+    dirs.data_dir().join("projects")
 }
 
 #[tauri::command]
@@ -79,4 +91,372 @@ pub fn ensure_project_directory(project_id: &str) -> Result<(), String> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+/// A directory MCP servers may work in, as returned by `roots/list`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Root {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    fn from_path(path: &Path) -> Option<Self> {
+        Some(Self {
+            uri: Url::from_directory_path(path).ok()?.to_string(),
+            name: path.file_name().and_then(|n| n.to_str()).map(String::from),
+        })
+    }
+}
+
+fn project_dir(projects_dir: &Path, project_id: &str) -> Result<PathBuf, String> {
+    if project_id.is_empty() || project_id == "." || project_id == ".." || project_id.contains(['/', '\\']) {
+        return Err(format!("Invalid project id: {}", project_id));
+    }
+    Ok(projects_dir.join(project_id))
+}
+
+fn read_project_file<T: DeserializeOwned + Default>(
+    projects_dir: &Path,
+    project_id: &str,
+    name: &str,
+) -> Result<T, String> {
+    let path = project_dir(projects_dir, project_id)?.join(name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_project_file<T: Serialize>(
+    projects_dir: &Path,
+    project_id: &str,
+    name: &str,
+    value: &T,
+) -> Result<(), String> {
+    let dir = project_dir(projects_dir, project_id)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(dir.join(name), content).map_err(|e| e.to_string())
+}
+
+/// The root directories stored for a project; none if it has no roots yet.
+pub fn load_roots(projects_dir: &Path, project_id: &str) -> Result<Vec<PathBuf>, String> {
+    read_project_file(projects_dir, project_id, "roots.json")
+}
+
+/// Stores the roots of a project. Roots must be existing absolute
+/// directories; duplicates are dropped.
+pub fn save_roots(projects_dir: &Path, project_id: &str, roots: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if !root.is_absolute() || !root.is_dir() {
+            return Err(format!("Not a directory: {}", root.display()));
+        }
+        if !unique.contains(root) {
+            unique.push(root.clone());
+        }
+    }
+
+    write_project_file(projects_dir, project_id, "roots.json", &unique)?;
+    Ok(unique)
+}
+
+/// The MCP server settings of a project; empty if it uses the global config.
+pub fn load_servers(projects_dir: &Path, project_id: &str) -> Result<ProjectServers, String> {
+    read_project_file(projects_dir, project_id, "servers.json")
+}
+
+pub fn save_servers(projects_dir: &Path, project_id: &str, servers: &ProjectServers) -> Result<(), String> {
+    write_project_file(projects_dir, project_id, "servers.json", servers)
+}
+
+struct LoadedProject {
+    id: String,
+    roots: Vec<PathBuf>,
+    servers: ProjectServers,
+}
+
+/// The project the user is working in: its roots are offered to every
+/// connected MCP server, and its server settings decide which servers run.
+pub struct ActiveProject {
+    projects_dir: PathBuf,
+    current: RwLock<Option<LoadedProject>>,
+    secrets: Option<Arc<SecretStore>>,
+}
+
+impl ActiveProject {
+    pub fn new(projects_dir: PathBuf) -> Self {
+        Self {
+            projects_dir,
+            current: RwLock::new(None),
+            secrets: None,
+        }
+    }
+
+    /// Keeps secret env values of server overrides out of `servers.json`.
+    pub fn with_secrets(mut self, secrets: Arc<SecretStore>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    pub fn project_id(&self) -> Option<String> {
+        let current = self.current.read().ok()?;
+        current.as_ref().map(|project| project.id.clone())
+    }
+
+    pub fn roots(&self) -> Vec<Root> {
+        let Ok(current) = self.current.read() else {
+            return Vec::new();
+        };
+        current
+            .iter()
+            .flat_map(|project| project.roots.iter().filter_map(|root| Root::from_path(root)))
+            .collect()
+    }
+
+    /// Server settings of the active project, if there is one.
+    pub fn servers(&self) -> Option<ProjectServers> {
+        let current = self.current.read().ok()?;
+        current.as_ref().map(|project| project.servers.clone())
+    }
+
+    /// Switches to another project, or to none. Returns whether the roots
+    /// changed.
+    pub fn activate(&self, project_id: Option<String>) -> Result<bool, String> {
+        let next = match project_id {
+            Some(id) => Some(LoadedProject {
+                roots: load_roots(&self.projects_dir, &id)?,
+                servers: load_servers(&self.projects_dir, &id)?,
+                id,
+            }),
+            None => None,
+        };
+
+        let mut current = self.current.write().map_err(|e| e.to_string())?;
+        let changed = current.as_ref().map(|p| &p.roots) != next.as_ref().map(|p| &p.roots);
+        *current = next;
+        Ok(changed)
+    }
+
+    pub fn load_roots(&self, project_id: &str) -> Result<Vec<PathBuf>, String> {
+        load_roots(&self.projects_dir, project_id)
+    }
+
+    /// Stores new roots for a project. Returns whether the active roots
+    /// changed.
+    pub fn set_roots(&self, project_id: &str, roots: &[PathBuf]) -> Result<bool, String> {
+        let roots = save_roots(&self.projects_dir, project_id, roots)?;
+
+        let mut current = self.current.write().map_err(|e| e.to_string())?;
+        match current.as_mut() {
+            Some(project) if project.id == project_id && project.roots != roots => {
+                project.roots = roots;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn load_servers(&self, project_id: &str) -> Result<ProjectServers, String> {
+        load_servers(&self.projects_dir, project_id)
+    }
+
+    /// Stores the server settings of a project, with secret env values of
+    /// the overrides moved into the store. Returns whether it is the active
+    /// project.
+    pub fn set_servers(&self, project_id: &str, servers: ProjectServers) -> Result<bool, String> {
+        project_dir(&self.projects_dir, project_id)?;
+        let mut servers = servers;
+        for (server_id, changes) in &mut servers.overrides {
+            credentials::check_references(server_id, changes.env.values()).map_err(|e| e.to_string())?;
+            if let Some(secrets) = &self.secrets {
+                credentials::externalize_override(server_id, project_id, &mut changes.env, secrets)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        let old = load_servers(&self.projects_dir, project_id).unwrap_or_default();
+        save_servers(&self.projects_dir, project_id, &servers)?;
+        if let Some(secrets) = &self.secrets {
+            for (server_id, changes) in &old.overrides {
+                let new = servers.overrides.get(server_id).map(|c| &c.env);
+                credentials::forget_override(server_id, project_id, &changes.env, new, secrets)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        let mut current = self.current.write().map_err(|e| e.to_string())?;
+        match current.as_mut() {
+            Some(project) if project.id == project_id => {
+                project.servers = servers;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl Default for ActiveProject {
+    fn default() -> Self {
+        Self::new(get_projects_dir())
+    }
+}
+
+/// Hands the active project's server settings to the config and restarts
+/// the servers whose effective settings changed.
+async fn apply_servers(
+    app: &AppHandle,
+    active: &ActiveProject,
+    config: &Mutex<ConfigManager>,
+) -> Result<(), String> {
+    let diff = config.lock().map_err(|e| e.to_string())?.set_project(active.servers());
+    apply(app, &diff).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_active_project(
+    app: AppHandle,
+    project_id: Option<String>,
+    active: State<'_, ActiveProject>,
+    config: State<'_, Mutex<ConfigManager>>,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    let roots_changed = active.activate(project_id)?;
+    apply_servers(&app, &active, &config).await?;
+    if roots_changed {
+        connections.notify_all(ROOTS_CHANGED_NOTIFICATION).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_project_roots(project_id: String, active: State<'_, ActiveProject>) -> Result<Vec<PathBuf>, String> {
+    active.load_roots(&project_id)
+}
+
+#[tauri::command]
+pub async fn set_project_roots(
+    project_id: String,
+    roots: Vec<PathBuf>,
+    active: State<'_, ActiveProject>,
+    connections: State<'_, ConnectionManager>
+) -> Result<(), String> {
+    if active.set_roots(&project_id, &roots)? {
+        connections.notify_all(ROOTS_CHANGED_NOTIFICATION).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_project_servers(project_id: String, active: State<'_, ActiveProject>) -> Result<ProjectServers, String> {
+    active.load_servers(&project_id)
+}
+
+#[tauri::command]
+pub async fn set_project_servers(
+    app: AppHandle,
+    project_id: String,
+    servers: ProjectServers,
+    active: State<'_, ActiveProject>,
+    config: State<'_, Mutex<ConfigManager>>
+) -> Result<(), String> {
+    if active.set_servers(&project_id, servers)? {
+        apply_servers(&app, &active, &config).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::ServerOverride;
+
+    #[test]
+    fn test_roots_follow_the_active_project() {
+        let projects_dir = crate::mcp::store::temp_dir();
+        let workspace = crate::mcp::store::temp_dir();
+        let active = ActiveProject::new(projects_dir.clone());
+
+        assert!(active.set_roots("../escape", &[]).is_err());
+        assert!(active.set_roots("web", &[PathBuf::from("relative/dir")]).is_err());
+        assert!(!active.set_roots("web", &[workspace.clone(), workspace.clone()]).unwrap());
+        assert_eq!(load_roots(&projects_dir, "web").unwrap(), vec![workspace.clone()]);
+
+        assert!(active.activate(Some("web".to_string())).unwrap());
+        let roots = active.roots();
+        assert_eq!(roots.len(), 1);
+        assert!(roots[0].uri.starts_with("file:///") && roots[0].uri.ends_with('/'));
+        assert_eq!(roots[0].name.as_deref(), workspace.file_name().and_then(|n| n.to_str()));
+
+        // Editing the active project's roots is a change; another project's is not.
+        assert!(active.set_roots("web", &[]).unwrap());
+        assert!(!active.set_roots("docs", &[workspace]).unwrap());
+        assert!(active.roots().is_empty());
+
+        assert!(active.activate(Some("docs".to_string())).unwrap());
+        assert_eq!(active.project_id().as_deref(), Some("docs"));
+        assert!(active.activate(None).unwrap());
+    }
+
+    #[test]
+    fn test_server_settings_are_stored_per_project() {
+        let projects_dir = crate::mcp::store::temp_dir();
+        let active = ActiveProject::new(projects_dir.clone());
+        let ops = ProjectServers {
+            enabled: Some(vec!["k8s".to_string()]),
+            ..Default::default()
+        };
+
+        assert!(!active.set_servers("ops", ops.clone()).unwrap());
+        assert_eq!(active.servers(), None);
+
+        active.activate(Some("ops".to_string())).unwrap();
+        assert_eq!(active.servers(), Some(ops));
+        active.activate(Some("monorepo".to_string())).unwrap();
+        assert_eq!(active.servers(), Some(ProjectServers::default()));
+        assert!(active.set_servers("monorepo", ProjectServers::default()).unwrap());
+
+        fs::remove_dir_all(&projects_dir).unwrap();
+    }
+
+    #[test]
+    fn test_override_secrets_stay_out_of_the_project() {
+        let projects_dir = crate::mcp::store::temp_dir();
+        let secrets = Arc::new(SecretStore::new(crate::secrets::MemoryBackend::default()));
+        let active = ActiveProject::new(projects_dir.clone()).with_secrets(secrets.clone());
+        let overrides = |env: &[(&str, &str)]| ProjectServers {
+            overrides: [(
+                "k8s".to_string(),
+                ServerOverride {
+                    args: None,
+                    env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        active.set_servers("ops", overrides(&[("KUBE_TOKEN", "kt-1"), ("KUBE_NS", "ops")])).unwrap();
+        let content = fs::read_to_string(projects_dir.join("ops/servers.json")).unwrap();
+        assert!(!content.contains("kt-1"));
+        let stored = load_servers(&projects_dir, "ops").unwrap().overrides["k8s"].env.clone();
+        assert_eq!(stored["KUBE_TOKEN"], "secret://mcp/k8s/project/ops/env/KUBE_TOKEN");
+        assert_eq!(stored["KUBE_NS"], "ops");
+        assert_eq!(secrets.resolve(&stored["KUBE_TOKEN"]).unwrap(), "kt-1");
+
+        // Overrides may not point at secrets of the app or other servers.
+        for reference in ["secret://anthropic-api-key", "secret://mcp/github/token"] {
+            let error = active.set_servers("ops", overrides(&[("KUBE_TOKEN", reference)])).unwrap_err();
+            assert!(error.contains("not accessible"), "{}", error);
+        }
+
+        // Dropping the override drops its secret.
+        active.set_servers("ops", ProjectServers::default()).unwrap();
+        assert!(secrets.resolve(&stored["KUBE_TOKEN"]).is_err());
+
+        fs::remove_dir_all(&projects_dir).unwrap();
+    }
+}
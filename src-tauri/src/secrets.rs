@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use directories::ProjectDirs;
+use keyring::Entry;
+use thiserror::Error;
+
+use crate::mcp::store::write_private;
+
+const SERVICE_NAME: &str = "luke-desktop";
+const REFERENCE_PREFIX: &str = "secret://";
+
+/// Overrides backend detection: `keyring`, `file` or `memory`.
+pub const BACKEND_ENV: &str = "LUKE_SECRET_BACKEND";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+
+    #[error("Secret file error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Secret file is corrupt: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Secret not found: {0}")]
+    NotFound(String),
+
+    #[error("Secret not accessible here: {0}")]
+    Forbidden(String),
+}
+
+/// Where secrets are actually kept.
+pub trait SecretBackend: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError>;
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError>;
+    fn delete(&self, key: &str) -> Result<(), SecretError>;
+}
+
+/// The OS credential store (Keychain, Credential Manager, Secret Service).
+pub struct KeyringBackend;
+
+impl SecretBackend for KeyringBackend {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        match Entry::new(SERVICE_NAME, key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        Ok(Entry::new(SERVICE_NAME, key)?.set_password(value)?)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        match Entry::new(SERVICE_NAME, key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Process-local store for tests and throwaway sessions.
+#[derive(Default)]
+pub struct MemoryBackend(Mutex<HashMap<String, String>>);
+
+impl MemoryBackend {
+    fn map(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SecretBackend for MemoryBackend {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        Ok(self.map().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        self.map().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        self.map().remove(key);
+        Ok(())
+    }
+}
+
+/// A JSON file readable only by the current user. Used where no credential
+/// store is available, e.g. headless Linux without a Secret Service.
+pub struct FileBackend {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, String>, SecretError> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, secrets: &HashMap<String, String>) -> Result<(), SecretError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(write_private(&self.path, serde_json::to_string_pretty(secrets)?.as_bytes())?)
+    }
+}
+
+impl SecretBackend for FileBackend {
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.read()?.get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), value.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Secret storage shared by the Anthropic client and the MCP config.
+/// Config files only ever hold references of the form `secret://<key>`.
+pub struct SecretStore {
+    backend: Box<dyn SecretBackend>,
+}
+
+impl SecretStore {
+    pub fn new(backend: impl SecretBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// Uses the OS keyring when it works and falls back to a file in the
+    /// config directory otherwise. `LUKE_SECRET_BACKEND` forces a backend.
+    pub fn detect() -> Self {
+        let file_backend = || {
+            let path = ProjectDirs::from("com", "lukedesktop", "LukeDesktop")
+                .map(|dirs| dirs.config_dir().join("secrets.json"))
+                .unwrap_or_else(|| PathBuf::from("secrets.json"));
+            FileBackend::new(path)
+        };
+
+        match std::env::var(BACKEND_ENV).as_deref() {
+            Ok("memory") => return Self::new(MemoryBackend::default()),
+            Ok("file") => return Self::new(file_backend()),
+            Ok("keyring") => return Self::new(KeyringBackend),
+            _ => {}
+        }
+
+        match KeyringBackend.get("probe") {
+            Ok(_) => Self::new(KeyringBackend),
+            Err(e) => {
+                eprintln!("OS keyring unavailable ({}), storing secrets in a file", e);
+                Self::new(file_backend())
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        self.backend.get(key)
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        self.backend.set(key, value)
+    }
+
+    pub fn delete(&self, key: &str) -> Result<(), SecretError> {
+        self.backend.delete(key)
+    }
+
+    /// Stores `value` under `key` and returns the reference to put in config.
+    pub fn store(&self, key: &str, value: &str) -> Result<String, SecretError> {
+        self.set(key, value)?;
+        Ok(reference(key))
+    }
+
+    /// Returns the secret behind a reference, or the value itself if it is
+    /// not a reference.
+    pub fn resolve(&self, value: &str) -> Result<String, SecretError> {
+        match reference_key(value) {
+            Some(key) => self.get(key)?.ok_or_else(|| SecretError::NotFound(key.to_string())),
+            None => Ok(value.to_string()),
+        }
+    }
+}
+
+pub fn reference(key: &str) -> String {
+    format!("{}{}", REFERENCE_PREFIX, key)
+}
+
+pub fn reference_key(value: &str) -> Option<&str> {
+    value.strip_prefix(REFERENCE_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_resolve() {
+        let store = SecretStore::new(MemoryBackend::default());
+
+        let reference = store.store("mcp/github/token", "ghp_x").unwrap();
+        assert_eq!(reference, "secret://mcp/github/token");
+        assert_eq!(store.resolve(&reference).unwrap(), "ghp_x");
+        assert_eq!(store.resolve("plain").unwrap(), "plain");
+
+        store.delete("mcp/github/token").unwrap();
+        assert!(matches!(store.resolve(&reference), Err(SecretError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_file_backend() {
+        let path = std::env::temp_dir().join(format!("luke-secrets-{}.json", uuid::Uuid::new_v4()));
+        let backend = FileBackend::new(path.clone());
+        // A file left readable by others is replaced, not reused.
+        fs::write(&path, "{}").unwrap();
+
+        backend.set("a", "1").unwrap();
+        backend.set("b", "2").unwrap();
+        backend.delete("a").unwrap();
+
+        let reopened = FileBackend::new(path.clone());
+        assert_eq!(reopened.get("a").unwrap(), None);
+        assert_eq!(reopened.get("b").unwrap().as_deref(), Some("2"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
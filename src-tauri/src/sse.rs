@@ -0,0 +1,163 @@
+/// A single server-sent event as defined by the HTML living standard.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+}
+
+/// Incremental `text/event-stream` parser. Network chunks can be pushed as
+/// they arrive; complete events are returned once their terminating blank
+/// line has been seen.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    has_data: bool,
+    last_event_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// The id of the last event seen, including events without data, which
+    /// are never returned.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// The reconnection time last announced by the server, in milliseconds.
+    pub fn retry(&self) -> Option<u64> {
+        self.retry
+    }
+
+    /// Flushes a trailing event that was not terminated by a blank line.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            self.process_line(line.trim_end_matches('\r'));
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.current.event = Some(value.to_string()),
+            "data" => {
+                if self.has_data {
+                    self.current.data.push('\n');
+                }
+                self.current.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" => {
+                self.current.id = Some(value.to_string());
+                self.last_event_id = Some(value.to_string());
+            }
+            "retry" => {
+                self.current.retry = value.parse().ok();
+                self.retry = self.current.retry.or(self.retry);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        // Events without data are not dispatched.
+        let event = std::mem::take(&mut self.current);
+        self.has_data = false;
+        if event.data.is_empty() {
+            None
+        } else {
+            Some(event)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_split_chunks() {
+        let mut parser = SseParser::new();
+
+        assert!(parser.push(b"event: message_start\ndata: {\"a\"").is_empty());
+        let events = parser.push(b":1}\r\n\r\n: keep-alive\n\nid: 7\ndata: line one\ndata: line two\n\n");
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.as_deref(), Some("message_start"));
+        assert_eq!(events[0].data, "{\"a\":1}");
+        assert_eq!(events[1].id.as_deref(), Some("7"));
+        assert_eq!(events[1].data, "line one\nline two");
+    }
+
+    #[test]
+    fn test_events_without_data_are_not_dispatched() {
+        let mut parser = SseParser::new();
+
+        let events = parser.push(b"event: ping
+
+retry: 500
+id: 3
+data:
+
+data: x
+
+");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "x");
+        assert_eq!(parser.last_event_id(), Some("3"));
+        assert_eq!(parser.retry(), Some(500));
+    }
+
+    #[test]
+    fn test_multibyte_boundary() {
+        let mut parser = SseParser::new();
+        let bytes = "data: grüße\n\n".as_bytes();
+        let (head, tail) = bytes.split_at(9);
+
+        assert!(parser.push(head).is_empty());
+        assert_eq!(parser.push(tail)[0].data, "grüße");
+    }
+
+    #[test]
+    fn test_finish_flushes_unterminated_event() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: tail").is_empty());
+        assert_eq!(parser.finish().map(|e| e.data), Some("tail".to_string()));
+    }
+}
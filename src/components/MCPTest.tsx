@@ -1,5 +1,7 @@
-import React, { useState } from 'react';
+import React, { useCallback, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { useMCP, useMCPServer } from '../hooks';
+import { MCPServerOverview, MCPTrafficEntry } from '../lib/mcp/types';
 
 export function MCPTest() {
   const { 
@@ -17,6 +19,7 @@ export function MCPTest() {
     error: serverError,
   } = useMCPServer(defaultServer);
 
+  const [inspectedId, setInspectedId] = useState<string | null>(null);
   const [newServerUrl, setNewServerUrl] = useState('');
   const [newServerName, setNewServerName] = useState('');
 
@@ -95,6 +98,12 @@ export function MCPTest() {
               >
                 Set Default
               </button>
+              <button
+                onClick={() => setInspectedId(server.id)}
+                className="bg-gray-500 text-white px-3 py-1 rounded"
+              >
+                Inspect
+              </button>
               <button
                 onClick={() => removeServer(server.id)}
                 className="bg-red-500 text-white px-3 py-1 rounded"
@@ -116,6 +125,153 @@ export function MCPTest() {
           )}
         </div>
       )}
+
+      {inspectedId && <MCPInspector serverId={inspectedId} />}
+    </div>
+  );
+}
+
+const errorMessage = (err: unknown) => (err instanceof Error ? err.message : String(err));
+
+/** Talks to one server over an isolated inspector session. */
+function MCPInspector({ serverId }: { serverId: string }) {
+  const [overview, setOverview] = useState<MCPServerOverview | null>(null);
+  const [toolName, setToolName] = useState('');
+  const [toolArguments, setToolArguments] = useState('{}');
+  const [resourceUri, setResourceUri] = useState('');
+  const [result, setResult] = useState<unknown>(null);
+  const [traffic, setTraffic] = useState<MCPTrafficEntry[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  const run = async (action: () => Promise<void>) => {
+    setError(null);
+    try {
+      await action();
+    } catch (err) {
+      setError(errorMessage(err));
+    }
+  };
+
+  const refreshTraffic = useCallback(async () => {
+    setTraffic(await invoke<MCPTrafficEntry[]>('get_inspector_traffic', { id: serverId }));
+  }, [serverId]);
+
+  useEffect(() => {
+    setOverview(null);
+    setResult(null);
+    refreshTraffic().catch(() => undefined);
+    const timer = setInterval(() => refreshTraffic().catch(() => undefined), 1000);
+    return () => clearInterval(timer);
+  }, [refreshTraffic]);
+
+  const connect = () => run(async () => {
+    setOverview(await invoke<MCPServerOverview>('inspector_connect', { id: serverId }));
+  });
+
+  const disconnect = () => run(async () => {
+    await invoke('inspector_disconnect', { id: serverId });
+    setOverview(null);
+  });
+
+  const callTool = () => run(async () => {
+    setResult(await invoke('inspector_call_tool', { id: serverId, name: toolName, arguments: toolArguments }));
+  });
+
+  const readResource = () => run(async () => {
+    setResult(await invoke('inspector_read_resource', { id: serverId, uri: resourceUri }));
+  });
+
+  const clearTraffic = () => run(async () => {
+    await invoke('clear_inspector_traffic', { id: serverId });
+    setTraffic([]);
+  });
+
+  return (
+    <div className="mt-4 p-4 border rounded space-y-4">
+      <div className="flex items-center justify-between">
+        <h3 className="font-bold">Inspector: {overview?.server.serverInfo.name ?? serverId}</h3>
+        <div className="space-x-2">
+          <button onClick={connect} className="bg-blue-500 text-white px-3 py-1 rounded">
+            {overview ? 'Reconnect' : 'Connect'}
+          </button>
+          <button onClick={disconnect} className="bg-gray-500 text-white px-3 py-1 rounded" disabled={!overview}>
+            Disconnect
+          </button>
+        </div>
+      </div>
+
+      {error && <div className="bg-red-100 border border-red-400 text-red-700 px-4 py-2 rounded">{error}</div>}
+
+      {overview && (
+        <>
+          <div>
+            <h4 className="font-semibold">Capabilities</h4>
+            <pre className="text-xs bg-gray-100 p-2 rounded overflow-auto">
+              {JSON.stringify(overview.server, null, 2)}
+            </pre>
+          </div>
+
+          <div className="space-y-2">
+            <h4 className="font-semibold">Call a tool</h4>
+            <select value={toolName} onChange={(e) => setToolName(e.target.value)} className="border p-2 rounded w-full">
+              <option value="">Select a tool</option>
+              {overview.tools.map((tool) => (
+                <option key={tool.name} value={tool.name}>{tool.name}</option>
+              ))}
+            </select>
+            <textarea
+              value={toolArguments}
+              onChange={(e) => setToolArguments(e.target.value)}
+              rows={4}
+              className="border p-2 rounded w-full font-mono text-xs"
+            />
+            <button onClick={callTool} className="bg-blue-500 text-white px-3 py-1 rounded" disabled={!toolName}>
+              Call
+            </button>
+          </div>
+
+          <div className="space-y-2">
+            <h4 className="font-semibold">Read a resource</h4>
+            <input
+              list="inspector-resources"
+              value={resourceUri}
+              onChange={(e) => setResourceUri(e.target.value)}
+              placeholder="Resource URI"
+              className="border p-2 rounded w-full"
+            />
+            <datalist id="inspector-resources">
+              {overview.resources.map((resource) => (
+                <option key={resource.uri} value={resource.uri}>{resource.name}</option>
+              ))}
+            </datalist>
+            <button onClick={readResource} className="bg-blue-500 text-white px-3 py-1 rounded" disabled={!resourceUri}>
+              Read
+            </button>
+          </div>
+        </>
+      )}
+
+      {result !== null && (
+        <div>
+          <h4 className="font-semibold">Result</h4>
+          <pre className="text-xs bg-gray-100 p-2 rounded overflow-auto">{JSON.stringify(result, null, 2)}</pre>
+        </div>
+      )}
+
+      <div>
+        <div className="flex items-center justify-between">
+          <h4 className="font-semibold">Traffic</h4>
+          <button onClick={clearTraffic} className="text-sm text-gray-600">Clear</button>
+        </div>
+        <div className="max-h-96 overflow-auto text-xs font-mono">
+          {traffic.map((entry, index) => (
+            <div key={index} className={entry.direction === 'sent' ? 'text-blue-700' : 'text-green-700'}>
+              <span className="text-gray-500">{entry.timestamp}</span>{' '}
+              {entry.direction === 'sent' ? '→' : '←'} {JSON.stringify(entry.message)}
+            </div>
+          ))}
+        </div>
+      </div>
     </div>
   );
-}
\ No newline at end of file
+}
@@ -1,4 +1,5 @@
-import Anthropic from '@anthropic-ai/sdk';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import {
   ClaudeConfig,
   ClaudeMessage,
@@ -6,18 +7,17 @@ import {
   ClaudeError,
   ClaudeStreamCallbacks,
   ClaudeRequestOptions,
+  MessagesResponse,
+  StreamPayload,
 } from './types';
 
+const STREAM_EVENT = 'anthropic-stream';
+
 export class ClaudeClient {
-  private client: Anthropic;
   private config: ClaudeConfig;
 
   constructor(config: ClaudeConfig) {
     this.config = config;
-
-    this.client = new Anthropic({
-      apiKey: config.apiKey,
-    });
   }
 
   private async delay(ms: number): Promise<void> {
@@ -65,119 +65,145 @@ export class ClaudeClient {
     } = options;
 
     const formattedMessages = this.formatMessages(messages);
-    
-    // Add system prompt as first message if provided
-    const allMessages = systemPrompt 
-      ? [{ role: 'user' as const, content: systemPrompt }, ...formattedMessages]
-      : formattedMessages;
 
     try {
       const response = await this.retryWithBackoff(
         async () => {
-          const completion = await this.client.messages.create({
-            model: this.config.model,
-            messages: allMessages,
-            max_tokens: maxTokens,
-            temperature,
-            stop_sequences: stopSequences,
+          const completion = await invoke<MessagesResponse>('send_message', {
+            request: {
+              model: this.config.model,
+              messages: formattedMessages,
+              max_tokens: maxTokens,
+              system: systemPrompt,
+              temperature,
+              stop_sequences: stopSequences,
+            },
           });
 
           return {
             id: completion.id,
             model: completion.model,
-            response: completion.content[0]?.type === 'text' ? completion.content[0].text : '',
+            response: completion.content
+              .filter(block => block.type === 'text')
+              .map(block => block.text)
+              .join(''),
             created_at: Date.now(),
-            stop_reason: completion.stop_reason || null,
-            stop_sequence: completion.stop_sequence || null,
-            usage: {
-              input_tokens: completion.usage.input_tokens,
-              output_tokens: completion.usage.output_tokens,
-            },
+            stop_reason: completion.stop_reason,
+            stop_sequence: completion.stop_sequence,
+            usage: completion.usage,
           };
         }
       );
 
       return response;
     } catch (error) {
-      const claudeError = error as ClaudeError;
-      if (error instanceof Error) {
-        claudeError.message = error.message;
-      }
+      const claudeError = (error instanceof Error ? error : new Error(String(error))) as ClaudeError;
       throw claudeError;
     }
   }
 
+  /**
+   * Starts a streaming request in the backend and forwards its events to the
+   * callbacks. Pass `options.requestId` to be able to cancel the stream via
+   * `cancelStream` while it is running.
+   */
   async streamMessage(
     messages: ClaudeMessage[],
     callbacks: ClaudeStreamCallbacks,
     options: ClaudeRequestOptions = {}
-  ): Promise<void> {
+  ): Promise<string> {
     const {
       temperature = 0.7,
       maxTokens = 4096,
       stopSequences,
       systemPrompt,
+      requestId = crypto.randomUUID(),
     } = options;
 
-    const formattedMessages = this.formatMessages(messages);
-    
-    // Add system prompt as first message if provided
-    const allMessages = systemPrompt 
-      ? [{ role: 'user' as const, content: systemPrompt }, ...formattedMessages]
-      : formattedMessages;
-
-    try {
-      await this.retryWithBackoff(
-        async () => {
-          const stream = await this.client.messages.create({
-            model: this.config.model,
-            messages: allMessages,
-            max_tokens: maxTokens,
-            temperature,
-            stop_sequences: stopSequences,
-            stream: true,
-          });
+    let settle!: { resolve: () => void; reject: (error: ClaudeError) => void };
+    const finished = new Promise<void>((resolve, reject) => {
+      settle = { resolve, reject };
+    });
 
-          let fullContent = '';
-          let usage = {
-            input_tokens: 0,
-            output_tokens: 0,
-          };
+    const unlisten = await listen<StreamPayload>(STREAM_EVENT, ({ payload }) => {
+      if (payload.request_id !== requestId) {
+        return;
+      }
 
-          for await (const chunk of stream) {
-            if (chunk.type === 'message_delta') {
-              const content = (chunk.delta as any)?.text || '';
-              fullContent += content;
-              
-              if (callbacks.onMessageDelta) {
-                callbacks.onMessageDelta(chunk);
-              }
-            }
-          }
-
-          if (callbacks.onMessageStop) {
-            callbacks.onMessageStop({
-              id: crypto.randomUUID(),
-              model: this.config.model,
-              response: fullContent,
-              created_at: Date.now(),
-              stop_reason: null,
-              stop_sequence: null,
-              usage,
-            });
-          }
+      switch (payload.kind) {
+        case 'event':
+          this.dispatchStreamEvent(payload.event, callbacks);
+          break;
+        case 'done':
+          callbacks.onMessageStop?.({
+            id: payload.message.id,
+            model: payload.message.model,
+            response: payload.message.content
+              .filter(block => block.type === 'text')
+              .map(block => block.text)
+              .join(''),
+            created_at: Date.now(),
+            stop_reason: payload.message.stop_reason,
+            stop_sequence: payload.message.stop_sequence,
+            usage: payload.message.usage,
+          } satisfies ClaudeCompletion);
+          settle.resolve();
+          break;
+        case 'cancelled':
+          settle.resolve();
+          break;
+        case 'error': {
+          const error = new Error(payload.message) as ClaudeError;
+          callbacks.onError?.(error);
+          settle.reject(error);
+          break;
         }
-      );
-    } catch (error) {
-      const claudeError = error as ClaudeError;
-      if (error instanceof Error) {
-        claudeError.message = error.message;
-      }
-      
-      if (callbacks.onError) {
-        callbacks.onError(claudeError);
       }
+    });
+
+    try {
+      await invoke('stream_message', {
+        requestId,
+        request: {
+          model: this.config.model,
+          messages: this.formatMessages(messages),
+          max_tokens: maxTokens,
+          system: systemPrompt,
+          temperature,
+          stop_sequences: stopSequences,
+        },
+      });
+      await finished;
+      return requestId;
+    } catch (error) {
+      const claudeError = (error instanceof Error ? error : new Error(String(error))) as ClaudeError;
       throw claudeError;
+    } finally {
+      unlisten();
+    }
+  }
+
+  async cancelStream(requestId: string): Promise<void> {
+    await invoke('cancel_stream', { requestId });
+  }
+
+  private dispatchStreamEvent(event: any, callbacks: ClaudeStreamCallbacks): void {
+    switch (event.type) {
+      case 'message_start':
+        callbacks.onMessageStart?.(event);
+        break;
+      case 'content_block_start':
+        callbacks.onContentBlockStart?.(event);
+        break;
+      case 'content_block_delta':
+        callbacks.onContentBlockDelta?.(event);
+        break;
+      case 'content_block_stop':
+        callbacks.onContentBlockStop?.(event);
+        break;
+      case 'message_delta':
+        callbacks.onMessageDelta?.(event);
+        break;
     }
   }
-}
\ No newline at end of file
+}
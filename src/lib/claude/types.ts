@@ -1,5 +1,6 @@
 export interface ClaudeConfig {
-  apiKey: string;
+  /** @deprecated The API key is stored in and used by the backend. */
+  apiKey?: string;
   model: string;
   organization?: string;
 }
@@ -25,6 +26,28 @@ export interface ClaudeCompletion {
   };
 }
 
+// Mirrors `anthropic::types::MessagesResponse` in the Rust backend.
+export interface MessagesResponse {
+  id: string;
+  model: string;
+  role: 'assistant';
+  content: { type: 'text'; text: string }[];
+  stop_reason: string | null;
+  stop_sequence: string | null;
+  usage: {
+    input_tokens: number;
+    output_tokens: number;
+  };
+}
+
+// Mirrors `anthropic::commands::StreamPayload` in the Rust backend.
+export type StreamPayload = { request_id: string } & (
+  | { kind: 'event'; event: { type: string; [key: string]: any } }
+  | { kind: 'done'; message: MessagesResponse }
+  | { kind: 'error'; message: string }
+  | { kind: 'cancelled' }
+);
+
 export interface ClaudeStreamCallbacks {
   onMessageStart?: (data: any) => void;
   onMessageDelta?: (data: any) => void;
@@ -49,4 +72,5 @@ export interface ClaudeRequestOptions {
   systemPrompt?: string;
   maxRetries?: number;
   retryDelay?: number;
+  requestId?: string;
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
-import { MCPServer, MCPResponse, MCPError, MCPConnectionStatus } from './types';
+import { invoke } from '@tauri-apps/api/core';
+import { MCPServer, MCPResponse, MCPError, MCPConnectionStatus, MCPServerStatus } from './types';
 
 export class MCPClient {
   private server: MCPServer;
@@ -14,32 +15,35 @@ export class MCPClient {
 
   async connect(): Promise<MCPConnectionStatus> {
     try {
-      const response = await fetch(`${this.server.url}/health`, {
-        method: 'GET',
-        headers: this.getHeaders(),
-      });
-
-      if (!response.ok) {
-        throw new Error(`Server returned ${response.status}`);
-      }
+      await invoke('start_mcp_server', { id: this.server.id });
+    } catch {
+      // The backend records the failure; it shows up in the status below.
+    }
+    return this.refreshStatus();
+  }
 
+  /** Reads the health the backend monitor tracks for this server. */
+  async refreshStatus(): Promise<MCPConnectionStatus> {
+    try {
+      const [status] = await invoke<MCPServerStatus[]>('get_mcp_server_status', { id: this.server.id });
       this.status = {
-        connected: true,
-        lastChecked: new Date().toISOString()
+        connected: status.state === 'ready' || status.state === 'degraded',
+        state: status.state,
+        latencyMs: status.latency_ms ?? undefined,
+        error: status.last_error ? { code: 'CONNECTION_ERROR', message: status.last_error } : undefined,
+        lastChecked: status.last_checked ?? new Date().toISOString()
       };
-
-      return this.status;
     } catch (error) {
       this.status = {
         connected: false,
         error: {
           code: 'CONNECTION_ERROR',
-          message: error instanceof Error ? error.message : 'Unknown error occurred',
+          message: error instanceof Error ? error.message : String(error),
         },
         lastChecked: new Date().toISOString()
       };
-      return this.status;
     }
+    return this.status;
   }
 
   private getHeaders(): Headers {
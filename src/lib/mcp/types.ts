@@ -25,8 +25,42 @@ export interface MCPError {
   details?: any;
 }
 
+export type MCPServerState = 'connecting' | 'ready' | 'degraded' | 'failed' | 'sign_in_required' | 'disabled';
+
+/** Payload of `get_mcp_server_status` and the `mcp-server-status` event. */
+export interface MCPServerStatus {
+  server_id: string;
+  state: MCPServerState;
+  latency_ms: number | null;
+  last_error: string | null;
+  last_checked: string | null;
+  last_connected: string | null;
+}
+
 export interface MCPConnectionStatus {
   connected: boolean;
+  state?: MCPServerState;
+  latencyMs?: number;
   error?: MCPError;
   lastChecked: string;
-}
\ No newline at end of file
+}
+/** Payload of `inspector_connect` and `inspector_list_capabilities`. */
+export interface MCPServerOverview {
+  server: {
+    protocolVersion: string;
+    capabilities: Record<string, unknown>;
+    serverInfo: { name: string; version: string };
+    instructions?: string;
+  };
+  tools: { name: string; description?: string; inputSchema: unknown }[];
+  resources: { uri: string; name: string; description?: string; mimeType?: string }[];
+  resource_templates: { uriTemplate: string; name: string; description?: string }[];
+  prompts: { name: string; description?: string }[];
+}
+
+/** One raw JSON-RPC message from `get_inspector_traffic`. */
+export interface MCPTrafficEntry {
+  timestamp: string;
+  direction: 'sent' | 'received';
+  message: any;
+}